@@ -0,0 +1,280 @@
+use std::io::{self, BufRead, Read};
+
+use crate::client::{read_line, ClientError, MAX_HEADERS};
+
+/// Reads the whole body, however the server chose to frame it.
+pub(crate) fn read<R>(
+    reader: &mut R,
+    chunked: bool,
+    length: Option<usize>,
+    limit: usize,
+) -> Result<Vec<u8>, ClientError>
+where
+    R: BufRead,
+{
+    if chunked {
+        return read_chunked(reader, limit);
+    }
+
+    match length {
+        Some(length) if length > limit => Err(ClientError::TooLarge(limit)),
+        Some(length) => {
+            let mut body = vec![0; length];
+
+            reader.read_exact(&mut body)?;
+
+            Ok(body)
+        }
+        None => {
+            let mut body = Vec::new();
+
+            // one more than the limit, to tell a body that fits exactly from one that doesn't
+            //
+            // the connection closing is the only thing that says the body is done, so one that closes
+            // without saying goodbye over tls could have been cut short and isn't let through
+            reader.take(limit as u64 + 1).read_to_end(&mut body)?;
+
+            if body.len() > limit {
+                return Err(ClientError::TooLarge(limit));
+            }
+
+            Ok(body)
+        }
+    }
+}
+
+fn read_chunked<R>(reader: &mut R, limit: usize) -> Result<Vec<u8>, ClientError>
+where
+    R: BufRead,
+{
+    let mut body = Vec::new();
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        read_line(reader, &mut line)?;
+
+        // chunk extensions come after a `;`, nothing uses them
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| ClientError::InvalidResponse("invalid chunk size"))?;
+
+        if size == 0 {
+            break;
+        }
+
+        let end = match body.len().checked_add(size) {
+            Some(end) if end <= limit => end,
+            _ => return Err(ClientError::TooLarge(limit)),
+        };
+
+        let start = body.len();
+        body.resize(end, 0);
+        reader.read_exact(&mut body[start..])?;
+
+        line.clear();
+        read_line(reader, &mut line)?;
+    }
+
+    // skip over any trailers until the blank line that ends the body
+    for _ in 0..=MAX_HEADERS {
+        line.clear();
+
+        match read_line(reader, &mut line) {
+            Ok(0) => return Ok(body),
+            Ok(_) if line.trim().is_empty() => return Ok(body),
+            Ok(_) => {}
+            // the last chunk has been read, servers that close without saying goodbye over tls can't
+            // have cut anything short
+            Err(ClientError::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(body)
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(ClientError::InvalidResponse("too many trailers"))
+}
+
+/// Undoes the `Content-Encoding`, for the ones the client asks for.
+pub(crate) fn decode(
+    encoding: Option<&str>,
+    body: Vec<u8>,
+    limit: usize,
+) -> Result<Vec<u8>, ClientError> {
+    let encoding = match encoding.map(str::trim) {
+        None | Some("") => return Ok(body),
+        Some(encoding) => encoding.to_ascii_lowercase(),
+    };
+
+    let inflate = |data: &[u8], zlib: bool| {
+        let result = if zlib {
+            miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, limit)
+        } else {
+            miniz_oxide::inflate::decompress_to_vec_with_limit(data, limit)
+        };
+
+        result.map_err(|err| match err.status {
+            miniz_oxide::inflate::TINFLStatus::HasMoreOutput => ClientError::TooLarge(limit),
+            _ => ClientError::InvalidResponse("unable to decompress body"),
+        })
+    };
+
+    match encoding.as_str() {
+        "identity" => Ok(body),
+        "gzip" | "x-gzip" => inflate(gzip_data(&body)?, false),
+        // some servers send raw deflate rather than the zlib wrapped data the spec asks for
+        "deflate" => match inflate(&body, true) {
+            Err(ClientError::InvalidResponse(_)) => inflate(&body, false),
+            result => result,
+        },
+        _ => Err(ClientError::InvalidResponse("unsupported content encoding")),
+    }
+}
+
+/// Skips the gzip header, leaving the deflate data and the trailer.
+fn gzip_data(body: &[u8]) -> Result<&[u8], ClientError> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    let invalid = || ClientError::InvalidResponse("invalid gzip header");
+
+    if body.len() < 18 || body[0..3] != [0x1f, 0x8b, 0x08] {
+        return Err(invalid());
+    }
+
+    let flags = body[3];
+    let mut offset = 10;
+
+    if flags & FEXTRA != 0 {
+        let len = body.get(offset..offset + 2).ok_or_else(invalid)?;
+
+        offset += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    }
+
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let end = body
+                .get(offset..)
+                .and_then(|rest| rest.iter().position(|b| *b == 0))
+                .ok_or_else(invalid)?;
+
+            offset += end + 1;
+        }
+    }
+
+    if flags & FHCRC != 0 {
+        offset += 2;
+    }
+
+    // the last eight bytes are the crc and size, inflating stops before them anyway
+    body.get(offset..body.len() - 8).ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_chunked() {
+        let raw = b"5\r\nHello\r\n7;ext=1\r\n, World\r\n0\r\nTrailer: 1\r\n\r\n";
+
+        let body = read(&mut &raw[..], true, None, 1024).unwrap();
+
+        assert_eq!(b"Hello, World", &body[..]);
+
+        assert!(matches!(
+            read(&mut &raw[..], true, None, 8),
+            Err(ClientError::TooLarge(8))
+        ));
+
+        let raw = b"5\r\nHello\r\nffffffffffffffff\r\n, World\r\n0\r\n\r\n";
+
+        assert!(matches!(
+            read(&mut &raw[..], true, None, usize::MAX),
+            Err(ClientError::TooLarge(usize::MAX))
+        ));
+    }
+
+    #[test]
+    fn test_read_chunked_long_lines() {
+        let mut raw = b"5".to_vec();
+        raw.resize(64 * 1024, b' ');
+        raw.extend_from_slice(b"\r\nHello\r\n0\r\n\r\n");
+
+        assert!(matches!(
+            read(&mut &raw[..], true, None, 1024),
+            Err(ClientError::InvalidResponse(_))
+        ));
+
+        let mut raw = b"5\r\nHello\r\n0\r\n".to_vec();
+        raw.extend(b"Trailer: 1\r\n".repeat(1000));
+        raw.extend_from_slice(b"\r\n");
+
+        assert!(matches!(
+            read(&mut &raw[..], true, None, 1024),
+            Err(ClientError::InvalidResponse(_))
+        ));
+    }
+
+    /// Cuts the connection off like a tls server that closes without a `close_notify`.
+    struct Truncated<'a>(&'a [u8]);
+
+    impl Read for Truncated<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_read_truncated() {
+        let mut reader = io::BufReader::new(Truncated(b"5\r\nHello\r\n0\r\n"));
+        let body = read(&mut reader, true, None, 1024).unwrap();
+
+        assert_eq!(b"Hello", &body[..]);
+
+        let mut reader = io::BufReader::new(Truncated(b"Hello"));
+        let body = read(&mut reader, false, Some(5), 1024).unwrap();
+
+        assert_eq!(b"Hello", &body[..]);
+
+        let mut reader = io::BufReader::new(Truncated(b"Hello"));
+
+        assert!(matches!(
+            read(&mut reader, false, None, 1024),
+            Err(ClientError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode() {
+        let text = b"Hello, World! Hello, World! Hello, World!";
+
+        let raw = miniz_oxide::deflate::compress_to_vec(text, 6);
+        let zlib = miniz_oxide::deflate::compress_to_vec_zlib(text, 6);
+
+        let mut gzip = vec![0x1f, 0x8b, 0x08, 0x08, 0, 0, 0, 0, 0, 0xff];
+        gzip.extend_from_slice(b"hello.txt\0");
+        gzip.extend_from_slice(&raw);
+        gzip.extend_from_slice(&[0; 8]);
+
+        assert_eq!(&text[..], &decode(Some("gzip"), gzip, 1024).unwrap()[..]);
+        assert_eq!(&text[..], &decode(Some("deflate"), zlib, 1024).unwrap()[..]);
+        assert_eq!(
+            &text[..],
+            &decode(Some("deflate"), raw.clone(), 1024).unwrap()[..]
+        );
+
+        assert!(matches!(
+            decode(Some("deflate"), raw, 8),
+            Err(ClientError::TooLarge(8))
+        ));
+        assert!(decode(Some("br"), text.to_vec(), 1024).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    convert::TryFrom as _,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use crate::client::{url::Url, Client, ClientError};
+
+/// A connection to the server, which gives up on reads and writes once the request's deadline has passed.
+pub(crate) struct Connection {
+    stream: Stream,
+    timeout: Duration,
+    deadline: Instant,
+}
+
+enum Stream {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+}
+
+impl Connection {
+    pub(crate) fn open(client: &Client, url: &Url, deadline: Instant) -> Result<Self, ClientError> {
+        #[cfg(not(feature = "tls"))]
+        if url.https {
+            return Err(ClientError::UnsupportedScheme(url.to_string()));
+        }
+
+        let timeout = client.timeout;
+
+        let left = deadline.saturating_duration_since(Instant::now());
+
+        if left.is_zero() {
+            return Err(timed_out().into());
+        }
+
+        let stream = Self::connect(url, timeout.min(left))?;
+
+        #[cfg(feature = "tls")]
+        if url.https {
+            let name = rustls::pki_types::ServerName::try_from(url.host.clone())
+                .map_err(|_| ClientError::InvalidUrl(url.to_string()))?;
+
+            let conn = rustls::ClientConnection::new(client.tls.clone(), name)
+                .map_err(|err| ClientError::Tls(err.to_string()))?;
+
+            return Ok(Connection {
+                stream: Stream::Tls(Box::new(rustls::StreamOwned::new(conn, stream))),
+                timeout,
+                deadline,
+            });
+        }
+
+        Ok(Connection {
+            stream: Stream::Plain(stream),
+            timeout,
+            deadline,
+        })
+    }
+
+    /// Tries each address the host resolves to, as some of them may not be reachable (IPv6 usually).
+    fn connect(url: &Url, timeout: Duration) -> Result<TcpStream, ClientError> {
+        let mut last = None;
+
+        for addr in (url.host.as_str(), url.port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last = Some(err),
+            }
+        }
+
+        Err(last
+            .map(ClientError::from)
+            .unwrap_or_else(|| ClientError::InvalidUrl(url.to_string())))
+    }
+
+    /// Sets the socket's timeouts to whichever is sooner, the client's timeout or the deadline.
+    ///
+    /// The client's timeout on its own lets a server that sends a byte every so often hold the request open
+    /// forever.
+    fn arm(&self) -> io::Result<()> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+
+        if left.is_zero() {
+            return Err(timed_out());
+        }
+
+        let stream = match &self.stream {
+            Stream::Plain(stream) => stream,
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => &stream.sock,
+        };
+
+        stream.set_read_timeout(Some(self.timeout.min(left)))?;
+        stream.set_write_timeout(Some(self.timeout.min(left)))?;
+
+        Ok(())
+    }
+
+    /// A read or write cut short by [`Connection::arm`] shortening the socket's timeouts is the deadline
+    /// passing, not the server going quiet.
+    fn expired(&self, result: io::Result<usize>) -> io::Result<usize> {
+        match result {
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) && Instant::now() >= self.deadline =>
+            {
+                Err(timed_out())
+            }
+            result => result,
+        }
+    }
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "request took too long")
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.arm()?;
+
+        let read = match &mut self.stream {
+            Stream::Plain(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.read(buf),
+        };
+
+        self.expired(read)
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.arm()?;
+
+        let written = match &mut self.stream {
+            Stream::Plain(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.write(buf),
+        };
+
+        self.expired(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.arm()?;
+
+        match &mut self.stream {
+            Stream::Plain(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
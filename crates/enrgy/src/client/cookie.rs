@@ -0,0 +1,282 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::client::url::Url;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cookie {
+    name: String,
+    value: String,
+    domain: String,
+    /// Set without a `Domain`, so it only goes back to the exact host and not its subdomains.
+    host_only: bool,
+    path: String,
+    secure: bool,
+    expires: Option<SystemTime>,
+}
+
+impl Cookie {
+    fn parse(url: &Url, header: &str, now: SystemTime) -> Option<Self> {
+        let mut parts = header.split(';');
+
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.trim().trim_matches('"').to_string(),
+            domain: url.host.clone(),
+            host_only: true,
+            path: default_path(url.path()),
+            secure: false,
+            expires: None,
+        };
+
+        let mut max_age = None;
+
+        for part in parts {
+            let (key, value) = part.split_once('=').unwrap_or((part, ""));
+            let (key, value) = (key.trim(), value.trim());
+
+            if key.eq_ignore_ascii_case("domain") && !value.is_empty() {
+                let domain = value.trim_start_matches('.').to_ascii_lowercase();
+
+                // a site can't set cookies for another one
+                if !domain_matches(&url.host, &domain) {
+                    return None;
+                }
+
+                cookie.domain = domain;
+                cookie.host_only = false;
+            } else if key.eq_ignore_ascii_case("path") && value.starts_with('/') {
+                cookie.path = value.to_string();
+            } else if key.eq_ignore_ascii_case("secure") {
+                cookie.secure = true;
+            } else if key.eq_ignore_ascii_case("max-age") {
+                max_age = value.parse::<i64>().ok();
+            } else if key.eq_ignore_ascii_case("expires") {
+                cookie.expires = parse_date(value);
+            }
+        }
+
+        // `Max-Age` wins over `Expires` when both are there
+        if let Some(max_age) = max_age {
+            cookie.expires = Some(if max_age <= 0 {
+                UNIX_EPOCH
+            } else {
+                now + Duration::from_secs(max_age as u64)
+            });
+        }
+
+        Some(cookie)
+    }
+
+    fn expired(&self, now: SystemTime) -> bool {
+        self.expires.map(|expires| expires <= now).unwrap_or(false)
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let domain = if self.host_only {
+            url.host == self.domain
+        } else {
+            domain_matches(&url.host, &self.domain)
+        };
+
+        domain && path_matches(url.path(), &self.path) && (url.https || !self.secure)
+    }
+}
+
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain || (host.ends_with(domain) && host[..host.len() - domain.len()].ends_with('.'))
+}
+
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/')))
+}
+
+/// The directory of the request's path, what a cookie without a `Path` is scoped to.
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => path[..i].to_string(),
+    }
+}
+
+/// Parses the `Thu, 01 Jan 1970 00:00:00 GMT` form of an HTTP date, the only one still in use.
+fn parse_date(value: &str) -> Option<SystemTime> {
+    let mut parts = value.split_whitespace().skip(1);
+
+    let day = parts.next()?.parse::<u64>().ok()?;
+    let month = match parts.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year = parts.next()?.parse::<u64>().ok()?;
+
+    let mut time = parts
+        .next()?
+        .split(':')
+        .map(|part| part.parse::<u64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+
+    if year < 1970 || day == 0 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // days since the epoch, from Howard Hinnant's `days_from_civil`
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let yoe = y % 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = (era * 146_097 + doe).checked_sub(719_468)?;
+
+    Some(UNIX_EPOCH + Duration::from_secs(days * 86_400 + hour * 3_600 + minute * 60 + second))
+}
+
+/// Keeps the cookies sites set, and gives them back on later requests.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a `Set-Cookie` header from a response to the URL.
+    pub fn store(&mut self, url: &Url, header: &str) {
+        let now = SystemTime::now();
+
+        let cookie = match Cookie::parse(url, header, now) {
+            Some(cookie) => cookie,
+            None => return,
+        };
+
+        self.cookies.retain(|other| {
+            !(other.name == cookie.name
+                && other.domain == cookie.domain
+                && other.path == cookie.path)
+        });
+
+        // an expiry in the past is how a site removes a cookie, so there's nothing to keep
+        if !cookie.expired(now) {
+            self.cookies.push(cookie);
+        }
+    }
+
+    /// The `Cookie` header for a request to the URL, if there are any to send.
+    pub fn header(&mut self, url: &Url) -> Option<String> {
+        let now = SystemTime::now();
+
+        self.cookies.retain(|cookie| !cookie.expired(now));
+
+        let mut cookies = self
+            .cookies
+            .iter()
+            .filter(|cookie| cookie.matches(url))
+            .collect::<Vec<_>>();
+
+        if cookies.is_empty() {
+            return None;
+        }
+
+        // more specific paths go first
+        cookies.sort_by(|a, b| b.path.len().cmp(&a.path.len()));
+
+        Some(
+            cookies
+                .iter()
+                .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+
+    pub fn clear(&mut self) {
+        self.cookies.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_jar() {
+        let mut jar = CookieJar::new();
+
+        let login = Url::parse("https://archiveofourown.org/users/login").unwrap();
+
+        jar.store(&login, "_otwarchive_session=abc; path=/; secure; HttpOnly");
+        jar.store(
+            &login,
+            "user_credentials=1; domain=.archiveofourown.org; path=/",
+        );
+        jar.store(&login, "scoped=1");
+        jar.store(&login, "tracker=1; domain=example.com");
+
+        let work = Url::parse("https://archiveofourown.org/works/1").unwrap();
+
+        assert_eq!(
+            Some("_otwarchive_session=abc; user_credentials=1".to_string()),
+            jar.header(&work)
+        );
+
+        let insecure = Url::parse("http://archiveofourown.org/users/x").unwrap();
+
+        assert_eq!(
+            Some("scoped=1; user_credentials=1".to_string()),
+            jar.header(&insecure)
+        );
+
+        let subdomain = Url::parse("https://download.archiveofourown.org/users/x").unwrap();
+
+        assert_eq!(
+            Some("user_credentials=1".to_string()),
+            jar.header(&subdomain)
+        );
+
+        jar.store(
+            &login,
+            "user_credentials=; domain=archiveofourown.org; path=/; expires=Thu, 01 Jan 1970 00:00:00 GMT",
+        );
+        jar.store(&login, "_otwarchive_session=; path=/; max-age=0");
+
+        assert_eq!(None, jar.header(&work));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            Some(UNIX_EPOCH),
+            parse_date("Thu, 01 Jan 1970 00:00:00 GMT")
+        );
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(1_445_412_480)),
+            parse_date("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+        assert_eq!(None, parse_date("tomorrow"));
+    }
+}
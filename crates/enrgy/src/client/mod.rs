@@ -0,0 +1,359 @@
+//! A small blocking HTTP/1.1 client, enough to download pages and files.
+//!
+//! Every request gets its own connection, which is closed once the response is read.
+//! HTTPS needs the `tls` feature.
+
+mod body;
+mod conn;
+mod cookie;
+mod url;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+use std::{
+    error, fmt,
+    io::{self, BufRead, BufReader, Read as _, Write},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::http::StatusCode;
+
+use self::conn::Connection;
+
+#[doc(inline)]
+pub use self::{cookie::CookieJar, url::Url};
+
+/// The longest a status, header or chunk size line can be.
+const MAX_LINE: usize = 8 * 1024;
+
+/// The most headers, or trailers, a response can have.
+pub(crate) const MAX_HEADERS: usize = 100;
+
+#[derive(Debug)]
+pub enum ClientError {
+    InvalidUrl(String),
+    UnsupportedScheme(String),
+    InvalidResponse(&'static str),
+    /// The body is bigger than the client's limit, which is given.
+    TooLarge(usize),
+    TooManyRedirects(usize),
+    Tls(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::InvalidUrl(url) => write!(f, "invalid url `{}`", url),
+            ClientError::UnsupportedScheme(url) => write!(f, "unsupported url scheme `{}`", url),
+            ClientError::InvalidResponse(reason) => write!(f, "invalid response: {}", reason),
+            ClientError::TooLarge(limit) => {
+                write!(f, "response is larger than the limit of {} bytes", limit)
+            }
+            ClientError::TooManyRedirects(limit) => {
+                write!(f, "followed more than {} redirects", limit)
+            }
+            ClientError::Tls(err) => write!(f, "tls error: {}", err),
+            // a read or write timing out shows up as either, depending on the platform
+            ClientError::Io(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                f.write_str("request timed out")
+            }
+            ClientError::Io(err) => write!(f, "io error: {}", err),
+        }
+    }
+}
+
+impl error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ClientError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(v: io::Error) -> Self {
+        Self::Io(v)
+    }
+}
+
+#[derive(Debug)]
+pub struct ClientResponse {
+    pub status: StatusCode,
+    /// Where the response came from, after any redirects.
+    pub url: Url,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl ClientResponse {
+    /// The first header with the name, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status.0)
+    }
+}
+
+pub struct Client {
+    user_agent: String,
+    timeout: Duration,
+    deadline: Duration,
+    max_redirects: usize,
+    max_size: usize,
+    cookies: Mutex<CookieJar>,
+    #[cfg(feature = "tls")]
+    tls: std::sync::Arc<rustls::ClientConfig>,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Self {
+            user_agent: concat!("enrgy/", env!("CARGO_PKG_VERSION")).to_string(),
+            timeout: Duration::from_secs(30),
+            deadline: Duration::from_secs(10 * 60),
+            max_redirects: 10,
+            max_size: 64 * 1024 * 1024,
+            cookies: Mutex::new(CookieJar::new()),
+            #[cfg(feature = "tls")]
+            tls: Self::tls_config(),
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    fn tls_config() -> std::sync::Arc<rustls::ClientConfig> {
+        let roots = rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+
+        let provider = std::sync::Arc::new(rustls::crypto::ring::default_provider());
+
+        let config = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .expect("the ring provider supports the default protocol versions")
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        std::sync::Arc::new(config)
+    }
+
+    pub fn user_agent<S>(mut self, user_agent: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// How long connecting, or any single read or write, can take before the request gives up.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How long a whole request, redirects and all, can take before it gives up.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// The most bytes a body can be, both as sent and once decompressed.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn cookies(&self) -> std::sync::MutexGuard<'_, CookieJar> {
+        // the jar is only ever a list, a panic part way through an update can't leave it broken
+        self.cookies
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Gets the URL, following redirects.
+    ///
+    /// Responses with error statuses are still given back, check [`ClientResponse::is_success`].
+    pub fn get(&self, url: &str) -> Result<ClientResponse, ClientError> {
+        let mut url = Url::parse(url)?;
+
+        let deadline = Instant::now() + self.deadline;
+
+        for _ in 0..=self.max_redirects {
+            let response = self.request(&url, deadline)?;
+
+            let location = match response.status.0 {
+                301 | 302 | 303 | 307 | 308 => response.header("Location"),
+                _ => None,
+            };
+
+            match location {
+                Some(location) => url = url.join(location)?,
+                None => return Ok(response),
+            }
+        }
+
+        Err(ClientError::TooManyRedirects(self.max_redirects))
+    }
+
+    fn request(&self, url: &Url, deadline: Instant) -> Result<ClientResponse, ClientError> {
+        let mut conn = Connection::open(self, url, deadline)?;
+
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nAccept: */*\r\nAccept-Encoding: gzip, deflate\r\nConnection: close\r\n",
+            url.target,
+            url.authority(),
+            self.user_agent,
+        );
+
+        if let Some(cookies) = self.cookies().header(url) {
+            request.push_str("Cookie: ");
+            request.push_str(&cookies);
+            request.push_str("\r\n");
+        }
+
+        request.push_str("\r\n");
+
+        conn.write_all(request.as_bytes())?;
+        conn.flush()?;
+
+        let mut reader = BufReader::new(conn);
+
+        let (status, headers) = loop {
+            let status = Self::read_status(&mut reader)?;
+            let headers = Self::read_headers(&mut reader)?;
+
+            // `100 Continue` and friends come before the real response
+            if !(100..200).contains(&status.0) {
+                break (status, headers);
+            }
+        };
+
+        let mut response = ClientResponse {
+            status,
+            url: url.clone(),
+            headers,
+            body: Vec::new(),
+        };
+
+        for (key, value) in &response.headers {
+            if key.eq_ignore_ascii_case("Set-Cookie") {
+                self.cookies().store(url, value);
+            }
+        }
+
+        let chunked = response
+            .header("Transfer-Encoding")
+            .map(|value| value.to_ascii_lowercase().contains("chunked"))
+            .unwrap_or(false);
+
+        let length = match response.header("Content-Length") {
+            Some(value) => Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| ClientError::InvalidResponse("invalid content length"))?,
+            ),
+            None => None,
+        };
+
+        let no_body = status.0 == 204 || status.0 == 304;
+
+        if !no_body {
+            let body = body::read(&mut reader, chunked, length, self.max_size)?;
+
+            response.body = body::decode(response.header("Content-Encoding"), body, self.max_size)?;
+        }
+
+        Ok(response)
+    }
+
+    fn read_status<R>(reader: &mut R) -> Result<StatusCode, ClientError>
+    where
+        R: BufRead,
+    {
+        let mut line = String::new();
+
+        if read_line(reader, &mut line)? == 0 {
+            return Err(ClientError::InvalidResponse(
+                "connection closed without a response",
+            ));
+        }
+
+        let mut parts = line.split_whitespace();
+
+        match (parts.next(), parts.next().map(str::parse::<u16>)) {
+            (Some(version), Some(Ok(code))) if version.starts_with("HTTP/1.") => {
+                Ok(StatusCode(code))
+            }
+            _ => Err(ClientError::InvalidResponse("invalid status line")),
+        }
+    }
+
+    fn read_headers<R>(reader: &mut R) -> Result<Vec<(String, String)>, ClientError>
+    where
+        R: BufRead,
+    {
+        let mut headers = Vec::new();
+        let mut line = String::new();
+
+        for _ in 0..=MAX_HEADERS {
+            line.clear();
+
+            if read_line(reader, &mut line)? == 0 {
+                return Err(ClientError::InvalidResponse(
+                    "connection closed in the headers",
+                ));
+            }
+
+            let line = line.trim_end();
+
+            if line.is_empty() {
+                return Ok(headers);
+            }
+
+            if let Some((key, value)) = line.split_once(':') {
+                headers.push((key.trim().to_string(), value.trim().to_string()));
+            }
+        }
+
+        Err(ClientError::InvalidResponse("too many headers"))
+    }
+}
+
+/// Reads a line, giving up on one longer than [`MAX_LINE`] rather than holding on to all of it.
+pub(crate) fn read_line<R>(reader: &mut R, line: &mut String) -> Result<usize, ClientError>
+where
+    R: BufRead,
+{
+    let read = reader.by_ref().take(MAX_LINE as u64 + 1).read_line(line)?;
+
+    if read > MAX_LINE {
+        return Err(ClientError::InvalidResponse("line is too long"));
+    }
+
+    Ok(read)
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
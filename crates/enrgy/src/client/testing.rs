@@ -0,0 +1,45 @@
+//! A stand in server for testing code that makes requests, needs the `testing` feature.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    thread::{self, JoinHandle},
+};
+
+/// Serves the canned responses in order, one per connection, giving back the requests it got.
+///
+/// Each request is its request line and headers, the body is never read.
+pub fn serve(responses: Vec<Vec<u8>>) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        let mut requests = Vec::new();
+
+        for response in responses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut request = String::new();
+
+            loop {
+                let mut line = String::new();
+
+                if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                    break;
+                }
+
+                request.push_str(&line);
+            }
+
+            requests.push(request);
+
+            // the client may have given up already, which is what some tests want
+            let _ = reader.get_mut().write_all(&response);
+        }
+
+        requests
+    });
+
+    (base, handle)
+}
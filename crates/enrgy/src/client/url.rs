@@ -0,0 +1,210 @@
+use std::fmt;
+
+use crate::client::ClientError;
+
+/// The parts of an `http` or `https` URL the client needs to make a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    pub https: bool,
+    pub host: String,
+    pub port: u16,
+    /// The path and query, always starting with a `/`.
+    pub target: String,
+}
+
+impl Url {
+    pub fn parse(src: &str) -> Result<Self, ClientError> {
+        let src = src.trim();
+
+        if has_control(src) {
+            return Err(ClientError::InvalidUrl(src.to_string()));
+        }
+
+        let (https, rest) = match src.split_once("://") {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("http") => (false, rest),
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("https") => (true, rest),
+            Some(_) => return Err(ClientError::UnsupportedScheme(src.to_string())),
+            None => return Err(ClientError::InvalidUrl(src.to_string())),
+        };
+
+        // fragments are never sent to the server
+        let rest = rest.split('#').next().unwrap_or_default();
+
+        let (authority, target) = match rest.find(['/', '?']) {
+            Some(i) if rest.as_bytes()[i] == b'?' => (&rest[..i], format!("/{}", &rest[i..])),
+            Some(i) => (&rest[..i], rest[i..].to_string()),
+            None => (rest, "/".to_string()),
+        };
+
+        if authority.is_empty() || authority.contains('@') {
+            return Err(ClientError::InvalidUrl(src.to_string()));
+        }
+
+        let default_port = if https { 443 } else { 80 };
+
+        let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+            let (host, rest) = rest
+                .split_once(']')
+                .ok_or_else(|| ClientError::InvalidUrl(src.to_string()))?;
+
+            match rest.strip_prefix(':') {
+                Some(port) => (host, Self::port(src, port)?),
+                None if rest.is_empty() => (host, default_port),
+                None => return Err(ClientError::InvalidUrl(src.to_string())),
+            }
+        } else {
+            match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Self::port(src, port)?),
+                None => (authority, default_port),
+            }
+        };
+
+        if host.is_empty() {
+            return Err(ClientError::InvalidUrl(src.to_string()));
+        }
+
+        Ok(Self {
+            https,
+            host: host.to_ascii_lowercase(),
+            port,
+            target: target.replace(' ', "%20"),
+        })
+    }
+
+    fn port(src: &str, port: &str) -> Result<u16, ClientError> {
+        port.parse()
+            .map_err(|_| ClientError::InvalidUrl(src.to_string()))
+    }
+
+    /// Resolves a `Location` header against this URL.
+    pub fn join(&self, location: &str) -> Result<Self, ClientError> {
+        let location = location.trim();
+
+        if has_control(location) {
+            return Err(ClientError::InvalidUrl(location.to_string()));
+        }
+
+        if location.contains("://") {
+            return Self::parse(location);
+        }
+
+        if let Some(rest) = location.strip_prefix("//") {
+            return Self::parse(&format!(
+                "{}://{}",
+                if self.https { "https" } else { "http" },
+                rest
+            ));
+        }
+
+        let target = if location.starts_with('/') {
+            location.to_string()
+        } else if location.starts_with('?') {
+            format!("{}{}", self.path(), location)
+        } else {
+            let path = self.path();
+            let dir = &path[..=path.rfind('/').unwrap_or(0)];
+
+            format!("{}{}", dir, location)
+        };
+
+        let target = target.split('#').next().unwrap_or_default();
+
+        Ok(Self {
+            target: target.replace(' ', "%20"),
+            ..self.clone()
+        })
+    }
+
+    /// The target without its query.
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or("/")
+    }
+
+    /// The value for the `Host` header, which leaves out the port when it's the default one.
+    pub fn authority(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+
+        match (self.https, self.port) {
+            (false, 80) | (true, 443) => host,
+            (_, port) => format!("{}:{}", host, port),
+        }
+    }
+}
+
+/// Control characters could end the request line early and start a header of the URL's choosing.
+fn has_control(src: &str) -> bool {
+    src.bytes().any(|b| b < 0x20 || b == 0x7f)
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}://{}{}",
+            if self.https { "https" } else { "http" },
+            self.authority(),
+            self.target
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let url = Url::parse("https://Archiveofourown.org/works/1?view_adult=true#main").unwrap();
+
+        assert!(url.https);
+        assert_eq!("archiveofourown.org", url.host);
+        assert_eq!(443, url.port);
+        assert_eq!("/works/1?view_adult=true", url.target);
+        assert_eq!(
+            "https://archiveofourown.org/works/1?view_adult=true",
+            url.to_string()
+        );
+
+        let url = Url::parse("http://127.0.0.1:8080").unwrap();
+
+        assert_eq!(8080, url.port);
+        assert_eq!("/", url.target);
+        assert_eq!("127.0.0.1:8080", url.authority());
+
+        let url = Url::parse("http://[::1]:8080/a?b").unwrap();
+
+        assert_eq!("::1", url.host);
+        assert_eq!("[::1]:8080", url.authority());
+
+        assert!(Url::parse("ftp://example.com/").is_err());
+        assert!(Url::parse("example.com/").is_err());
+        assert!(Url::parse("http://user@example.com/").is_err());
+        assert!(Url::parse("http://example.com:http/").is_err());
+        assert!(Url::parse("http://example.com/a\r\nCookie: b").is_err());
+        assert!(Url::parse("http://example.com/a\tb").is_err());
+        assert!(Url::parse("http://example.com/\x7f").is_err());
+    }
+
+    #[test]
+    fn test_join() {
+        let base = Url::parse("http://example.com/works/1/chapters?page=2").unwrap();
+
+        let join = |location: &str| base.join(location).unwrap().to_string();
+
+        assert_eq!("https://other.org/", join("https://other.org"));
+        assert_eq!("http://cdn.example.com/a", join("//cdn.example.com/a"));
+        assert_eq!("http://example.com/login", join("/login"));
+        assert_eq!("http://example.com/works/1/2", join("2"));
+        assert_eq!(
+            "http://example.com/works/1/chapters?page=3",
+            join("?page=3")
+        );
+
+        assert!(base.join("/login\r\nCookie: a").is_err());
+        assert!(base.join("http://other.org/\0").is_err());
+    }
+}
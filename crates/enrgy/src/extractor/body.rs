@@ -29,7 +29,7 @@ impl Extractor for Body {
 
     fn extract(req: &mut HttpRequest) -> Result<Self, Self::Error> {
         let value = match req.body.as_ref() {
-            Some(body) => body.as_vec(),
+            Some(body) => body.as_vec().map_err(|_| BodyRejection {})?,
             None => return Err(BodyRejection {}),
         };
 
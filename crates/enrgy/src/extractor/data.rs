@@ -13,6 +13,16 @@ where
     pub(crate) data: Arc<T>,
 }
 
+impl<T> Data<T>
+where
+    T: ?Sized,
+{
+    /// Gets a handle to the data that can outlive the request, like in a streamed body.
+    pub fn into_inner(self) -> Arc<T> {
+        self.data
+    }
+}
+
 impl<T> Deref for Data<T>
 where
     T: ?Sized,
@@ -17,7 +17,7 @@ pub use self::{
     data::Data,
     header::{Header, OptionalHeader, ParseHeader},
     param::{OptionalParam, Param, ParseParam},
-    query::{OptionalQuery, ParseQuery, Query, RawQuery},
+    query::{OptionalQuery, OptionalRawQuery, ParseQuery, Query, RawQuery},
 };
 
 pub trait Extractor: Sized {
@@ -21,6 +21,9 @@ struct ParsedQuery(ArrayMap<String, Option<String>, 32>);
 // by Martin Algesten (algesten) - MIT License
 fn str_to_map(origin: &str) -> ArrayMap<String, Option<String>, 32> {
     fn decode(s: &str) -> String {
+        // forms encode spaces as `+`, which percent decoding leaves alone
+        let s = &s.replace('+', " ");
+
         percent_decode(s.as_bytes())
             .decode_utf8()
             .map(|cow| cow.into_owned())
@@ -242,6 +245,34 @@ impl Extractor for RawQuery {
     }
 }
 
+pub struct OptionalRawQuery {
+    value: Option<String>,
+}
+
+impl const Deref for OptionalRawQuery {
+    type Target = Option<String>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl const DerefMut for OptionalRawQuery {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl Extractor for OptionalRawQuery {
+    type Error = RawQueryMissingRejection;
+
+    fn extract(req: &mut HttpRequest) -> Result<Self, Self::Error> {
+        Ok(Self {
+            value: req.uri.query.clone(),
+        })
+    }
+}
+
 pub struct RawQueryMissingRejection {}
 
 #[cfg(feature = "std")]
@@ -128,6 +128,7 @@ impl_headers! {
     CACHE_CONTROL => "Cache-Control",
     CLEAR_SITE_DATA => "Clear-Site-Data",
     CONNECTION => "Connection",
+    CONTENT_DISPOSITION => "Content-Disposition",
     CONTENT_ENCODING => "Content-Encoding",
     CONTENT_LANGUAGE => "Content-Language",
     CONTENT_LENGTH => "Content-Length",
@@ -150,6 +151,7 @@ impl_headers! {
     IF_UNMODIFIED_SINCE => "If-Unmodified-Since",
     KEEP_ALIVE => "Keep-Alive",
     LAST_MODIFIED => "Last-Modified",
+    LINK => "Link",
     LOCATION => "Location",
     MAX_FORWARDS => "Max-Forwards",
     ORIGIN => "Origin",
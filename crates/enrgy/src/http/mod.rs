@@ -7,8 +7,7 @@ pub mod uri;
 
 use std::{
     cmp, fmt,
-    io::{BufRead, Write},
-    net::TcpStream,
+    io::{self, BufRead, BufWriter, Write},
     str::FromStr,
     sync::Arc,
 };
@@ -59,14 +58,100 @@ impl From<std::num::ParseIntError> for HttpError {
 pub enum HttpBody {
     Bytes(&'static [u8]),
     Vector(Vec<u8>),
+    Stream(HttpStream),
 }
 
 impl HttpBody {
-    pub fn as_vec(&self) -> Vec<u8> {
+    pub fn as_vec(&self) -> io::Result<Vec<u8>> {
         match self {
-            HttpBody::Bytes(bytes) => bytes.to_vec(),
-            HttpBody::Vector(vector) => vector.clone(),
+            HttpBody::Bytes(bytes) => Ok(bytes.to_vec()),
+            HttpBody::Vector(vector) => Ok(vector.clone()),
+            HttpBody::Stream(stream) => {
+                let mut vector = Vec::new();
+
+                (stream.0)(&mut vector)?;
+
+                Ok(vector)
+            }
+        }
+    }
+}
+
+/// Writes a streamed body out, failing if the body can't be made.
+pub type StreamWriter = dyn Fn(&mut dyn Write) -> io::Result<()> + Send + Sync;
+
+/// A body that's written straight to the connection, for ones too big to build up front.
+///
+/// It's sent chunked, a body that fails part way through is cut off without the last chunk so the
+/// client can tell it didn't get all of it. HTTP/1.0 clients can't read chunked bodies, they're sent it
+/// as is and the connection closing is the end of it.
+#[derive(Clone)]
+pub struct HttpStream(Arc<StreamWriter>);
+
+impl HttpStream {
+    pub fn new<F>(writer: F) -> Self
+    where
+        F: Fn(&mut dyn Write) -> io::Result<()> + Send + Sync + 'static,
+    {
+        Self(Arc::new(writer))
+    }
+
+    fn write_chunked(&self, stream: &mut dyn Write) -> io::Result<()> {
+        {
+            let mut chunks = BufWriter::with_capacity(8 * 1024, Chunked(&mut *stream));
+
+            (self.0)(&mut chunks)?;
+
+            chunks.flush()?;
+        }
+
+        stream.write_all(b"0\r\n\r\n")
+    }
+
+    fn write_raw(&self, stream: &mut dyn Write) -> io::Result<()> {
+        let mut raw = BufWriter::with_capacity(8 * 1024, stream);
+
+        (self.0)(&mut raw)?;
+
+        raw.flush()
+    }
+}
+
+/// Writes everything given to it as a chunk of a chunked body.
+struct Chunked<W>(W);
+
+impl<W> Write for Chunked<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // an empty chunk is the end of the body
+        if buf.is_empty() {
+            return Ok(0);
         }
+
+        self.0
+            .write_all(format!("{:x}\r\n", buf.len()).as_bytes())?;
+        self.0.write_all(buf)?;
+        self.0.write_all(b"\r\n")?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl fmt::Debug for HttpStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HttpStream")
+    }
+}
+
+impl PartialEq for HttpStream {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
     }
 }
 
@@ -94,6 +179,12 @@ impl From<Vec<u8>> for HttpBody {
     }
 }
 
+impl From<HttpStream> for HttpBody {
+    fn from(s: HttpStream) -> Self {
+        Self::Stream(s)
+    }
+}
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum HttpMethod {
     Get,
@@ -453,12 +544,22 @@ impl HttpResponse {
     }
 }
 
+/// Writes the response to a request made with the `request` version of HTTP.
 pub fn write_response(
     res: HttpResponse,
+    request: HttpVersion,
     compress: bool,
-    stream: &mut TcpStream,
+    stream: &mut dyn Write,
 ) -> std::io::Result<()> {
-    stream.write_all(res.version.as_str().as_bytes())?;
+    // chunked bodies only exist from HTTP/1.1 on
+    let chunked = request == HttpVersion::Http11;
+
+    let version = match res.body {
+        Some(HttpBody::Stream(_)) if chunked => HttpVersion::Http11,
+        _ => res.version,
+    };
+
+    stream.write_all(version.as_str().as_bytes())?;
     stream.write_all(b" ")?;
     stream.write_all(itoa::Buffer::new().format(res.status.0).as_bytes())?;
     stream.write_all(b" ")?;
@@ -476,7 +577,7 @@ pub fn write_response(
         headers: &headers::HttpHeaderMap,
         bytes: &[u8],
         compress: bool,
-        stream: &mut TcpStream,
+        stream: &mut dyn Write,
     ) -> std::io::Result<()> {
         let pre_compressed = {
             match headers.get(&headers::CONTENT_ENCODING) {
@@ -517,6 +618,19 @@ pub fn write_response(
         Some(HttpBody::Vector(bytes)) => {
             write_bytes(&res.headers, bytes.as_slice(), compress, stream)?;
         }
+        Some(HttpBody::Stream(body)) if chunked => {
+            stream.write_all(b"Transfer-Encoding: chunked\r\n")?;
+            stream.write_all(b"Connection: close\r\n")?;
+            stream.write_all(b"\r\n")?;
+
+            body.write_chunked(stream)?;
+        }
+        Some(HttpBody::Stream(body)) => {
+            stream.write_all(b"Connection: close\r\n")?;
+            stream.write_all(b"\r\n")?;
+
+            body.write_raw(stream)?;
+        }
         None => {
             stream.write_all(b"Content-Length: 0\r\n")?;
         }
@@ -524,3 +638,65 @@ pub fn write_response(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stream_chunked() {
+        let body = HttpStream::new(|out| {
+            out.write_all(b"hello ")?;
+            out.write_all(b"world")
+        });
+
+        let mut written = Vec::new();
+        body.write_chunked(&mut written).unwrap();
+
+        assert_eq!(b"b\r\nhello world\r\n0\r\n\r\n", &written[..]);
+    }
+
+    #[test]
+    fn test_stream_error() {
+        let body = HttpStream::new(|out| {
+            out.write_all(b"hello")?;
+
+            Err(io::Error::new(io::ErrorKind::Other, "story went missing"))
+        });
+
+        let mut written = Vec::new();
+        assert!(body.write_chunked(&mut written).is_err());
+
+        // whatever was written is still sent, just never the last chunk
+        assert_eq!(b"5\r\nhello\r\n", &written[..]);
+
+        let err = HttpBody::Stream(body).as_vec().unwrap_err();
+        assert_eq!("story went missing", err.to_string());
+    }
+
+    fn streamed() -> HttpResponse {
+        HttpResponse::ok().body(HttpStream::new(|out| out.write_all(b"hello world")))
+    }
+
+    #[test]
+    fn test_write_stream_http11() {
+        let mut written = Vec::new();
+        write_response(streamed(), HttpVersion::Http11, false, &mut written).unwrap();
+
+        assert_eq!(
+            &b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\nb\r\nhello world\r\n0\r\n\r\n"[..],
+            &written[..]
+        );
+    }
+
+    #[test]
+    fn test_write_stream_http10() {
+        let mut written = Vec::new();
+        write_response(streamed(), HttpVersion::Http10, false, &mut written).unwrap();
+
+        assert_eq!(
+            &b"HTTP/1.0 200 OK\r\nConnection: close\r\n\r\nhello world"[..],
+            &written[..]
+        );
+    }
+}
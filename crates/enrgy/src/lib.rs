@@ -26,6 +26,7 @@ mod handler;
 mod server;
 mod service;
 
+pub mod client;
 pub mod extractor;
 pub mod http;
 
@@ -37,5 +37,6 @@ macro_rules! impl_responder {
 impl_responder! {
     Atom => "application/atom+xml; charset=utf-8",
     Html => "text/html; charset=utf-8",
+    Json => "application/json; charset=utf-8",
     Xml => "application/xml; charset=utf-8",
 }
@@ -286,7 +286,7 @@ impl Server<SocketAddr> {
             response = middleware.after(&request, response);
         }
 
-        http::write_response(response, compress, stream)?;
+        http::write_response(response, request.version, compress, stream)?;
 
         Ok(())
     }
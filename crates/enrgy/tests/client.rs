@@ -0,0 +1,194 @@
+use std::{
+    io::{self, Write as _},
+    net::TcpListener,
+    thread,
+    time::{Duration, Instant},
+};
+
+use enrgy::client::{testing::serve, Client, ClientError};
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut gzip = vec![0x1f, 0x8b, 0x08, 0, 0, 0, 0, 0, 0, 0xff];
+    gzip.extend_from_slice(&miniz_oxide::deflate::compress_to_vec(data, 6));
+    gzip.extend_from_slice(&[0; 8]);
+    gzip
+}
+
+#[test]
+fn test_redirect_cookies_and_encoding() {
+    let text = b"<html><head><title>A Work - Archive</title></head></html>";
+    let compressed = gzip(text);
+
+    let mut chunked = format!(
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Encoding: gzip\r\n\r\n{:x}\r\n",
+        compressed.len()
+    )
+    .into_bytes();
+    chunked.extend_from_slice(&compressed);
+    chunked.extend_from_slice(b"\r\n0\r\n\r\n");
+
+    let (base, server) = serve(vec![
+        b"HTTP/1.1 302 Found\r\nLocation: /works/1?view_adult=true\r\nSet-Cookie: view_adult=true; path=/\r\nContent-Length: 0\r\n\r\n".to_vec(),
+        chunked,
+    ]);
+
+    let client = Client::new().user_agent("varela-test");
+
+    let response = client.get(&format!("{}/works/1", base)).unwrap();
+
+    assert!(response.is_success());
+    assert_eq!(&text[..], &response.body[..]);
+    assert_eq!(
+        format!("{}/works/1?view_adult=true", base),
+        response.url.to_string()
+    );
+
+    let requests = server.join().unwrap();
+
+    assert!(requests[0].starts_with("GET /works/1 HTTP/1.1\r\n"));
+    assert!(requests[0].contains("User-Agent: varela-test\r\n"));
+    assert!(requests[0].contains("Accept-Encoding: gzip, deflate\r\n"));
+    assert!(!requests[0].contains("Cookie:"));
+
+    assert!(requests[1].starts_with("GET /works/1?view_adult=true HTTP/1.1\r\n"));
+    assert!(requests[1].contains("Cookie: view_adult=true\r\n"));
+}
+
+#[test]
+fn test_body_without_length() {
+    let (base, server) = serve(vec![b"HTTP/1.0 404 Not Found\r\n\r\nmissing".to_vec()]);
+
+    let response = Client::new().get(&base).unwrap();
+
+    assert_eq!(404, response.status.0);
+    assert!(!response.is_success());
+    assert_eq!(b"missing", &response.body[..]);
+
+    server.join().unwrap();
+}
+
+#[test]
+fn test_size_limit() {
+    let (base, server) = serve(vec![
+        b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n".to_vec(),
+        b"HTTP/1.1 200 OK\r\n\r\n0123456789abcdef".to_vec(),
+    ]);
+
+    let client = Client::new().max_size(10);
+
+    assert!(matches!(client.get(&base), Err(ClientError::TooLarge(10))));
+    assert!(matches!(client.get(&base), Err(ClientError::TooLarge(10))));
+
+    server.join().unwrap();
+}
+
+#[test]
+fn test_redirect_limit() {
+    let redirect =
+        b"HTTP/1.1 301 Moved Permanently\r\nLocation: /again\r\nContent-Length: 0\r\n\r\n";
+
+    let (base, server) = serve(vec![redirect.to_vec(); 3]);
+
+    let client = Client::new().max_redirects(2);
+
+    assert!(matches!(
+        client.get(&base),
+        Err(ClientError::TooManyRedirects(2))
+    ));
+
+    server.join().unwrap();
+}
+
+#[test]
+fn test_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+
+    // accept the connection but never answer it
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        thread::sleep(Duration::from_secs(1));
+        drop(stream);
+    });
+
+    let client = Client::new().timeout(Duration::from_millis(200));
+
+    assert!(matches!(client.get(&base), Err(ClientError::Io(_))));
+
+    server.join().unwrap();
+}
+
+#[test]
+fn test_deadline() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+
+    // answer, but so slowly that no single read ever times out
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n");
+
+        for _ in 0..100 {
+            if stream.write_all(b"a").is_err() {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(20));
+        }
+    });
+
+    let client = Client::new()
+        .timeout(Duration::from_secs(1))
+        .deadline(Duration::from_millis(200));
+
+    let started = Instant::now();
+
+    assert!(matches!(
+        client.get(&base),
+        Err(ClientError::Io(err)) if err.kind() == io::ErrorKind::TimedOut
+    ));
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    server.join().unwrap();
+}
+
+#[test]
+fn test_header_limits() {
+    let mut long = b"HTTP/1.1 200 OK\r\nX-Long: ".to_vec();
+    long.resize(64 * 1024, b'a');
+    long.extend_from_slice(b"\r\nContent-Length: 0\r\n\r\n");
+
+    let mut many = b"HTTP/1.1 200 OK\r\n".to_vec();
+    many.extend(b"X-Many: a\r\n".repeat(1000));
+    many.extend_from_slice(b"Content-Length: 0\r\n\r\n");
+
+    let (base, server) = serve(vec![long, many]);
+
+    let client = Client::new();
+
+    assert!(matches!(
+        client.get(&base),
+        Err(ClientError::InvalidResponse(_))
+    ));
+    assert!(matches!(
+        client.get(&base),
+        Err(ClientError::InvalidResponse(_))
+    ));
+
+    server.join().unwrap();
+}
+
+#[test]
+fn test_invalid_url() {
+    let client = Client::new();
+
+    assert!(matches!(
+        client.get("ftp://example.com/"),
+        Err(ClientError::UnsupportedScheme(_))
+    ));
+    assert!(matches!(
+        client.get("example.com"),
+        Err(ClientError::InvalidUrl(_))
+    ));
+}
@@ -121,20 +121,12 @@ fn run_set(mut args: common::Args) -> Result<()> {
 
     match key.as_str() {
         key @ "theme" => {
-            let theme = match value.to_lowercase().as_str() {
-                "light" => Some(Theme::Light),
-                "dark" => Some(Theme::Dark),
-                _ => {
-                    error!(target: "config", "Unknown {} value: {}", key.bright_blue(), value.bright_red());
-
-                    None
-                }
-            };
-
-            if let Some(theme) = theme {
+            if let Some(theme) = Theme::from_class(&value.to_lowercase()) {
                 database.settings_mut().theme = theme;
 
                 info!(target: "config", "{} set to {}", key.bright_blue(), database.settings().theme.as_class().bright_yellow());
+            } else {
+                error!(target: "config", "Unknown {} value: {}", key.bright_blue(), value.bright_red());
             }
         }
         "sync-key" | "sync_key" => {
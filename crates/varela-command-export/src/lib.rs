@@ -0,0 +1,79 @@
+use std::{fs, path::PathBuf};
+
+use command_serve::export::{self, ExportFormat};
+use common::{database::Database, models::Id, prelude::*};
+
+#[inline(never)]
+pub fn run(mut args: common::Args) -> Result<()> {
+    if args.peek().map(|a| a == "--help").unwrap_or_default() {
+        vfmt::println!("Usage:");
+        vfmt::println!("  varela export <ID> [<ARGS>]");
+        vfmt::println!();
+        vfmt::println!("Options:");
+        vfmt::println!("  --help");
+        vfmt::println!();
+        vfmt::println!("Arguments:");
+        vfmt::println!("  id              the id of the story to export");
+        vfmt::println!("  format          one of epub, html, md, or txt [default: epub]");
+        vfmt::println!("  output          where to write the file [default: the story's title]");
+
+        return Ok(());
+    }
+
+    let id = args
+        .next()
+        .map(Id::from)
+        .ok_or_else(|| anyhow::anyhow!("`export` is missing `id` value"))?;
+
+    let mut format = ExportFormat::Epub;
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" if args.peek().is_some() => {
+                let value = unsafe { args.next().unwrap_unchecked() };
+
+                format = value
+                    .parse::<ExportFormat>()
+                    .map_err(|err| anyhow::anyhow!("{}", err.0))?;
+            }
+            "--output" if args.peek().is_some() => {
+                output = args.next();
+            }
+            arg => {
+                return Err(anyhow::anyhow!(
+                    "`export` got an unknown argument `{}`",
+                    arg
+                ));
+            }
+        }
+    }
+
+    let mut database = Database::open()?;
+
+    let title = database
+        .index()
+        .stories
+        .get(&id)
+        .map(|story| story.info.title.clone())
+        .ok_or_else(|| anyhow::anyhow!("story with id `{}` does not exist", id))?;
+
+    // the chapters are read from the mapped story files, which are unlocked again even if the export fails
+    database.lock_data()?;
+
+    let exported = export::export(&database, &id, format);
+
+    database.unlock_data()?;
+
+    let bytes = exported?;
+
+    let path = output
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(export::file_name(&title, format)));
+
+    fs::write(&path, &bytes)?;
+
+    info!(target: "export", "Exported {} to {}", title.bright_green(), path.display().to_string().bright_purple());
+
+    Ok(())
+}
@@ -1,11 +1,4 @@
-use std::{
-    collections::HashMap,
-    ffi::OsStr,
-    fs::{self, DirEntry},
-    hash::Hasher as _,
-    path::Path,
-    time::SystemTime,
-};
+use std::{collections::HashMap, ffi::OsStr, fs, hash::Hasher as _, path::Path, time::SystemTime};
 
 use common::{
     database::Database,
@@ -40,7 +33,7 @@ pub fn run(_args: common::Args) -> Result<()> {
     );
 
     for entry in FileIter::new(fs::read_dir(&database.data_path)?) {
-        handle_entry(&mut database, &mut known_ids, entry?)?;
+        handle_entry(&mut database, &mut known_ids, &entry?.path())?;
     }
 
     let index = database.index_mut();
@@ -79,8 +72,20 @@ pub fn run(_args: common::Args) -> Result<()> {
     Ok(())
 }
 
-fn handle_entry(db: &mut Database, known_ids: &mut Vec<Id>, entry: DirEntry) -> Result<()> {
-    let path = entry.path();
+/// Adds or updates a single file in the index, without looking at the rest of the data directory.
+///
+/// Gives back the story's ID, if the file is one that can be indexed.
+pub fn index_file(db: &mut Database, path: &Path) -> Result<Option<Id>> {
+    let mut known_ids = Vec::new();
+
+    handle_entry(db, &mut known_ids, path)?;
+
+    Ok(known_ids
+        .pop()
+        .filter(|id| db.index().stories.contains_key(id)))
+}
+
+fn handle_entry(db: &mut Database, known_ids: &mut Vec<Id>, path: &Path) -> Result<()> {
     let ext = path.extension().and_then(OsStr::to_str);
 
     let name = path
@@ -96,7 +101,7 @@ fn handle_entry(db: &mut Database, known_ids: &mut Vec<Id>, entry: DirEntry) ->
     };
 
     if let Some(kind) = file_kind {
-        let details = handle_file(db, known_ids, &path, name)
+        let details = handle_file(db, known_ids, path, name)
             .with_context(|| vfmt::format!("While reading file {}", name))?;
 
         if let Some((id, hash, updating)) = details {
@@ -115,7 +120,7 @@ fn handle_entry(db: &mut Database, known_ids: &mut Vec<Id>, entry: DirEntry) ->
             let site = match kind {
                 FileKind::Epub => {
                     let output = common::utils::command("unzip")
-                        .arg(&path)
+                        .arg(path)
                         .arg("-d")
                         .arg(&temp_file_path)
                         .output()?;
@@ -140,7 +145,7 @@ fn handle_entry(db: &mut Database, known_ids: &mut Vec<Id>, entry: DirEntry) ->
                     }
                 }
                 FileKind::Html => {
-                    let text = fs::read_to_string(&path)?;
+                    let text = fs::read_to_string(path)?;
 
                     if text.contains(DETECTOR_AO3.html) {
                         Site::ArchiveOfOurOwn
@@ -160,7 +165,7 @@ fn handle_entry(db: &mut Database, known_ids: &mut Vec<Id>, entry: DirEntry) ->
                         parsed?
                     }
                     FileKind::Html => {
-                        let text = fs::read_to_string(&path)?;
+                        let text = fs::read_to_string(path)?;
 
                         format_ao3::parse_html(&text)?
                     }
@@ -263,6 +268,7 @@ fn add_to_index(
                 summary: chapter.summary,
                 start_notes: chapter.start_notes,
                 end_notes: chapter.end_notes,
+                words: chapter.words,
             })
             .collect(),
         info: StoryInfo {
@@ -271,8 +277,12 @@ fn add_to_index(
             title: info.title.to_owned(),
             kind,
             summary: info.summary.to_owned(),
+            start_notes: info.start_notes,
+            end_notes: info.end_notes,
             created,
             updated: humantime::format_rfc3339(SystemTime::now()).to_string(),
+            source: info.source,
+            posted: info.posted,
         },
         meta: StoryMeta {
             rating: meta.rating,
@@ -304,3 +314,110 @@ fn new_id<V>(map: &HashMap<Id, V>) -> Id {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use aloene::Aloene as _;
+    use common::models::{v1, Rating, Theme, Version};
+
+    use super::*;
+
+    const NAME: &str = "A Work To Test Downloads.html";
+    const FILE: &str =
+        include_str!("../../varela-format-ao3/src/tests/A Work To Test Downloads.html");
+
+    fn v1(data_path: &Path, temp_path: &Path) -> v1::Config {
+        let story = v1::Story {
+            info: v1::StoryInfo {
+                file_name: NAME.to_owned(),
+                file_hash: {
+                    let mut hasher = crc32fast::Hasher::default();
+
+                    hasher.write(FILE.as_bytes());
+
+                    hasher.finish()
+                },
+                kind: FileKind::Html,
+                title: "A Work To Test Downloads".to_owned(),
+                summary: String::new(),
+                created: "2019-01-15T00:00:00Z".to_owned(),
+                updated: "2019-02-01T00:00:00Z".to_owned(),
+            },
+            meta: StoryMeta {
+                rating: Rating::General,
+                authors: vec![],
+                categories: vec![],
+                origins: vec![],
+                warnings: vec![],
+                pairings: vec![],
+                characters: vec![],
+                generals: vec![],
+            },
+            site: Site::ArchiveOfOurOwn,
+            chapters: vec![],
+        };
+
+        v1::Config {
+            version: Version::V1,
+            settings: v1::Settings {
+                theme: Theme::Dark,
+                sync_key: String::new(),
+                data_path: data_path.to_str().unwrap().to_owned(),
+                temp_path: temp_path.to_str().unwrap().to_owned(),
+                nodes: vec![],
+            },
+            index: v1::Index {
+                stories: HashMap::from([(Id::from("a".to_owned()), story)]),
+                categories: HashMap::new(),
+                authors: HashMap::new(),
+                origins: HashMap::new(),
+                warnings: HashMap::new(),
+                pairings: HashMap::new(),
+                characters: HashMap::new(),
+                generals: HashMap::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_index_migrated() {
+        let dir = env::temp_dir().join(format!("varela-index-{}", std::process::id()));
+        let data_path = dir.join("data");
+        let temp_path = dir.join("temp");
+
+        fs::create_dir_all(&data_path).unwrap();
+        fs::write(data_path.join(NAME), FILE).unwrap();
+
+        let mut buf = Vec::new();
+
+        v1(&data_path, &temp_path).serialize(&mut buf).unwrap();
+
+        fs::write(
+            dir.join("varela.aloe.dfl"),
+            miniz_oxide::deflate::compress_to_vec(&buf, 10),
+        )
+        .unwrap();
+
+        let mut db = Database::open_in(&dir).unwrap();
+
+        let id = index_file(&mut db, &data_path.join(NAME)).unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let id = id.unwrap();
+        let story = &db.index().stories[&id];
+
+        // the same story, now with everything the older index didn't have
+        assert_eq!("a", id.as_str());
+        assert_eq!("2019-01-15T00:00:00Z", story.info.created);
+        assert_eq!(
+            Some("http://archiveofourown.org/works/12336681"),
+            story.info.source.as_deref()
+        );
+        assert!(story.info.posted.is_some());
+        assert_eq!(1, story.chapters.len());
+        assert_eq!(2266, story.chapters[0].words);
+    }
+}
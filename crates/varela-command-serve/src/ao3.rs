@@ -0,0 +1,173 @@
+//! Downloading works from AO3 straight into the library.
+
+use std::sync::RwLock;
+
+use common::{
+    database::Database,
+    models::{FileKind, Id},
+    prelude::*,
+};
+
+use crate::{
+    export::{self, ExportFormat},
+    utils,
+};
+
+/// Finds the work the link is to, asking the archive where a link to a chapter on its own leads.
+pub fn resolve_work(archive: &str, url: &str) -> Result<String> {
+    if let Some(work) = format_ao3::site::work_id(url) {
+        return Ok(work.to_string());
+    }
+
+    let chapter = format_ao3::site::chapter_id(url)
+        .ok_or_else(|| anyhow!("`{}` is not a link to a work on AO3", url))?;
+
+    let located = utils::http::locate(&format_ao3::site::chapter_url(archive, chapter))?;
+
+    format_ao3::site::redirected_work_id(archive, &located)
+        .map(String::from)
+        .ok_or_else(|| {
+            anyhow!(
+                "chapter {} didn't lead to a work, it may only be visible to logged in users",
+                chapter
+            )
+        })
+}
+
+/// Gets the HTML download of a work, finding the link to it on the work's page.
+pub fn fetch_work(archive: &str, work: &str) -> Result<String> {
+    let page = utils::http::get(&format_ao3::site::work_url(archive, work))?;
+
+    // the html download is the one the index can read
+    let link = format_ao3::site::export_link(archive, &String::from_utf8_lossy(&page), "html")
+        .ok_or_else(|| {
+            anyhow!(
+                "unable to find the download link for work {}, it may only be visible to logged in users",
+                work
+            )
+        })?;
+
+    Ok(String::from_utf8(utils::http::get(&link)?)?)
+}
+
+/// Writes the work's HTML download to the data directory and indexes it, replacing the file if the work was downloaded before.
+///
+/// A followed story is marked as updated when the download has a different chapter count or posted date to the one it replaces.
+pub fn save_work(db: &RwLock<Database>, work: &str, text: &str) -> Result<Id> {
+    let (info, _, _) = format_ao3::parse_html(text)
+        .with_context(|| vfmt::format!("work {} downloaded, but it couldn't be read", work))?;
+
+    let mut db = utils::write(db)?;
+
+    let name = file_name(&db, work, &info.title);
+
+    let before = stats(&db, &name);
+
+    let id = utils::store(&mut db, &name, text.as_bytes())?
+        .ok_or_else(|| anyhow!("`{}` was downloaded but not indexed", name))?;
+
+    if let Some(before) = before {
+        let updated = stats(&db, &name)
+            .map(|after| after != before)
+            .unwrap_or_default();
+
+        if let Some(bookmark) = db.library_mut().bookmarks.get_mut(&id) {
+            bookmark.updated |= updated && bookmark.following;
+        }
+    }
+
+    db.save()?;
+
+    utils::uncache_story(&id)?;
+
+    info!(target: "download", "downloaded work {} as `{}`", work, name);
+
+    Ok(id)
+}
+
+/// The chapter count and last posted date of the story in the file, if it has been indexed.
+fn stats(db: &Database, name: &str) -> Option<(usize, Option<String>)> {
+    db.index()
+        .stories
+        .values()
+        .find(|story| story.info.file_name == name)
+        .map(|story| (story.chapters.len(), story.info.posted.clone()))
+}
+
+/// Names the file after the work so downloading it again replaces it, keeping the name it already has if there is one.
+fn file_name(db: &Database, work: &str, title: &str) -> String {
+    let prefix = vfmt::format!("{} - ", work);
+
+    // files that weren't downloaded by the server are found by the link to the work in them
+    let existing = db
+        .index()
+        .stories
+        .values()
+        .find(|story| {
+            story.info.file_name.starts_with(&prefix)
+                || (story.info.kind == FileKind::Html
+                    && story
+                        .info
+                        .source
+                        .as_deref()
+                        .and_then(format_ao3::site::work_id)
+                        == Some(work))
+        })
+        .map(|story| &story.info.file_name);
+
+    if let Some(existing) = existing {
+        return existing.clone();
+    }
+
+    // some titles are whole sentences, which makes for an unwieldy file name
+    let title = title.chars().take(100).collect::<String>();
+
+    export::file_name(&vfmt::format!("{}{}", prefix, title), ExportFormat::Html)
+}
+
+#[cfg(test)]
+mod test {
+    use enrgy::client::testing::serve;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve_work() {
+        let (base, server) = serve(vec![
+            b"HTTP/1.1 302 Found\r\nLocation: /works/36141154/chapters/90082141\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            b"HTTP/1.1 302 Found\r\nLocation: /users/login\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_vec(),
+        ]);
+
+        assert_eq!(
+            "36141154",
+            resolve_work(&base, "https://archiveofourown.org/chapters/90082141").unwrap()
+        );
+        assert!(resolve_work(&base, "https://archiveofourown.org/chapters/1").is_err());
+
+        // links with the work in them never ask the archive
+        assert_eq!(
+            "5",
+            resolve_work(&base, "https://archiveofourown.org/works/5/chapters/6").unwrap()
+        );
+        assert!(resolve_work(&base, "https://archiveofourown.org/works/new").is_err());
+
+        let paths = server
+            .join()
+            .unwrap()
+            .iter()
+            .map(|request| request.split(' ').nth(1).unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                "/chapters/90082141?view_adult=true",
+                "/works/36141154/chapters/90082141",
+                "/chapters/1?view_adult=true",
+                "/users/login",
+            ],
+            paths
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Many stories in one zip, for moving a reading list onto a device.
+
+use std::{io::Write, sync::RwLock};
+
+use common::{
+    database::Database,
+    models::{FileKind, Id},
+    prelude::*,
+    zip::{Method, ZipWriter},
+};
+
+use crate::{
+    export::{self, epub, ExportFormat},
+    json, utils,
+};
+
+pub const MIME: &str = "application/zip";
+
+/// What each story goes into the zip as.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BundleFormat {
+    /// The file the story was indexed from, whatever it is.
+    Original,
+    /// An EPUB, generating one for stories that aren't already.
+    Epub,
+}
+
+impl BundleFormat {
+    pub fn parse(value: Option<&str>) -> Option<Self> {
+        match value {
+            None | Some("original") => Some(BundleFormat::Original),
+            Some("epub") => Some(BundleFormat::Epub),
+            Some(_) => None,
+        }
+    }
+}
+
+/// A story going into a zip, with everything the manifest needs already taken from the index.
+pub struct Entry {
+    pub id: Id,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub kind: FileKind,
+    /// The name of the story's file in the zip.
+    pub name: String,
+}
+
+/// Picks out the stories going into a zip and their names in it, skipping any that don't exist.
+pub fn entries(db: &Database, ids: &[Id], format: BundleFormat) -> Vec<Entry> {
+    // the manifest goes in last, so no story can take its name
+    let mut names = vec!["manifest.json".to_owned()];
+
+    let mut entries = Vec::with_capacity(ids.len());
+
+    for id in ids {
+        let Some(story) = db.index().stories.get(id) else {
+            continue;
+        };
+
+        let name = match format {
+            BundleFormat::Epub => export::file_name(&story.info.title, ExportFormat::Epub),
+            BundleFormat::Original => story.info.file_name.clone(),
+        };
+
+        let name = unique(&names, name);
+
+        names.push(name.clone());
+
+        let authors = story
+            .meta
+            .authors
+            .iter()
+            .map(|author| {
+                db.index()
+                    .authors
+                    .get(author)
+                    .map(|entity| entity.text.clone())
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        entries.push(Entry {
+            id: id.clone(),
+            title: story.info.title.clone(),
+            authors,
+            kind: story.info.kind,
+            name,
+        });
+    }
+
+    entries
+}
+
+/// Writes the stories out as a zip, along with a `manifest.json` listing what each file is.
+///
+/// Stories are written one at a time, so only one has to be held in memory. The lock is only taken
+/// while each one is read, never while it's being written out.
+pub fn write<W: Write>(
+    db: &RwLock<Database>,
+    entries: &[Entry],
+    format: BundleFormat,
+    writer: W,
+) -> Result<W> {
+    let mut zip = ZipWriter::new(writer);
+
+    let mut manifest = String::from("{");
+
+    json::key(&mut manifest, "stories");
+    manifest.push('[');
+
+    for entry in entries {
+        let bytes = {
+            let db = utils::read(db)?;
+
+            // removed since the zip was started
+            if !db.index().stories.contains_key(&entry.id) {
+                continue;
+            }
+
+            match (format, entry.kind) {
+                (BundleFormat::Epub, FileKind::Html) => epub::cached(&db, &entry.id)?,
+                (BundleFormat::Epub, FileKind::Epub) | (BundleFormat::Original, _) => {
+                    db.get_file(&entry.id)?.to_vec()
+                }
+            }
+        };
+
+        // epubs are zips already, deflating them again only costs time
+        let method = match (format, entry.kind) {
+            (BundleFormat::Original, FileKind::Html) => Method::Deflated,
+            _ => Method::Stored,
+        };
+
+        zip.file(&entry.name, method, &bytes)?;
+
+        if !manifest.ends_with('[') {
+            manifest.push(',');
+        }
+
+        manifest.push('{');
+        json::key(&mut manifest, "id");
+        json::string(&mut manifest, entry.id.as_str());
+        manifest.push(',');
+        json::key(&mut manifest, "title");
+        json::string(&mut manifest, &entry.title);
+        manifest.push(',');
+        json::key(&mut manifest, "authors");
+        manifest.push('[');
+        for (i, author) in entry.authors.iter().enumerate() {
+            if i != 0 {
+                manifest.push(',');
+            }
+
+            json::string(&mut manifest, author);
+        }
+        manifest.push_str("],");
+        json::key(&mut manifest, "file");
+        json::string(&mut manifest, &entry.name);
+        manifest.push('}');
+    }
+
+    manifest.push_str("]}");
+
+    zip.file("manifest.json", Method::Deflated, manifest.as_bytes())?;
+
+    zip.finish()
+}
+
+/// Numbers the name if another story already has it, as a zip can't have two files with the same name.
+fn unique(names: &[String], name: String) -> String {
+    if !names.contains(&name) {
+        return name;
+    }
+
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) => (stem, vfmt::format!(".{}", ext)),
+        None => (name.as_str(), String::new()),
+    };
+
+    let mut i = 2;
+
+    loop {
+        let numbered = vfmt::format!("{} ({}){}", stem, i, ext);
+
+        if !names.contains(&numbered) {
+            return numbered;
+        }
+
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unique() {
+        let names = vec!["a.epub".to_owned(), "a (2).epub".to_owned(), "b".to_owned()];
+
+        assert_eq!("c.epub", unique(&names, "c.epub".to_owned()));
+        assert_eq!("a (3).epub", unique(&names, "a.epub".to_owned()));
+        assert_eq!("b (2)", unique(&names, "b".to_owned()));
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use common::{
+    database::Database,
+    models::{FileKind, Id},
+    prelude::*,
+    zip::{Method, ZipWriter},
+};
+
+use crate::{
+    export::{self, xhtml},
+    templates::epub::{ChapterLink, ChapterPage, Nav, Package, TitlePage},
+    utils,
+};
+
+pub const MIME: &str = "application/epub+zip";
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+    <rootfiles>
+        <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+    </rootfiles>
+</container>
+"#;
+
+const STYLE: &str = "body { margin: 0 5%; line-height: 1.4; }
+h1, h2 { text-align: center; }
+.author { text-align: center; font-style: italic; }
+.tags { font-size: 0.85em; }
+.notes { font-size: 0.9em; margin: 1em 0; padding: 0 1em; border-left: 2px solid #999; }
+.chapter p { margin: 0 0 1em 0; }
+";
+
+/// Makes each in progress file unique, so two requests for the same story can't write over each other.
+static PARTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Gets a story as an EPUB, building it if there isn't one cached for the story's current file.
+///
+/// Stories that are already EPUBs are given back as they are.
+pub fn cached(db: &Database, id: &Id) -> Result<Vec<u8>> {
+    let story = db
+        .index()
+        .stories
+        .get(id)
+        .ok_or_else(|| anyhow!("story with id `{}` does not exist", id))?;
+
+    if story.info.kind == FileKind::Epub {
+        return Ok(db.get_file(id)?.to_vec());
+    }
+
+    let dir = db.temp_path.join("epub");
+    let path = dir.join(vfmt::format!(
+        "{}-{}.epub",
+        id.as_str(),
+        story.info.file_hash
+    ));
+
+    match fs::read(&path) {
+        Ok(bytes) => return Ok(bytes),
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+
+    let bytes = build(db, id)?;
+
+    fs::create_dir_all(&dir)?;
+
+    let part = dir.join(vfmt::format!(
+        "{}.{}.part",
+        id.as_str(),
+        PARTS.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    fs::write(&part, &bytes)?;
+    fs::rename(&part, &path)?;
+
+    // the story's file changed since the last one was made, so that one will never be used again
+    remove_stale(&dir, id, &path)?;
+
+    Ok(bytes)
+}
+
+fn remove_stale(dir: &Path, id: &Id, current: &Path) -> Result<()> {
+    let prefix = vfmt::format!("{}-", id.as_str());
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        let stale = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.starts_with(&prefix) && name.ends_with(".epub"))
+            .unwrap_or(false);
+
+        if stale && path != current {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds an EPUB 3 from a story's HTML.
+pub fn build(db: &Database, id: &Id) -> Result<Vec<u8>> {
+    let story = utils::get_story_full(db, id)?;
+
+    let chapters = (1..=story.chapters.len())
+        .map(|index| ChapterLink {
+            id: vfmt::format!("chapter-{}", index),
+            title: export::chapter_title(&story, index),
+        })
+        .collect::<Vec<_>>();
+
+    let authors = story
+        .meta
+        .authors
+        .iter()
+        .map(|author| author.text.clone())
+        .collect::<Vec<_>>();
+
+    let tags = story
+        .meta
+        .origins
+        .iter()
+        .chain(story.meta.pairings.iter())
+        .chain(story.meta.characters.iter())
+        .chain(story.meta.generals.iter())
+        .map(|entity| entity.text.clone())
+        .collect::<Vec<_>>();
+
+    let mut zip = ZipWriter::new(Vec::new());
+
+    // readers find out what the file is from the first entry, so it has to be there and uncompressed
+    zip.file("mimetype", Method::Stored, MIME.as_bytes())?;
+    zip.file(
+        "META-INF/container.xml",
+        Method::Deflated,
+        CONTAINER.as_bytes(),
+    )?;
+
+    let package = Package {
+        id: vfmt::format!("urn:varela:story:{}", id.as_str()),
+        title: story.info.title.clone(),
+        authors: authors.clone(),
+        subjects: tags.clone(),
+        summary: story.info.summary.clone(),
+        modified: modified(&story.info.updated),
+        chapters,
+    };
+
+    zip.file(
+        "OEBPS/content.opf",
+        Method::Deflated,
+        render(&package)?.as_bytes(),
+    )?;
+
+    let nav = Nav {
+        title: story.info.title.clone(),
+        chapters: package.chapters,
+    };
+
+    zip.file(
+        "OEBPS/nav.xhtml",
+        Method::Deflated,
+        render(&nav)?.as_bytes(),
+    )?;
+    zip.file("OEBPS/style.css", Method::Deflated, STYLE.as_bytes())?;
+
+    let title = TitlePage {
+        title: story.info.title.clone(),
+        authors,
+        summary: xhtml::from_html(&story.info.summary),
+        tags,
+    };
+
+    zip.file(
+        "OEBPS/title.xhtml",
+        Method::Deflated,
+        render(&title)?.as_bytes(),
+    )?;
+
+    for (i, link) in nav.chapters.iter().enumerate() {
+        let body = utils::chapter_body(db, id, &story, i + 1)?;
+
+        let clean = |notes: Vec<(&'static str, String)>| {
+            notes
+                .into_iter()
+                .map(|(name, notes)| (name, xhtml::from_html(&notes)))
+                .collect::<Vec<_>>()
+        };
+
+        let page = ChapterPage {
+            title: link.title.clone(),
+            summary: body.summary.as_deref().map(xhtml::from_html),
+            start_notes: clean(body.start_notes),
+            body: xhtml::from_html(&body.chapter),
+            end_notes: clean(body.end_notes),
+        };
+
+        zip.file(
+            &vfmt::format!("OEBPS/{}.xhtml", link.id),
+            Method::Deflated,
+            render(&page)?.as_bytes(),
+        )?;
+    }
+
+    zip.finish()
+}
+
+fn render<T: opal::Template>(template: &T) -> Result<String> {
+    template
+        .render_as_string()
+        .map_err(|err| anyhow!("unable to render epub file: {}", err))
+}
+
+/// EPUB wants the modified date as `YYYY-MM-DDThh:mm:ssZ` exactly, stories have whatever their site gave.
+fn modified(updated: &str) -> String {
+    let digits = |range: std::ops::Range<usize>| {
+        updated
+            .get(range)
+            .map(|part| part.bytes().all(|b| b.is_ascii_digit()))
+            .unwrap_or(false)
+    };
+
+    let date = digits(0..4)
+        && updated.get(4..5) == Some("-")
+        && digits(5..7)
+        && updated.get(7..8) == Some("-")
+        && digits(8..10);
+
+    let time = date
+        && matches!(updated.get(10..11), Some("T" | " "))
+        && digits(11..13)
+        && updated.get(13..14) == Some(":")
+        && digits(14..16)
+        && updated.get(16..17) == Some(":")
+        && digits(17..19);
+
+    if time {
+        vfmt::format!("{}T{}Z", &updated[..10], &updated[11..19])
+    } else if date {
+        vfmt::format!("{}T00:00:00Z", &updated[..10])
+    } else {
+        humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_modified() {
+        assert_eq!(
+            "2021-03-04T05:06:07Z",
+            modified("2021-03-04T05:06:07+00:00")
+        );
+        assert_eq!("2021-03-04T05:06:07Z", modified("2021-03-04 05:06:07"));
+        assert_eq!("2021-03-04T00:00:00Z", modified("2021-03-04"));
+        assert_eq!(20, modified("yesterday").len());
+    }
+}
@@ -0,0 +1,51 @@
+use common::{database::Database, models::Id, prelude::*};
+use opal::Template as _;
+
+use crate::{
+    export::{self, xhtml},
+    templates::export::{ExportChapter, StoryPage},
+    utils,
+};
+
+/// Builds a story as a single HTML page that doesn't need anything else to be read.
+pub fn build(db: &Database, id: &Id) -> Result<String> {
+    let story = utils::get_story_full(db, id)?;
+
+    let clean = |notes: Vec<(&'static str, String)>| {
+        notes
+            .into_iter()
+            .map(|(name, notes)| (name, xhtml::from_html(&notes)))
+            .collect::<Vec<_>>()
+    };
+
+    let chapters = (1..=story.chapters.len())
+        .map(|index| {
+            let body = utils::chapter_body(db, id, &story, index)?;
+
+            Ok(ExportChapter {
+                id: vfmt::format!("chapter-{}", index),
+                title: export::chapter_title(&story, index),
+                summary: body.summary.as_deref().map(xhtml::from_html),
+                start_notes: clean(body.start_notes),
+                body: xhtml::from_html(&body.chapter),
+                end_notes: clean(body.end_notes),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let page = StoryPage {
+        title: story.info.title.clone(),
+        authors: story
+            .meta
+            .authors
+            .iter()
+            .map(|author| author.text.clone())
+            .collect(),
+        details: export::details(&story),
+        summary: xhtml::from_html(&story.info.summary),
+        chapters,
+    };
+
+    page.render_as_string()
+        .map_err(|err| anyhow!("unable to render story: {}", err))
+}
@@ -0,0 +1,148 @@
+//! Turning stories into files for e-readers and other apps.
+
+pub mod bundle;
+pub mod epub;
+pub mod html;
+pub mod text;
+pub mod xhtml;
+
+use std::str::FromStr;
+
+use common::{
+    database::Database,
+    models::{Entity, Existing, Id, ResolvedStory},
+    prelude::*,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Epub,
+    Html,
+    Markdown,
+    Text,
+}
+
+impl ExportFormat {
+    pub const fn ext(self) -> &'static str {
+        match self {
+            ExportFormat::Epub => "epub",
+            ExportFormat::Html => "html",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Text => "txt",
+        }
+    }
+
+    pub const fn mime(self) -> &'static str {
+        match self {
+            ExportFormat::Epub => epub::MIME,
+            ExportFormat::Html => "text/html; charset=utf-8",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ExportFormat::Text => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = ExportFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "epub" => Ok(ExportFormat::Epub),
+            "html" => Ok(ExportFormat::Html),
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            "txt" | "text" => Ok(ExportFormat::Text),
+            ext => Err(ExportFormatError(vfmt::format!(
+                "Unknown export format: {}",
+                ext
+            ))),
+        }
+    }
+}
+
+pub struct ExportFormatError(pub String);
+
+impl vfmt::uDebug for ExportFormatError {
+    fn fmt<W>(&self, f: &mut vfmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: vfmt::uWrite + ?Sized,
+    {
+        f.write_str(&self.0)
+    }
+}
+
+/// Gets a story as the given format.
+pub fn export(db: &Database, id: &Id, format: ExportFormat) -> Result<Vec<u8>> {
+    match format {
+        ExportFormat::Epub => epub::cached(db, id),
+        ExportFormat::Html => html::build(db, id).map(String::into_bytes),
+        ExportFormat::Markdown => {
+            text::build(db, id, text::Style::Markdown).map(String::into_bytes)
+        }
+        ExportFormat::Text => text::build(db, id, text::Style::Plain).map(String::into_bytes),
+    }
+}
+
+/// A file name for the story that's safe everywhere, so only ASCII and without any path separators.
+pub fn file_name(title: &str, format: ExportFormat) -> String {
+    let name = title
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.' | ',' | '(' | ')') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    // a title with nothing readable left would give a file named only by its extension
+    let name = if name.chars().any(|c| c.is_ascii_alphanumeric()) {
+        name.trim()
+    } else {
+        "story"
+    };
+
+    vfmt::format!("{}.{}", name, format.ext())
+}
+
+fn join(entities: &[Existing<Entity>]) -> String {
+    entities
+        .iter()
+        .map(|entity| entity.text.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The story's details as a label and text, what the other formats show before the first chapter.
+pub fn details(story: &ResolvedStory) -> Vec<(&'static str, String)> {
+    let words = story
+        .chapters
+        .iter()
+        .map(|chapter| chapter.words)
+        .sum::<usize>();
+
+    let mut details = vec![
+        ("Rating", story.meta.rating.name().to_owned()),
+        ("Fandoms", join(&story.meta.origins)),
+        ("Warnings", join(&story.meta.warnings)),
+        ("Relationships", join(&story.meta.pairings)),
+        ("Characters", join(&story.meta.characters)),
+        ("Tags", join(&story.meta.generals)),
+        ("Published", story.info.created.clone()),
+        ("Updated", story.info.updated.clone()),
+        ("Chapters", vfmt::format!("{}", story.chapters.len())),
+        ("Words", vfmt::format!("{}", words)),
+    ];
+
+    details.retain(|(_, value)| !value.is_empty());
+
+    details
+}
+
+/// The chapter's title, or its number for stories that don't name them.
+pub fn chapter_title(story: &ResolvedStory, index: usize) -> String {
+    match story.chapters.get(index - 1) {
+        Some(chapter) if !chapter.title.is_empty() => chapter.title.clone(),
+        _ => vfmt::format!("Chapter {}", index),
+    }
+}
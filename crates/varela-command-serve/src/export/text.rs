@@ -0,0 +1,553 @@
+//! Plain text and Markdown versions of a story, written by walking the DOM of each chapter.
+
+use common::{database::Database, models::Id, prelude::*};
+use html_parser::{Dom, Node, NodeData};
+
+use crate::{export, export::xhtml, utils};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    Markdown,
+}
+
+/// Builds a story as plain text or Markdown, with its details at the top and a heading for each chapter.
+pub fn build(db: &Database, id: &Id, style: Style) -> Result<String> {
+    let story = utils::get_story_full(db, id)?;
+
+    let mut writer = Writer::new(style);
+
+    writer.heading(1, &story.info.title);
+
+    if !story.meta.authors.is_empty() {
+        writer.paragraph(&vfmt::format!("by {}", export::join(&story.meta.authors)));
+    }
+
+    writer.breaks(Break::Paragraph);
+
+    for (label, value) in export::details(&story) {
+        if style == Style::Markdown {
+            writer.raw("- **");
+            writer.text(label);
+            writer.raw(":** ");
+        } else {
+            writer.text(label);
+            writer.raw(": ");
+        }
+
+        writer.text(&value);
+        writer.breaks(Break::Line);
+    }
+
+    if !story.info.summary.is_empty() {
+        writer.heading(2, "Summary");
+        writer.html(&story.info.summary);
+    }
+
+    for index in 1..=story.chapters.len() {
+        let body = utils::chapter_body(db, id, &story, index)?;
+
+        writer.rule();
+        writer.heading(2, &export::chapter_title(&story, index));
+
+        if let Some(summary) = &body.summary {
+            writer.heading(3, "Summary");
+            writer.html(summary);
+        }
+
+        for (name, notes) in &body.start_notes {
+            writer.heading(3, &capitalize(name));
+            writer.html(notes);
+        }
+
+        if !body.start_notes.is_empty() || body.summary.is_some() {
+            writer.rule();
+        }
+
+        writer.html(&body.chapter);
+
+        if !body.end_notes.is_empty() {
+            writer.rule();
+        }
+
+        for (name, notes) in &body.end_notes {
+            writer.heading(3, &capitalize(name));
+            writer.html(notes);
+        }
+    }
+
+    Ok(writer.finish())
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Turns the entities left by [`xhtml::from_html`] back into characters.
+fn decode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+
+        rest = &rest[i..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                body => body
+                    .strip_prefix("#x")
+                    .or_else(|| body.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| body.strip_prefix('#').map(|dec| dec.parse::<u32>().ok()))
+                    .flatten()
+                    .and_then(char::from_u32),
+            };
+
+            c.map(|c| (c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+
+    out
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Break {
+    None,
+    Line,
+    Paragraph,
+}
+
+/// Writes text a word at a time, collapsing whitespace like a browser would.
+///
+/// Line breaks are held until there is something to write after them, so blocks that are next to each other only
+/// end up with one blank line between them, and there's none at the end.
+struct Writer {
+    style: Style,
+    out: String,
+    /// The break waiting to be written before the next word.
+    pending: Break,
+    /// Whether a space is waiting to be written before the next word.
+    space: bool,
+    /// How many block quotes the text is in.
+    quotes: usize,
+    /// The numbering of each list being written, `None` for unordered lists.
+    lists: Vec<Option<usize>>,
+    /// A list item's bullet or a heading's hashes, written at the start of the next line.
+    marker: Option<String>,
+}
+
+impl Writer {
+    fn new(style: Style) -> Self {
+        Self {
+            style,
+            out: String::new(),
+            // so the first line gets its prefix and marker too
+            pending: Break::Paragraph,
+            space: false,
+            quotes: 0,
+            lists: Vec::new(),
+            marker: None,
+        }
+    }
+
+    fn finish(mut self) -> String {
+        let len = self.out.trim_end().len();
+
+        self.out.truncate(len);
+        self.out.push('\n');
+
+        self.out
+    }
+
+    fn prefix(&self) -> String {
+        let quote = match self.style {
+            Style::Markdown => "> ",
+            Style::Plain => "    ",
+        };
+
+        quote.repeat(self.quotes)
+    }
+
+    fn breaks(&mut self, kind: Break) {
+        self.pending = self.pending.max(kind);
+        self.space = false;
+    }
+
+    /// Gets ready to write something inline, writing out any waiting breaks, prefixes, and spaces.
+    fn start(&mut self) {
+        if self.pending != Break::None {
+            if !self.out.is_empty() {
+                self.out.push('\n');
+
+                if self.pending == Break::Paragraph {
+                    self.out.push_str(self.prefix().trim_end());
+                    self.out.push('\n');
+                }
+            }
+
+            self.pending = Break::None;
+
+            let prefix = self.prefix();
+            self.out.push_str(&prefix);
+
+            if let Some(marker) = self.marker.take() {
+                self.out.push_str(&marker);
+            }
+        } else if self.space {
+            self.out.push(' ');
+        }
+
+        self.space = false;
+    }
+
+    fn raw(&mut self, text: &str) {
+        self.start();
+
+        self.out.push_str(text);
+    }
+
+    fn text(&mut self, text: &str) {
+        for c in text.chars() {
+            // non-breaking spaces are there on purpose, unless they're holding open an empty paragraph
+            if c.is_ascii_whitespace() || (c == '\u{a0}' && self.pending != Break::None) {
+                self.space = self.pending == Break::None && !self.out.is_empty();
+
+                continue;
+            }
+
+            self.start();
+
+            if self.style == Style::Markdown
+                && matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#')
+            {
+                self.out.push('\\');
+            }
+
+            self.out.push(c);
+        }
+    }
+
+    fn paragraph(&mut self, text: &str) {
+        self.breaks(Break::Paragraph);
+        self.text(text);
+        self.breaks(Break::Paragraph);
+    }
+
+    fn heading(&mut self, level: usize, text: &str) {
+        self.breaks(Break::Paragraph);
+
+        match self.style {
+            Style::Markdown => {
+                self.marker = Some(vfmt::format!("{} ", "#".repeat(level)));
+
+                self.text(text);
+            }
+            Style::Plain => {
+                self.text(text);
+
+                // underline the bigger headings, setext style, as there's no other way to make them stand out
+                if level <= 2 {
+                    let underline = if level == 1 { "=" } else { "-" };
+
+                    self.breaks(Break::Line);
+                    self.raw(&underline.repeat(text.chars().count()));
+                }
+            }
+        }
+
+        self.breaks(Break::Paragraph);
+    }
+
+    fn rule(&mut self) {
+        self.breaks(Break::Paragraph);
+        self.raw("* * *");
+        self.breaks(Break::Paragraph);
+    }
+
+    /// Writes a chunk of a story's HTML.
+    fn html(&mut self, html: &str) {
+        self.breaks(Break::Paragraph);
+
+        let html = xhtml::from_html(html);
+
+        match Dom::parse(&html) {
+            Ok(dom) => {
+                for node in &dom.children {
+                    self.node(node);
+                }
+            }
+            // the cleaned up html should always parse, but if not the text is better than nothing
+            Err(_) => {
+                let mut rest = html.as_str();
+
+                while let Some(start) = rest.find('<') {
+                    self.text(&decode(&rest[..start]));
+
+                    rest = rest[start..]
+                        .find('>')
+                        .map_or("", |end| &rest[start + end + 1..]);
+                }
+
+                self.text(&decode(rest));
+            }
+        }
+
+        self.breaks(Break::Paragraph);
+    }
+
+    fn children(&mut self, node: &Node<'_>) {
+        for child in &node.children {
+            self.node(child);
+        }
+    }
+
+    /// Writes inline markup around the node's children, if it's Markdown and they aren't empty.
+    fn wrapped(&mut self, node: &Node<'_>, open: &str, close: &str) {
+        if self.style == Style::Plain {
+            self.children(node);
+
+            return;
+        }
+
+        self.start();
+
+        let before = self.out.len();
+
+        self.out.push_str(open);
+
+        let after = self.out.len();
+
+        self.children(node);
+
+        if self.out.len() == after {
+            self.out.truncate(before);
+        } else {
+            // markers can't have a space on their inside, so it waits till after the marker
+            let space = self.space;
+
+            self.out.push_str(close);
+            self.space = space;
+        }
+    }
+
+    fn node(&mut self, node: &Node<'_>) {
+        // the parser drops the whitespace around text, so it's found again from where the node sits in the html
+        let input = node.span.get_input();
+
+        if input[..node.span.start()].ends_with(|c: char| c.is_ascii_whitespace()) {
+            self.text(" ");
+        }
+
+        self.visit(node);
+
+        if input[node.span.end()..].starts_with(|c: char| c.is_ascii_whitespace()) {
+            self.text(" ");
+        }
+    }
+
+    fn visit(&mut self, node: &Node<'_>) {
+        let element = match &node.data {
+            NodeData::Text { contains } => {
+                self.text(&decode(contains));
+
+                return;
+            }
+            NodeData::Comment { .. } => return,
+            NodeData::Element(element) => element,
+        };
+
+        let markdown = self.style == Style::Markdown;
+
+        match element.name {
+            "script" | "style" | "head" | "title" => {}
+            "br" => {
+                if markdown && self.pending == Break::None && !self.out.is_empty() {
+                    self.out.push_str("  ");
+                }
+
+                self.breaks(Break::Line);
+            }
+            "hr" => self.rule(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.breaks(Break::Paragraph);
+
+                if markdown {
+                    // the story's headings sit under the chapter's
+                    let level = (element.name.as_bytes()[1] - b'0') as usize;
+
+                    self.marker = Some(vfmt::format!("{} ", "#".repeat((level + 2).min(6))));
+                }
+
+                self.children(node);
+                self.breaks(Break::Paragraph);
+            }
+            "blockquote" => {
+                self.breaks(Break::Paragraph);
+                self.quotes += 1;
+                self.children(node);
+                self.breaks(Break::Paragraph);
+                self.quotes -= 1;
+            }
+            "ul" | "ol" => {
+                self.breaks(Break::Paragraph);
+                self.lists.push((element.name == "ol").then_some(1));
+                self.children(node);
+                self.lists.pop();
+                self.breaks(Break::Paragraph);
+            }
+            "li" => {
+                self.breaks(Break::Line);
+
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+
+                        vfmt::format!("{}{}. ", indent, *number - 1)
+                    }
+                    _ => vfmt::format!("{}- ", indent),
+                };
+
+                self.marker = Some(bullet);
+
+                self.children(node);
+                self.breaks(Break::Line);
+            }
+            "p" | "div" | "section" | "article" | "pre" | "center" | "table" | "dl" | "dd"
+            | "dt" | "figure" | "header" | "footer" => {
+                self.breaks(Break::Paragraph);
+                self.children(node);
+                self.breaks(Break::Paragraph);
+            }
+            "tr" => {
+                self.breaks(Break::Line);
+                self.children(node);
+                self.breaks(Break::Line);
+            }
+            "td" | "th" => {
+                self.space = true;
+                self.children(node);
+                self.space = true;
+            }
+            "em" | "i" | "cite" | "dfn" => self.wrapped(node, "*", "*"),
+            "strong" | "b" => self.wrapped(node, "**", "**"),
+            "s" | "del" | "strike" => self.wrapped(node, "~~", "~~"),
+            "code" | "kbd" | "samp" => self.wrapped(node, "`", "`"),
+            "a" => match element.attributes.get("href").copied().flatten() {
+                Some(href) if markdown && !href.starts_with('#') => {
+                    let href = decode(href);
+
+                    self.wrapped(node, "[", &vfmt::format!("]({})", href));
+                }
+                _ => self.children(node),
+            },
+            "img" => {
+                let alt = element.attributes.get("alt").copied().flatten().map(decode);
+                let src = element.attributes.get("src").copied().flatten().map(decode);
+
+                match (alt, src) {
+                    (alt, Some(src)) if markdown => {
+                        self.raw("![");
+                        self.text(alt.as_deref().unwrap_or(""));
+                        self.raw("](");
+                        self.out.push_str(&src);
+                        self.out.push(')');
+                    }
+                    (Some(alt), _) => self.text(&alt),
+                    _ => {}
+                }
+            }
+            _ => self.children(node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn convert(style: Style, html: &str) -> String {
+        let mut writer = Writer::new(style);
+
+        writer.html(html);
+
+        writer.finish()
+    }
+
+    #[test]
+    fn test_paragraphs() {
+        let html = "<p>One  two\n three.</p><p>Four<br>five &amp; six&nbsp;seven</p>";
+
+        assert_eq!(
+            "One two three.\n\nFour\nfive & six\u{a0}seven\n",
+            convert(Style::Plain, html)
+        );
+        assert_eq!(
+            "One two three.\n\nFour  \nfive & six\u{a0}seven\n",
+            convert(Style::Markdown, html)
+        );
+    }
+
+    #[test]
+    fn test_inline() {
+        let html = "<p>A <em>very </em><strong>bold</strong> <a href=\"https://example.com\">link</a><i></i> *here*</p>";
+
+        assert_eq!(
+            "A *very* **bold** [link](https://example.com) \\*here\\*\n",
+            convert(Style::Markdown, html)
+        );
+        assert_eq!("A very bold link *here*\n", convert(Style::Plain, html));
+    }
+
+    #[test]
+    fn test_blocks() {
+        let html = "<blockquote><p>one</p><p>two</p></blockquote><ol><li>a</li><li>b<ul><li>c</li></ul></li></ol><hr>end";
+
+        assert_eq!(
+            "> one\n>\n> two\n\n1. a\n2. b\n\n  - c\n\n* * *\n\nend\n",
+            convert(Style::Markdown, html)
+        );
+    }
+
+    #[test]
+    fn test_headings() {
+        let mut writer = Writer::new(Style::Plain);
+
+        writer.heading(1, "Title");
+        writer.paragraph("by someone");
+
+        assert_eq!("Title\n=====\n\nby someone\n", writer.finish());
+
+        let mut writer = Writer::new(Style::Markdown);
+
+        writer.heading(2, "Chapter 1");
+        writer.html("<h1>Part One</h1><p>text</p>");
+
+        assert_eq!("## Chapter 1\n\n### Part One\n\ntext\n", writer.finish());
+    }
+}
@@ -0,0 +1,325 @@
+//! Turns the HTML from story files into XHTML, e-readers refuse to open chapters that aren't well formed.
+
+/// Elements that can't have children, these get self closed.
+static VOID: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements that are closed by another of the same kind starting, like a `<p>` without its `</p>`.
+static SIBLINGS: [&str; 5] = ["dd", "dt", "li", "p", "tr"];
+
+/// The named HTML entities that show up in stories, as XML only knows the five it defines.
+static ENTITIES: [(&str, u32); 24] = [
+    ("nbsp", 160),
+    ("iexcl", 161),
+    ("copy", 169),
+    ("laquo", 171),
+    ("reg", 174),
+    ("deg", 176),
+    ("middot", 183),
+    ("raquo", 187),
+    ("iquest", 191),
+    ("eacute", 233),
+    ("times", 215),
+    ("ensp", 8194),
+    ("emsp", 8195),
+    ("thinsp", 8201),
+    ("ndash", 8211),
+    ("mdash", 8212),
+    ("lsquo", 8216),
+    ("rsquo", 8217),
+    ("ldquo", 8220),
+    ("rdquo", 8221),
+    ("bull", 8226),
+    ("hellip", 8230),
+    ("prime", 8242),
+    ("trade", 8482),
+];
+
+pub fn from_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() + html.len() / 8);
+    let mut open = Vec::<String>::new();
+
+    let mut rest = html;
+
+    while let Some(i) = rest.find(['<', '&']) {
+        text(&mut out, &rest[..i]);
+
+        rest = &rest[i..];
+
+        if rest.starts_with('&') {
+            rest = entity(&mut out, rest);
+        } else if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>').unwrap_or(after.len());
+
+            close(&mut out, &mut open, &name(&after[..end]));
+
+            rest = after.get(end + 1..).unwrap_or("");
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            rest = tag(&mut out, &mut open, &rest[1..]);
+        } else {
+            out.push_str("&lt;");
+
+            rest = &rest[1..];
+        }
+    }
+
+    text(&mut out, rest);
+
+    while let Some(name) = open.pop() {
+        out.push_str("</");
+        out.push_str(&name);
+        out.push('>');
+    }
+
+    out
+}
+
+fn text(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '>' => out.push_str("&gt;"),
+            // control characters aren't allowed anywhere in XML
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c < ' ' => {}
+            c => out.push(c),
+        }
+    }
+}
+
+/// Writes out the entity at the start of `rest`, escaping the `&` if it isn't one, returning what comes after it.
+fn entity<'i>(out: &mut String, rest: &'i str) -> &'i str {
+    let body = rest[1..]
+        .find(';')
+        .filter(|end| *end <= 32)
+        .map(|end| &rest[1..end + 1]);
+
+    let Some(body) = body else {
+        out.push_str("&amp;");
+
+        return &rest[1..];
+    };
+
+    let numeric = body
+        .strip_prefix("#x")
+        .or_else(|| body.strip_prefix("#X"))
+        .map(|hex| u32::from_str_radix(hex, 16))
+        .or_else(|| body.strip_prefix('#').map(str::parse::<u32>));
+
+    let valid = match numeric {
+        Some(number) => number.ok().and_then(char::from_u32).is_some(),
+        None if matches!(body, "amp" | "lt" | "gt" | "quot" | "apos") => true,
+        None => {
+            if let Some((_, number)) = ENTITIES.iter().find(|(name, _)| *name == body) {
+                out.push_str(&vfmt::format!("&#{};", number));
+
+                return &rest[body.len() + 2..];
+            }
+
+            false
+        }
+    };
+
+    if valid {
+        out.push('&');
+        out.push_str(body);
+        out.push(';');
+
+        &rest[body.len() + 2..]
+    } else {
+        out.push_str("&amp;");
+
+        &rest[1..]
+    }
+}
+
+fn name(text: &str) -> String {
+    text.trim()
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':'))
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+fn close(out: &mut String, open: &mut Vec<String>, name: &str) {
+    // a closing tag for something that was never opened would break the nesting, so it's dropped
+    if !open.iter().any(|open| open == name) {
+        return;
+    }
+
+    while let Some(open) = open.pop() {
+        out.push_str("</");
+        out.push_str(&open);
+        out.push('>');
+
+        if open == name {
+            break;
+        }
+    }
+}
+
+/// Writes out the tag at the start of `rest`, just after the `<`, returning what comes after it.
+fn tag<'i>(out: &mut String, open: &mut Vec<String>, rest: &'i str) -> &'i str {
+    let name = name(rest);
+
+    let mut rest = &rest[name.len()..];
+
+    if SIBLINGS.contains(&name.as_str()) && open.last() == Some(&name) {
+        close(out, open, &name);
+    }
+
+    out.push('<');
+    out.push_str(&name);
+
+    let mut written = Vec::<String>::new();
+    let mut self_closed = false;
+
+    loop {
+        rest = rest.trim_start();
+
+        if let Some(after) = rest.strip_prefix("/>") {
+            self_closed = true;
+            rest = after;
+
+            break;
+        }
+
+        if let Some(after) = rest.strip_prefix('>') {
+            rest = after;
+
+            break;
+        }
+
+        if rest.is_empty() {
+            break;
+        }
+
+        let end = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+            .unwrap_or(rest.len())
+            .max(1);
+
+        let attribute = rest[..end].to_ascii_lowercase();
+
+        rest = rest[end..].trim_start();
+
+        let mut value = None;
+
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+
+            let (raw, remaining) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let after = &after[1..];
+                    let end = after.find(quote).unwrap_or(after.len());
+
+                    (&after[..end], after.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(after.len());
+
+                    (&after[..end], &after[end..])
+                }
+            };
+
+            value = Some(raw);
+            rest = remaining;
+        }
+
+        let valid = attribute.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && attribute
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'));
+
+        if !valid || written.contains(&attribute) {
+            continue;
+        }
+
+        out.push(' ');
+        out.push_str(&attribute);
+        out.push_str("=\"");
+
+        // html allows attributes without values, like `hidden`, xml wants them spelled out
+        let value = value.unwrap_or(&attribute);
+
+        let mut value_rest = value;
+
+        while let Some(i) = value_rest.find(['&', '<', '"']) {
+            text(out, &value_rest[..i]);
+
+            value_rest = &value_rest[i..];
+
+            if value_rest.starts_with('&') {
+                value_rest = entity(out, value_rest);
+            } else {
+                out.push_str(if value_rest.starts_with('<') {
+                    "&lt;"
+                } else {
+                    "&quot;"
+                });
+
+                value_rest = &value_rest[1..];
+            }
+        }
+
+        text(out, value_rest);
+
+        out.push('"');
+
+        written.push(attribute);
+    }
+
+    if self_closed || VOID.contains(&name.as_str()) {
+        out.push_str("/>");
+    } else {
+        out.push('>');
+
+        open.push(name);
+    }
+
+    rest
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_void_elements() {
+        assert_eq!(
+            "<p>one<br/>two<hr/><img src=\"a.png\" alt=\"\"/></p>",
+            from_html("<P>one<br>two<hr><img src=a.png alt=\"\"></p>")
+        );
+    }
+
+    #[test]
+    fn test_entities() {
+        assert_eq!(
+            "<p>fish &amp; chips&#160;&amp;nope; &#8212; &amp;&#x2014;&lt;3</p>",
+            from_html("<p>fish & chips&nbsp;&nope; &mdash; &&#x2014;<3</p>")
+        );
+    }
+
+    #[test]
+    fn test_nesting() {
+        assert_eq!(
+            "<div><p>one</p><p>two<em>three</em></p></div>",
+            from_html("<div><p>one<p>two<em>three</div></span>")
+        );
+    }
+
+    #[test]
+    fn test_attributes() {
+        assert_eq!(
+            "<p class=\"a &quot;b&quot;\" hidden=\"hidden\" title=\"x &amp; y\">text</p>",
+            from_html("<p class='a \"b\"' hidden title=\"x & y\" class=c>text<!-- gone --></p>")
+        );
+    }
+}
@@ -40,3 +40,44 @@ impl<B: AsRef<[u8]>> opal::Template for PercentEncode<B> {
         len + (len / 2)
     }
 }
+
+pub fn escape<S: AsRef<str>>(text: S) -> Escape<S> {
+    Escape(text)
+}
+
+pub struct Escape<S>(S);
+
+impl<S: AsRef<str>> opal::Template for Escape<S> {
+    fn render<W>(&self, writer: &mut W) -> Result<(), W::Error>
+    where
+        W: opal::io::Write,
+    {
+        let text = self.0.as_ref();
+
+        let mut last = 0;
+
+        for (i, c) in text.char_indices() {
+            let replacement = match c {
+                '&' => "&amp;",
+                '<' => "&lt;",
+                '>' => "&gt;",
+                '"' => "&quot;",
+                '\'' => "&#39;",
+                _ => continue,
+            };
+
+            writer.write_str(&text[last..i])?;
+            writer.write_str(replacement)?;
+
+            last = i + c.len_utf8();
+        }
+
+        writer.write_str(&text[last..])?;
+
+        Ok(())
+    }
+
+    fn size_hint(&self) -> usize {
+        self.0.as_ref().len()
+    }
+}
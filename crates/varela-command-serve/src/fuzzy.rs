@@ -0,0 +1,228 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+/// The lowest [`similarity`] a candidate can have to still be suggested.
+pub const THRESHOLD: f32 = 0.3;
+
+/// Folds text into a form that can be loosely compared.
+///
+/// Case is folded, accents and other combining marks are stripped (so `Pokémon` becomes
+/// `pokemon`), ligatures are expanded and runs of whitespace are collapsed.
+///
+/// This is not full Unicode normalization, precomposed characters are only broken down for
+/// Latin script through [`decompose`], so accented Greek, Cyrillic or Vietnamese letters
+/// are kept as they are unless they are written with combining marks.
+pub fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+
+    for (i, word) in text.split_whitespace().enumerate() {
+        if i != 0 {
+            folded.push(' ');
+        }
+
+        for c in word.chars().flat_map(char::to_lowercase) {
+            if is_combining(c) {
+                continue;
+            }
+
+            match decompose(c) {
+                Some(base) => folded.push_str(base),
+                None => folded.push(c),
+            }
+        }
+    }
+
+    folded
+}
+
+/// Returns the trigram similarity of two already folded strings, from `0.0` (nothing in
+/// common) to `1.0` (identical).
+pub fn similarity(left: &str, right: &str) -> f32 {
+    let left = trigrams(left);
+    let right = trigrams(right);
+
+    if left.is_empty() || right.is_empty() {
+        return 0.0;
+    }
+
+    let (mut l, mut r, mut shared) = (0, 0, 0);
+
+    while l < left.len() && r < right.len() {
+        match left[l].cmp(&right[r]) {
+            Ordering::Less => l += 1,
+            Ordering::Greater => r += 1,
+            Ordering::Equal => {
+                shared += 1;
+                l += 1;
+                r += 1;
+            }
+        }
+    }
+
+    shared as f32 / (left.len() + right.len() - shared) as f32
+}
+
+/// Finds the `limit` candidates most similar to `term`, best first.
+///
+/// Candidates that fold to the same text are only returned once.
+pub fn closest<'c, I>(candidates: I, term: &str, limit: usize) -> Vec<&'c str>
+where
+    I: Iterator<Item = &'c str>,
+{
+    let term = fold(term);
+
+    let mut best = HashMap::<String, (f32, &'c str)>::new();
+
+    for candidate in candidates {
+        let folded = fold(candidate);
+        let score = similarity(&folded, &term);
+
+        if score < THRESHOLD {
+            continue;
+        }
+
+        best.entry(folded)
+            .and_modify(|kept| {
+                if rank(&(score, candidate), kept).is_lt() {
+                    *kept = (score, candidate);
+                }
+            })
+            .or_insert((score, candidate));
+    }
+
+    let mut scored = best.into_values().collect::<Vec<_>>();
+
+    scored.sort_by(rank);
+    scored.truncate(limit);
+
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+/// Orders scored candidates best first, breaking ties alphabetically.
+fn rank(a: &(f32, &str), b: &(f32, &str)) -> Ordering {
+    b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1))
+}
+
+fn trigrams(text: &str) -> Vec<[char; 3]> {
+    let padded = core::iter::repeat(' ')
+        .take(2)
+        .chain(text.chars())
+        .chain(core::iter::once(' '))
+        .collect::<Vec<_>>();
+
+    let mut grams = padded
+        .windows(3)
+        .map(|window| [window[0], window[1], window[2]])
+        .collect::<Vec<_>>();
+
+    grams.sort_unstable();
+    grams.dedup();
+
+    grams
+}
+
+#[inline]
+fn is_combining(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// Maps a lowercase precomposed Latin character to its unaccented base.
+///
+/// Only covers Latin-1 Supplement, Latin Extended-A and a few punctuation marks, anything
+/// else is left for [`fold`] to keep as is.
+#[rustfmt::skip]
+fn decompose(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' | 'ŉ' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ș' => "s",
+        'ţ' | 'ť' | 'ŧ' | 'ț' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        'æ' => "ae",
+        'œ' => "oe",
+        'ß' => "ss",
+        'þ' => "th",
+        '’' | '‘' => "'",
+        '“' | '”' => "\"",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fold_accents() {
+        assert_eq!("pokemon", fold("Pokémon"));
+        assert_eq!(fold("Pokemon"), fold("POKÉMON"));
+    }
+
+    #[test]
+    fn test_fold_combining() {
+        assert_eq!("pokemon", fold("Poke\u{0301}mon"));
+    }
+
+    #[test]
+    fn test_fold_ligatures() {
+        assert_eq!("strasse", fold("Straße"));
+        assert_eq!("aeon", fold("Æon"));
+    }
+
+    #[test]
+    fn test_fold_whitespace() {
+        assert_eq!("harry potter", fold("  Harry \t Potter "));
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(1.0, similarity("pokemon", "pokemon"));
+        assert_eq!(0.0, similarity("pokemon", ""));
+        assert!(similarity("pokmon", "pokemon") >= THRESHOLD);
+        assert!(similarity("digimon", "harry potter") < THRESHOLD);
+    }
+
+    #[test]
+    fn test_closest() {
+        let candidates = ["Pokémon", "Digimon", "Harry Potter", "Pokemon"];
+
+        assert_eq!(
+            vec!["Pokemon"],
+            closest(candidates.iter().copied(), "pokmon", 3)
+        );
+        assert_eq!(
+            vec!["Harry Potter"],
+            closest(candidates.iter().copied(), "hary poter", 3)
+        );
+    }
+
+    #[test]
+    fn test_closest_dedup() {
+        let candidates = ["Pokémon", "Digimon", "Pokemon!", "POKEMON"];
+
+        assert_eq!(
+            vec!["POKEMON", "Pokemon!"],
+            closest(candidates.iter().copied(), "pokemon", 2)
+        );
+    }
+}
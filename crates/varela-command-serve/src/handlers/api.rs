@@ -0,0 +1,88 @@
+use std::{str::FromStr, sync::RwLock};
+
+use common::{database::Database, models::EntityKind};
+use enrgy::{
+    extractor,
+    response::{IntoResponse, Json},
+};
+
+use crate::{json, search, templates::pages, utils};
+
+pub struct CompleteKind(EntityKind);
+
+impl FromStr for CompleteKind {
+    type Err = CompleteKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "author" => Ok(CompleteKind(EntityKind::Author)),
+            "warning" => Ok(CompleteKind(EntityKind::Warning)),
+            "origin" | "fandom" => Ok(CompleteKind(EntityKind::Origin)),
+            "pairing" => Ok(CompleteKind(EntityKind::Pairing)),
+            "character" => Ok(CompleteKind(EntityKind::Character)),
+            "general" => Ok(CompleteKind(EntityKind::General)),
+            kind => Err(CompleteKindError(vfmt::format!(
+                "Unknown entity kind: {}",
+                kind
+            ))),
+        }
+    }
+}
+
+pub struct CompleteKindError(String);
+
+impl vfmt::uDebug for CompleteKindError {
+    fn fmt<W>(&self, f: &mut vfmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: vfmt::uWrite + ?Sized,
+    {
+        f.write_str(&self.0)
+    }
+}
+
+pub struct KindParam;
+
+impl enrgy::extractor::query::QueryKey for KindParam {
+    const KEY: &'static str = "kind";
+}
+
+pub struct QParam;
+
+impl enrgy::extractor::query::QueryKey for QParam {
+    const KEY: &'static str = "q";
+}
+
+pub fn complete(
+    db: extractor::Data<RwLock<Database>>,
+    kind: extractor::ParseQuery<KindParam, CompleteKind>,
+    query: extractor::OptionalQuery<QParam>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
+    let query = query.as_deref().unwrap_or_default().trim();
+
+    let completions = search::complete(db.index(), kind.0, query, 10);
+
+    let mut body = String::from("[");
+
+    for (i, completion) in completions.iter().enumerate() {
+        if i != 0 {
+            body.push(',');
+        }
+
+        body.push('{');
+        json::key(&mut body, "id");
+        json::string(&mut body, completion.id.as_str());
+        body.push(',');
+        json::key(&mut body, "text");
+        json::string(&mut body, completion.text);
+        body.push(',');
+        json::key(&mut body, "count");
+        json::number(&mut body, completion.count);
+        body.push('}');
+    }
+
+    body.push(']');
+
+    Ok(Json(body))
+}
@@ -0,0 +1,157 @@
+use std::sync::RwLock;
+
+use common::{database::Database, models::EntityKind};
+use enrgy::{extractor, response::IntoResponse};
+
+use crate::{
+    fuzzy,
+    handlers::Template,
+    search,
+    templates::{
+        pages::{self, browse::BrowseGroup},
+        partials::{Contrast, Link},
+        Layout, Width,
+    },
+    utils,
+};
+
+/// The browse pages, in the order they are linked to each other.
+static KINDS: [(EntityKind, &str, &str); 6] = [
+    (EntityKind::Origin, "/fandoms", "fandoms"),
+    (EntityKind::Author, "/authors", "authors"),
+    (EntityKind::Character, "/characters", "characters"),
+    (EntityKind::Pairing, "/pairings", "pairings"),
+    (EntityKind::Warning, "/warnings", "warnings"),
+    (EntityKind::General, "/tags", "tags"),
+];
+
+/// The heading of entities that don't start with a letter.
+static OTHER: &str = "#";
+
+pub struct FilterParam;
+
+impl enrgy::extractor::query::QueryKey for FilterParam {
+    const KEY: &'static str = "filter";
+}
+
+macro_rules! browse_handler {
+    ($( $name:ident => $kind:expr , )*) => {
+        $(
+            pub fn $name(
+                db: extractor::Data<RwLock<Database>>,
+                filter: extractor::OptionalQuery<FilterParam>,
+            ) -> Result<impl IntoResponse, pages::Error> {
+                browse(&db, $kind, filter.as_deref().unwrap_or_default())
+            }
+        )*
+    };
+}
+
+browse_handler! {
+    fandoms => EntityKind::Origin,
+    authors => EntityKind::Author,
+    characters => EntityKind::Character,
+    pairings => EntityKind::Pairing,
+    warnings => EntityKind::Warning,
+    tags => EntityKind::General,
+}
+
+fn href(kind: EntityKind, id: &str) -> String {
+    match kind {
+        EntityKind::Author => vfmt::format!("/author/{}", id),
+        EntityKind::Origin => vfmt::format!("/origin/{}", id),
+        _ => vfmt::format!("/tag/{}", id),
+    }
+}
+
+fn browse(
+    db: &RwLock<Database>,
+    kind: EntityKind,
+    filter: &str,
+) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(db)?;
+
+    let (_, path, name) = KINDS
+        .iter()
+        .find(|(k, _, _)| *k == kind)
+        .copied()
+        .ok_or(pages::Error::not_found())?;
+
+    let default_filter = search::DefaultFilter::new(db.settings(), db.library());
+
+    let folded_filter = fuzzy::fold(filter);
+
+    let mut entities = search::entity_counts(
+        db.index(),
+        db.index()
+            .stories
+            .iter()
+            .filter(|(id, story)| default_filter.allows(id, story))
+            .map(|(_, story)| story),
+        kind,
+    )
+    .into_iter()
+    .map(|(id, entity, count)| (fuzzy::fold(&entity.text), id, entity, count))
+    .filter(|(folded, _, _, _)| folded.contains(&folded_filter))
+    .collect::<Vec<_>>();
+
+    entities.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut groups = Vec::<BrowseGroup>::new();
+
+    for (folded, id, entity, count) in entities {
+        let letter = match folded.chars().next() {
+            Some(c) if c.is_ascii_alphabetic() => c.to_ascii_uppercase().to_string(),
+            _ => OTHER.to_owned(),
+        };
+
+        let link = Link::new(Contrast::High, href(kind, id.as_str()), entity.text.clone());
+
+        match groups.iter_mut().find(|group| group.letter == letter) {
+            Some(group) => group.entities.push((link, count)),
+            None => groups.push(BrowseGroup {
+                letter,
+                entities: vec![(link, count)],
+            }),
+        }
+    }
+
+    // entities starting with digits or symbols sort before letters, keep them up top
+    groups.sort_by_key(|group| group.letter != OTHER);
+
+    let letters = core::iter::once(OTHER.to_owned())
+        .chain(('A'..='Z').map(|c| c.to_string()))
+        .map(|letter| {
+            let present = groups.iter().any(|group| group.letter == letter);
+
+            (letter, present)
+        })
+        .collect();
+
+    let kinds = KINDS
+        .iter()
+        .map(|(k, path, name)| {
+            let contrast = if *k == kind {
+                Contrast::High
+            } else {
+                Contrast::Low
+            };
+
+            Link::new(contrast, (*path).to_owned(), (*name).to_owned())
+        })
+        .collect();
+
+    Ok(Template(Layout::new(
+        Width::Slim,
+        db.settings().theme,
+        name,
+        None,
+        pages::Browse {
+            path,
+            kinds,
+            filter: filter.to_owned(),
+            letters,
+            groups,
+        },
+    )))
+}
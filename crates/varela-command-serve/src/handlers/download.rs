@@ -1,26 +1,40 @@
-use std::fs;
+use std::sync::RwLock;
 
-use common::{database::Database, prelude::*};
+use common::{
+    database::Database,
+    models::{DownloadStatus, Id},
+    prelude::*,
+};
 use enrgy::{extractor, response::IntoResponse};
 
 use crate::{
-    handlers::Template,
+    export::{self, ExportFormat},
+    handlers::{self, Template},
+    queue::{self, Waker},
     templates::{pages, Layout, Width},
     utils,
 };
 
-pub fn download_get(db: extractor::Data<Database>) -> impl IntoResponse {
-    Template(Layout::new(
+pub fn download_get(
+    db: extractor::Data<RwLock<Database>>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
+    Ok(Template(Layout::new(
         Width::Slim,
         db.settings().theme,
         "downloads",
         None,
         pages::Download::new(),
-    ))
+    )))
 }
 
+/// Adds the work from a link to it on AO3 to the download queue, then sends the reader to the queue.
+///
+/// Any other link is downloaded straight away, and the reader is sent to the story if it could be indexed.
 pub fn download_post(
-    db: extractor::Data<Database>,
+    db: extractor::Data<RwLock<Database>>,
+    waker: extractor::Data<Waker>,
     body: extractor::Body,
 ) -> Result<impl IntoResponse, pages::Error> {
     let mut parse = enrgy::http::encoding::form::parse(&body);
@@ -29,40 +43,147 @@ pub fn download_post(
         .find(|(key, _)| key == "download")
         .ok_or(pages::Error::bad_request())?;
 
-    fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
-        haystack
-            .windows(needle.len())
-            .position(|window| window == needle)
+    let result = if format_ao3::site::is_archive(&url) {
+        queue::push(&db, &url).map(|_| {
+            waker.wake();
+
+            "/downloads".to_string()
+        })
+    } else {
+        download_direct(&db, &url).map(|id| vfmt::format!("/story/{}/1", id.as_str()))
+    };
+
+    match result {
+        Ok(location) => Ok(handlers::redirect(&location)),
+        Err(err) => {
+            let db = utils::read(&db)?;
+
+            Ok(Template(Layout::new(
+                Width::Slim,
+                db.settings().theme,
+                "downloads",
+                None,
+                pages::Download::failed(url.into_owned(), format!("{:#}", err)),
+            ))
+            .into_response())
+        }
     }
+}
+
+/// Downloads the link into the data directory, naming the file after the page's title, and indexes it.
+fn download_direct(db: &RwLock<Database>, url: &str) -> Result<Id> {
+    let bytes = utils::http::get(url)?;
+
+    let text = String::from_utf8_lossy(&bytes);
 
-    let bytes = utils::http::get(&db.temp_path, &url)?;
+    // titles are like `Work Title - Author - Fandom`
+    let title = text
+        .split_once("<title>")
+        .and_then(|(_, rest)| rest.split_once("</title>"))
+        .map(|(title, _)| title.split(" - ").next().unwrap_or(title).trim())
+        .ok_or_else(|| anyhow!("unable to find the title of `{}`", url))?;
 
-    static TARGET_START: &[u8; 7] = b"<title>";
-    static TARGET_END: &[u8; 8] = b"</title>";
+    let name = export::file_name(title, ExportFormat::Html);
 
-    let start = find_subsequence(&bytes[..], &TARGET_START[..])
-        .ok_or_else(|| anyhow!("Unable to find start of page title"))?;
-    let end = find_subsequence(&bytes[..], &TARGET_END[..])
-        .ok_or_else(|| anyhow!("Unable to find end of page title"))?;
+    let mut db = utils::write(db)?;
 
-    let whole_title = &bytes[(start + TARGET_START.len())..end];
+    let id = utils::store(&mut db, &name, &bytes)?.ok_or_else(|| {
+        anyhow!(
+            "saved as `{}`, but it isn't a story that can be indexed",
+            name
+        )
+    })?;
 
-    let first_dash = find_subsequence(whole_title, b" - ")
-        .ok_or_else(|| anyhow!("Unable to find title separator"))?;
+    db.save()?;
+
+    utils::uncache_story(&id)?;
+
+    Ok(id)
+}
 
-    let title = &whole_title[0..first_dash];
+pub fn downloads(db: extractor::Data<RwLock<Database>>) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
 
-    let save_path = db
-        .data_path
-        .join(vfmt::format!("{}.html", String::from_utf8(title.to_vec())?));
+    let entries = db
+        .downloads()
+        .iter()
+        .map(|download| {
+            let story = download.story.as_ref().and_then(|id| {
+                db.index().stories.get(id).map(|story| {
+                    (
+                        vfmt::format!("/story/{}/1", id.as_str()),
+                        story.info.title.clone(),
+                    )
+                })
+            });
 
-    fs::write(save_path, bytes)?;
+            pages::DownloadEntry {
+                url: download.url.clone(),
+                work: download.work.clone(),
+                status: download.status.name(),
+                error: download.error.clone(),
+                attempts: download.attempts,
+                updated: download.updated.clone(),
+                retry_at: download
+                    .retry_at
+                    .clone()
+                    .filter(|_| download.status == DownloadStatus::Queued),
+                story,
+                retry: download.status == DownloadStatus::Failed,
+                remove: download.status != DownloadStatus::Downloading,
+            }
+        })
+        .collect();
 
     Ok(Template(Layout::new(
         Width::Slim,
         db.settings().theme,
         "downloads",
         None,
-        pages::Download::new(),
+        pages::Downloads::new(entries),
     )))
 }
+
+/// Retries or removes a download, clears the ones that have been indexed, or checks followed works for updates.
+pub fn downloads_post(
+    db: extractor::Data<RwLock<Database>>,
+    waker: extractor::Data<Waker>,
+    body: extractor::Body,
+) -> Result<impl IntoResponse, pages::Error> {
+    let form = enrgy::http::encoding::form::parse(&body).collect::<Vec<_>>();
+
+    let field = |key: &str| {
+        form.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.trim())
+    };
+
+    let work = || {
+        field("work")
+            .filter(|work| !work.is_empty())
+            .ok_or(pages::Error::bad_request())
+    };
+
+    match field("action").ok_or(pages::Error::bad_request())? {
+        "retry" => {
+            let work = work()?;
+
+            let url = utils::read(&db)?
+                .downloads()
+                .iter()
+                .find(|download| download.work == work)
+                .map(|download| download.url.clone())
+                .ok_or(pages::Error::not_found())?;
+
+            queue::push(&db, &url)?;
+
+            waker.wake();
+        }
+        "remove" => queue::remove(&db, work()?)?,
+        "clear" => queue::clear(&db)?,
+        "check" => waker.check(),
+        _ => return Err(pages::Error::bad_request()),
+    }
+
+    Ok(handlers::redirect("/downloads"))
+}
@@ -1,3 +1,5 @@
+use std::sync::RwLock;
+
 use common::{
     database::Database,
     models::{EntityKind, Id},
@@ -7,7 +9,8 @@ use enrgy::{extractor, response::IntoResponse};
 
 use crate::{
     handlers::Template,
-    templates::{pages, partials, Layout, Width},
+    search,
+    templates::{pages, partials, Layout, TagKind, Width},
     utils,
 };
 
@@ -18,46 +21,61 @@ impl enrgy::extractor::param::ParamKey for IdParam {
 }
 
 pub fn entity(
-    db: extractor::Data<Database>,
+    db: extractor::Data<RwLock<Database>>,
     id: extractor::ParseParam<IdParam, Id>,
 ) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
     let kind = db
         .get_entity_from_id(&id)
         .ok_or(pages::Error::not_found())?;
 
-    let entity = {
-        let entities = match kind {
-            EntityKind::Author => &db.index().authors,
-            EntityKind::Warning => &db.index().warnings,
-            EntityKind::Origin => &db.index().origins,
-            EntityKind::Pairing => &db.index().pairings,
-            EntityKind::Character => &db.index().characters,
-            EntityKind::General => &db.index().generals,
-        };
+    let entity = unsafe { db.index().entities(kind).get(&*id).unwrap_unchecked() };
 
-        unsafe { entities.get(&*id).unwrap_unchecked() }
-    };
+    let filter = search::DefaultFilter::new(db.settings(), db.library());
 
-    let mut stories = db
+    let matched = db
         .index()
         .stories
         .iter()
-        .filter(|(_, story)| {
-            let entities = match kind {
-                EntityKind::Author => &story.meta.authors,
-                EntityKind::Warning => &story.meta.warnings,
-                EntityKind::Origin => &story.meta.origins,
-                EntityKind::Pairing => &story.meta.pairings,
-                EntityKind::Character => &story.meta.characters,
-                EntityKind::General => &story.meta.generals,
+        .filter(|(story_id, story)| {
+            story.meta.entities(kind).contains(&id) && filter.allows(story_id, story)
+        })
+        .collect::<Vec<_>>();
+
+    // within a fandom show what characters and pairings it's written with
+    let related = (kind == EntityKind::Origin)
+        .then(|| {
+            let group = |name, tag_kind, kind| partials::RelatedGroup {
+                name,
+                kind: tag_kind,
+                entities: search::entity_counts(
+                    db.index(),
+                    matched.iter().map(|(_, story)| *story),
+                    kind,
+                )
+                .into_iter()
+                .map(|(id, entity, count)| (id.clone(), entity.text.clone(), count))
+                .collect(),
             };
 
-            entities.contains(&id)
-        })
-        .map(|(id, _)| {
-            utils::get_story_full(&db, id)
-                .and_then(|story| partials::StoryPartial::new(id.clone(), story, None))
+            partials::Related {
+                groups: vec![
+                    group("characters", TagKind::Character, EntityKind::Character),
+                    group("pairings", TagKind::Pairing, EntityKind::Pairing),
+                ],
+            }
         })
+        .filter(|related| {
+            related
+                .groups
+                .iter()
+                .any(|group| !group.entities.is_empty())
+        });
+
+    let mut stories = matched
+        .into_iter()
+        .map(|(id, _)| partials::StoryPartial::load(&db, id, None))
         .collect::<Result<Vec<_>>>()?;
 
     stories.sort_by(|a, b| a.title().cmp(b.title()));
@@ -67,6 +85,9 @@ pub fn entity(
         db.settings().theme,
         &entity.text,
         None,
-        pages::Index::new(stories),
+        pages::Index {
+            related,
+            ..pages::Index::new(stories)
+        },
     )))
 }
@@ -0,0 +1,234 @@
+use std::{
+    io,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use common::{database::Database, models::Id, prelude::*};
+use enrgy::{
+    extractor,
+    http::{
+        encoding::percent::{percent_decode, utf8_percent_encode, NON_ALPHANUMERIC},
+        headers::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        HttpResponse, HttpStream,
+    },
+    response::IntoResponse,
+};
+
+use crate::{
+    export::{
+        self,
+        bundle::{self, BundleFormat, Entry},
+        ExportFormat, ExportFormatError,
+    },
+    handlers::library::{shelf_text, NameParam},
+    search,
+    templates::pages,
+    utils,
+};
+
+/// A path parameter naming a story along with the format to download it as, like `N26FPXqr.epub`.
+pub struct ExportTarget {
+    id: Id,
+    format: ExportFormat,
+}
+
+impl FromStr for ExportTarget {
+    type Err = ExportFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (id, ext) = s
+            .rsplit_once('.')
+            .ok_or_else(|| ExportFormatError(vfmt::format!("Missing export format: {}", s)))?;
+
+        Ok(ExportTarget {
+            id: Id::from(id.to_owned()),
+            format: ext.parse()?,
+        })
+    }
+}
+
+pub struct IdParam;
+
+impl enrgy::extractor::param::ParamKey for IdParam {
+    const KEY: &'static str = "id";
+}
+
+/// Builds the `Content-Disposition` for a download, named after the story.
+///
+/// Older clients only understand the plain `filename`, so it gets an ASCII version of the title.
+pub(super) fn attachment(title: &str, format: ExportFormat) -> String {
+    vfmt::format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}.{}",
+        export::file_name(title, format),
+        utf8_percent_encode(title, NON_ALPHANUMERIC).to_string(),
+        format.ext()
+    )
+}
+
+pub fn export(
+    db: extractor::Data<RwLock<Database>>,
+    target: extractor::ParseParam<IdParam, ExportTarget>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
+    let ExportTarget { id, format } = &*target;
+
+    let story = db
+        .index()
+        .stories
+        .get(id)
+        .ok_or(pages::Error::not_found())?;
+
+    let bytes = export::export(&db, id, *format)?;
+
+    Ok(HttpResponse::ok()
+        .header(CONTENT_TYPE, format.mime())
+        .header(CONTENT_DISPOSITION, attachment(&story.info.title, *format))
+        .body(bytes))
+}
+
+pub struct FormatParam;
+
+impl enrgy::extractor::query::QueryKey for FormatParam {
+    const KEY: &'static str = "format";
+}
+
+/// Streams the stories out as a zip.
+fn bundle_response(
+    db: Arc<RwLock<Database>>,
+    entries: Vec<Entry>,
+    format: BundleFormat,
+    name: &str,
+) -> HttpResponse {
+    let stream = HttpStream::new(move |out| {
+        bundle::write(&db, &entries, format, out)
+            .map(|_| ())
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
+    });
+
+    HttpResponse::ok()
+        .header(CONTENT_TYPE, bundle::MIME)
+        .header(
+            CONTENT_DISPOSITION,
+            vfmt::format!("attachment; filename=\"{}\"", name),
+        )
+        .body(stream)
+}
+
+/// Downloads every story matching a `/search2` query as a zip.
+///
+/// Adding `format=epub` to the query gets EPUBs rather than the original files.
+pub fn search_download(
+    db: extractor::Data<RwLock<Database>>,
+    query: extractor::OptionalRawQuery,
+) -> Result<impl IntoResponse, pages::Error> {
+    let (entries, format) = {
+        let db = utils::read(&db)?;
+
+        let query = search::parse_query(query.as_deref().unwrap_or_default());
+
+        let filter = search::DefaultFilter::new(db.settings(), db.library());
+
+        let mut stories = db
+            .index()
+            .stories
+            .iter()
+            .filter(|(id, story)| filter.allows(id, story))
+            .collect::<Vec<_>>();
+
+        search::filter(&query[..], db.library(), &mut stories);
+
+        stories.sort_by(|(_, a), (_, b)| a.info.title.cmp(&b.info.title));
+
+        let format = query
+            .iter()
+            .find(|(key, _)| key == "format")
+            .map(|(_, value)| value.as_ref());
+
+        let format = BundleFormat::parse(format).ok_or(pages::Error::bad_request())?;
+
+        let ids = stories
+            .into_iter()
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+
+        (bundle::entries(&db, &ids, format), format)
+    };
+
+    Ok(bundle_response(
+        db.into_inner(),
+        entries,
+        format,
+        "search-results.zip",
+    ))
+}
+
+/// Downloads every story on a shelf as a zip, named after the shelf.
+pub fn shelf_download(
+    db: extractor::Data<RwLock<Database>>,
+    name: extractor::Param<NameParam>,
+    format: extractor::OptionalQuery<FormatParam>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let format = BundleFormat::parse(format.as_deref()).ok_or(pages::Error::bad_request())?;
+
+    let (entries, file) = {
+        let db = utils::read(&db)?;
+
+        let name = percent_decode(name.as_bytes()).decode_utf8_lossy();
+
+        let mut ids = db
+            .library()
+            .shelf(&name)
+            .ok_or(pages::Error::not_found())?
+            .into_iter()
+            .filter_map(|id| db.index().stories.get_key_value(id))
+            .collect::<Vec<_>>();
+
+        ids.sort_by(|(_, a), (_, b)| a.info.title.cmp(&b.info.title));
+
+        let ids = ids
+            .into_iter()
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+
+        let file = export::file_name(shelf_text(&name), ExportFormat::Epub);
+        let file = vfmt::format!("{}.zip", file.trim_end_matches(".epub"));
+
+        (bundle::entries(&db, &ids, format), file)
+    };
+
+    Ok(bundle_response(db.into_inner(), entries, format, &file))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_export_target() {
+        let target = "N26FPXqr.epub".parse::<ExportTarget>().ok().unwrap();
+
+        assert_eq!("N26FPXqr", target.id.as_str());
+        assert!(target.format == ExportFormat::Epub);
+
+        assert!("N26FPXqr".parse::<ExportTarget>().is_err());
+        assert!("N26FPXqr.pdf".parse::<ExportTarget>().is_err());
+
+        let target = "N26FPXqr.md".parse::<ExportTarget>().ok().unwrap();
+
+        assert!(target.format == ExportFormat::Markdown);
+    }
+
+    #[test]
+    fn test_attachment() {
+        assert_eq!(
+            "attachment; filename=\"Caf_ _Night_.epub\"; filename*=UTF-8''Caf%C3%A9%20%22Night%22.epub",
+            attachment("Café \"Night\"", ExportFormat::Epub)
+        );
+        assert_eq!(
+            "attachment; filename=\"story.epub\"; filename*=UTF-8''%E2%9C%A8.epub",
+            attachment("✨", ExportFormat::Epub)
+        );
+    }
+}
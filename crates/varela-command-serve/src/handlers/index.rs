@@ -1,3 +1,5 @@
+use std::sync::RwLock;
+
 use common::{database::Database, prelude::*};
 use enrgy::{
     extractor,
@@ -7,19 +9,22 @@ use enrgy::{
 
 use crate::{
     handlers::Template,
+    search,
     templates::{pages, partials, Layout, Width},
     utils,
 };
 
-pub fn index(db: extractor::Data<Database>) -> Result<impl IntoResponse, pages::Error> {
+pub fn index(db: extractor::Data<RwLock<Database>>) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
+    let filter = search::DefaultFilter::new(db.settings(), db.library());
+
     let mut stories = db
         .index()
         .stories
-        .keys()
-        .map(|id| {
-            utils::get_story_full(&db, id)
-                .and_then(|story| partials::StoryPartial::new(id.clone(), story, None))
-        })
+        .iter()
+        .filter(|(id, story)| filter.allows(id, story))
+        .map(|(id, _)| partials::StoryPartial::load(&db, id, None))
         .collect::<Result<Vec<partials::StoryPartial>>>()?;
 
     stories.sort_by(|a, b| a.title().cmp(b.title()));
@@ -29,7 +34,10 @@ pub fn index(db: extractor::Data<Database>) -> Result<impl IntoResponse, pages::
         db.settings().theme,
         "home",
         None,
-        pages::Index::new(stories),
+        pages::Index {
+            continue_reading: partials::ContinueReading::new(&db),
+            ..pages::Index::new(stories)
+        },
     )))
 }
 
@@ -0,0 +1,246 @@
+use std::sync::RwLock;
+
+use common::{
+    database::Database,
+    models::{Bookmark, Id, Library, Shelf, Status},
+    prelude::*,
+};
+use enrgy::{
+    extractor,
+    http::encoding::percent::{percent_decode, utf8_percent_encode, NON_ALPHANUMERIC},
+    response::IntoResponse,
+};
+
+use crate::{
+    handlers::{self, RefererKey, Template},
+    templates::{pages, partials, Layout, Width},
+    utils,
+};
+
+pub struct IdParam;
+
+impl enrgy::extractor::param::ParamKey for IdParam {
+    const KEY: &'static str = "id";
+}
+
+pub struct NameParam;
+
+impl enrgy::extractor::param::ParamKey for NameParam {
+    const KEY: &'static str = "name";
+}
+
+fn shelf_href(name: &str) -> String {
+    vfmt::format!(
+        "/shelf/{}",
+        utf8_percent_encode(name, NON_ALPHANUMERIC).to_string()
+    )
+}
+
+pub(super) fn shelf_text(name: &str) -> &str {
+    Status::from_slug(name).map(Status::name).unwrap_or(name)
+}
+
+/// Updates a story's bookmark and shelves from the library form on its card.
+pub fn story_library(
+    db: extractor::Data<RwLock<Database>>,
+    id: extractor::ParseParam<IdParam, Id>,
+    referer: extractor::OptionalHeader<RefererKey>,
+    body: extractor::Body,
+) -> Result<impl IntoResponse, pages::Error> {
+    let form = enrgy::http::encoding::form::parse(&body).collect::<Vec<_>>();
+
+    let field = |key: &str| {
+        form.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.trim())
+            .unwrap_or_default()
+    };
+
+    let status = match field("status") {
+        "" => None,
+        slug => Some(Status::from_slug(slug).ok_or(pages::Error::bad_request())?),
+    };
+
+    let rating = match field("rating") {
+        "" => 0,
+        rating => rating
+            .parse()
+            .ok()
+            .filter(|rating| *rating <= Bookmark::RATING_MAX)
+            .ok_or(pages::Error::bad_request())?,
+    };
+
+    let mut tags = Vec::<String>::new();
+
+    for tag in field("tags").split(',').map(str::trim) {
+        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_owned());
+        }
+    }
+
+    let following = field("following") == "on";
+
+    let mut bookmark = Bookmark {
+        favourite: field("favourite") == "on",
+        status,
+        notes: field("notes").to_owned(),
+        rating,
+        tags,
+        following,
+        updated: false,
+    };
+
+    let shelved = form
+        .iter()
+        .filter(|(key, _)| key == "shelf")
+        .map(|(_, value)| value.as_ref())
+        .collect::<Vec<_>>();
+
+    let mut db = utils::write(&db)?;
+
+    if !db.index().stories.contains_key(&id) {
+        return Err(pages::Error::not_found());
+    }
+
+    let library = db.library_mut();
+
+    // the form doesn't have it, it's only cleared by reading the story or unfollowing it
+    bookmark.updated = following
+        && library
+            .bookmarks
+            .get(&id)
+            .map(|bookmark| bookmark.updated)
+            .unwrap_or_default();
+
+    if bookmark.is_empty() {
+        library.bookmarks.remove(&id);
+    } else {
+        library.bookmarks.insert(id.clone(), bookmark);
+    }
+
+    for shelf in &mut library.shelves {
+        let on_shelf = shelved.contains(&shelf.name.as_str());
+
+        shelf.stories.retain(|story| story != &*id);
+
+        if on_shelf {
+            shelf.stories.push(id.clone());
+        }
+    }
+
+    db.save()?;
+
+    let fallback = vfmt::format!("/story/{}/1", id.as_str());
+
+    Ok(handlers::redirect(
+        referer
+            .as_deref()
+            .and_then(handlers::local_path)
+            .unwrap_or(&fallback),
+    ))
+}
+
+pub fn shelves(db: extractor::Data<RwLock<Database>>) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
+    let library = db.library();
+
+    let shelves = library
+        .shelf_names()
+        .into_iter()
+        .map(|name| pages::shelves::ShelfEntry {
+            href: shelf_href(name),
+            query: utf8_percent_encode(name, NON_ALPHANUMERIC).to_string(),
+            name: name.to_owned(),
+            text: shelf_text(name).to_owned(),
+            count: library
+                .shelf(name)
+                .map(|stories| stories.len())
+                .unwrap_or(0),
+            custom: !Library::is_reserved(name),
+        })
+        .collect();
+
+    Ok(Template(Layout::new(
+        Width::Slim,
+        db.settings().theme,
+        "shelves",
+        None,
+        pages::Shelves::new(shelves),
+    )))
+}
+
+pub fn shelves_post(
+    db: extractor::Data<RwLock<Database>>,
+    body: extractor::Body,
+) -> Result<impl IntoResponse, pages::Error> {
+    let form = enrgy::http::encoding::form::parse(&body).collect::<Vec<_>>();
+
+    let field = |key: &str| {
+        form.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.trim())
+    };
+
+    let action = field("action").ok_or(pages::Error::bad_request())?;
+    let name = field("name")
+        .filter(|name| !name.is_empty())
+        .ok_or(pages::Error::bad_request())?;
+
+    let mut db = utils::write(&db)?;
+
+    let library = db.library_mut();
+
+    match action {
+        "create" => {
+            if Library::is_reserved(name) {
+                return Err(pages::Error::bad_request());
+            }
+
+            if !library.shelves.iter().any(|shelf| shelf.name == name) {
+                library.shelves.push(Shelf {
+                    name: name.to_owned(),
+                    stories: vec![],
+                });
+            }
+        }
+        "delete" => {
+            library.shelves.retain(|shelf| shelf.name != name);
+        }
+        _ => return Err(pages::Error::bad_request()),
+    }
+
+    db.save()?;
+
+    Ok(handlers::redirect("/shelves"))
+}
+
+pub fn shelf(
+    db: extractor::Data<RwLock<Database>>,
+    name: extractor::Param<NameParam>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
+    let name = percent_decode(name.as_bytes()).decode_utf8_lossy();
+
+    let ids = db.library().shelf(&name).ok_or(pages::Error::not_found())?;
+
+    let mut stories = ids
+        .into_iter()
+        .filter(|id| db.index().stories.contains_key(*id))
+        .map(|id| partials::StoryPartial::load(&db, id, None))
+        .collect::<Result<Vec<_>>>()?;
+
+    stories.sort_by(|a, b| a.title().cmp(b.title()));
+
+    Ok(Template(Layout::new(
+        Width::Slim,
+        db.settings().theme,
+        shelf_text(&name).to_owned(),
+        None,
+        pages::Index {
+            download: Some(vfmt::format!("{}/download", shelf_href(&name))),
+            ..pages::Index::new(stories)
+        },
+    )))
+}
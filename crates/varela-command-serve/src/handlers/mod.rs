@@ -1,29 +1,53 @@
+mod api;
+mod browse;
 mod download;
 mod entity;
+mod export;
 mod index;
+mod library;
 mod opds;
+mod random;
+mod reader;
+mod saved;
 mod search;
+mod statistics;
 mod story;
 
 pub use crate::handlers::{
-    download::{download_get, download_post},
+    api::complete,
+    browse::{authors, characters, fandoms, pairings, tags, warnings},
+    download::{download_get, download_post, downloads, downloads_post},
     entity::entity,
+    export::{export, search_download, shelf_download},
     index::{favicon, index},
-    opds::catalog,
+    library::{shelf, shelves, shelves_post, story_library},
+    opds::{
+        catalog, catalog_author, catalog_authors, catalog_fandom, catalog_fandoms, catalog_file,
+        catalog_recent, catalog_results, catalog_search, catalog_shelf, catalog_shelves,
+    },
+    random::random,
+    reader::reader_post,
+    saved::{saved, saved_post},
     search::{search, search_v2},
-    story::story,
+    statistics::statistics,
+    story::{story, story_all, story_contents, story_progress},
 };
 
+use std::sync::RwLock;
+
+use common::database::Database;
 use enrgy::{
     extractor,
     http::HttpResponse,
     http::{
         self,
-        headers::{CACHE_CONTROL, CONTENT_TYPE, ETAG},
+        headers::{CACHE_CONTROL, CONTENT_TYPE, ETAG, LOCATION},
     },
     response::{Html, IntoResponse},
 };
 
+use crate::{templates::pages, utils};
+
 pub struct Template<T>(pub T)
 where
     T: opal::Template;
@@ -37,6 +61,38 @@ where
     }
 }
 
+pub fn redirect(location: &str) -> HttpResponse {
+    HttpResponse::new(http::StatusCode::SEE_OTHER).header(LOCATION, location)
+}
+
+/// Gets the path from a `Referer`, so that forms can send the reader back to where they were.
+pub fn local_path(referer: &str) -> Option<&str> {
+    let rest = referer.split_once("://").map_or(referer, |(_, rest)| rest);
+
+    let path = &rest[rest.find('/')?..];
+
+    // a leading `//` would be read as another host
+    (!path.starts_with("//")).then_some(path)
+}
+
+pub struct RefererKey;
+
+impl enrgy::extractor::header::HeaderKey for RefererKey {
+    const KEY: &'static str = "Referer";
+}
+
+pub struct HostKey;
+
+impl enrgy::extractor::header::HeaderKey for HostKey {
+    const KEY: &'static str = "Host";
+}
+
+pub struct CookieKey;
+
+impl enrgy::extractor::header::HeaderKey for CookieKey {
+    const KEY: &'static str = "Cookie";
+}
+
 pub struct IfNoneMatchKey;
 
 impl enrgy::extractor::header::HeaderKey for IfNoneMatchKey {
@@ -44,9 +100,12 @@ impl enrgy::extractor::header::HeaderKey for IfNoneMatchKey {
 }
 
 pub fn style(header: extractor::OptionalHeader<IfNoneMatchKey>) -> HttpResponse {
-    static CSS: &str = include_str!("../../assets/dist/index.css");
+    static CSS: &str = concat!(
+        include_str!("../../assets/dist/index.css"),
+        include_str!("../../assets/themes.css"),
+    );
     // RELEASE: change anytime theres a release and the style gets updated
-    static CSS_TAG: &str = "\"C0017857370FA4EDD51C10B2276FEE04C76BDCA4879B415E510F958E4C3FF091\"";
+    static CSS_TAG: &str = "\"7E6DE3A70D3E0E0F9238B971F286189ADD7E251DD37A162C69279F0DFB0C7935\"";
 
     let mut res = HttpResponse::ok().header(CONTENT_TYPE, "text/css; charset=utf-8");
 
@@ -65,3 +124,35 @@ pub fn style(header: extractor::OptionalHeader<IfNoneMatchKey>) -> HttpResponse
 
     res.body(CSS)
 }
+
+/// Serves `custom.css` from the data directory, layered over the built in styles.
+pub fn custom_style(db: extractor::Data<RwLock<Database>>) -> Result<HttpResponse, pages::Error> {
+    let path = utils::read(&db)?.data_path.join("custom.css");
+
+    let css = match std::fs::read(&path) {
+        Ok(css) => css,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok(HttpResponse::ok()
+        .header(CONTENT_TYPE, "text/css; charset=utf-8")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(css))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_local_path() {
+        assert_eq!(
+            Some("/search2?io=x"),
+            local_path("http://localhost:8723/search2?io=x")
+        );
+        assert_eq!(Some("/shelves"), local_path("/shelves"));
+        assert_eq!(None, local_path("http://localhost:8723"));
+        assert_eq!(None, local_path("http://localhost:8723//evil.example"));
+    }
+}
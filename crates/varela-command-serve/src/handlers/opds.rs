@@ -1,23 +1,80 @@
-use std::str::FromStr;
+use std::{str::FromStr, sync::RwLock};
 
 use common::{
     database::Database,
-    models::{Existing, FileKind},
+    models::{EntityKind, FileKind, Id, Story},
     prelude::*,
 };
-use enrgy::{extractor, http::headers::CONTENT_TYPE, http::HttpResponse, response::IntoResponse};
+use enrgy::{
+    extractor,
+    http::{
+        encoding::percent::{percent_decode, utf8_percent_encode, NON_ALPHANUMERIC},
+        headers::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        HttpResponse,
+    },
+    response::IntoResponse,
+};
 
 use crate::{
-    templates::pages::{self, opds::OpdsFeed},
+    export, fuzzy,
+    handlers::{self, library, HostKey},
+    search,
+    templates::pages::{
+        self,
+        opds::{
+            Facet, FeedKind, FeedLink, NavigationEntry, OpdsEntry, OpdsFeed, OpenSearch,
+            StoryEntry, JSON_MIME,
+        },
+    },
     utils,
 };
 
+/// How many entries are in each page of a feed.
+const PAGE_SIZE: usize = 50;
+
+/// The root feed's path, without an extension.
+const ROOT: &str = "/opds/root";
+
+/// The OpenSearch description, linked to from every Atom feed.
+const SEARCH: &str = "/opds/search.xml";
+
+const SEARCH_MIME: &str = "application/opensearchdescription+xml";
+
+/// The catalog's entity feeds, along with the pages of the site that show the same thing.
+static KINDS: [(EntityKind, &str, &str, &str, &str); 2] = [
+    (
+        EntityKind::Origin,
+        "fandoms",
+        "By Fandom",
+        "/fandoms",
+        "/origin",
+    ),
+    (
+        EntityKind::Author,
+        "authors",
+        "By Author",
+        "/authors",
+        "/author",
+    ),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum CatalogFormat {
     Atom, // opds+1.2
     Html,
     Json, // opds+2.0
 }
 
+impl CatalogFormat {
+    const fn ext(self) -> &'static str {
+        match self {
+            CatalogFormat::Atom => "xml",
+            CatalogFormat::Html => "html",
+            CatalogFormat::Json => "json",
+        }
+    }
+}
+
 impl FromStr for CatalogFormat {
     type Err = CatalogFormatError;
 
@@ -44,34 +101,1006 @@ impl vfmt::uDebug for CatalogFormatError {
         f.write_str(&self.0)
     }
 }
+
+/// A path parameter naming an entity or shelf along with the format, like `zVrtpwkN.json`.
+///
+/// The router can't split the extension out itself, but neither ids or percent encoded names contain dots.
+pub struct FeedTarget {
+    name: String,
+    format: CatalogFormat,
+}
+
+impl FromStr for FeedTarget {
+    type Err = CatalogFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, ext) = s
+            .rsplit_once('.')
+            .ok_or_else(|| CatalogFormatError(vfmt::format!("Missing catalog format: {}", s)))?;
+
+        Ok(FeedTarget {
+            name: name.to_owned(),
+            format: ext.parse()?,
+        })
+    }
+}
+
 pub struct ExtParam;
 
 impl enrgy::extractor::param::ParamKey for ExtParam {
     const KEY: &'static str = "ext";
 }
 
+pub struct IdParam;
+
+impl enrgy::extractor::param::ParamKey for IdParam {
+    const KEY: &'static str = "id";
+}
+
+pub struct NameParam;
+
+impl enrgy::extractor::param::ParamKey for NameParam {
+    const KEY: &'static str = "name";
+}
+
+pub struct PageParam;
+
+impl enrgy::extractor::query::QueryKey for PageParam {
+    const KEY: &'static str = "page";
+}
+
+pub struct QParam;
+
+impl enrgy::extractor::query::QueryKey for QParam {
+    const KEY: &'static str = "q";
+}
+
+pub struct SortParam;
+
+impl enrgy::extractor::query::QueryKey for SortParam {
+    const KEY: &'static str = "sort";
+}
+
+/// The orders an acquisition feed can be put in, offered to catalog readers as facets.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Sort {
+    Title,
+    Updated,
+}
+
+impl Sort {
+    const ALL: [Sort; 2] = [Sort::Title, Sort::Updated];
+
+    const fn slug(self) -> &'static str {
+        match self {
+            Sort::Title => "title",
+            Sort::Updated => "updated",
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Sort::Title => "Title",
+            Sort::Updated => "Recently Updated",
+        }
+    }
+}
+
+/// The page of a feed being viewed, starting at `1`.
+fn page_number(page: Option<&str>) -> Result<usize, pages::Error> {
+    match page {
+        None => Ok(1),
+        Some(page) => page
+            .parse()
+            .ok()
+            .filter(|page| *page != 0)
+            .ok_or(pages::Error::bad_request()),
+    }
+}
+
+fn sort_order(sort: Option<&str>) -> Result<Option<Sort>, pages::Error> {
+    match sort {
+        None => Ok(None),
+        Some(sort) => Sort::ALL
+            .into_iter()
+            .find(|s| s.slug() == sort)
+            .map(Some)
+            .ok_or(pages::Error::bad_request()),
+    }
+}
+
+fn now() -> String {
+    humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string()
+}
+
+/// What every feed has, whether it lists feeds or stories.
+struct Feed {
+    id: String,
+    title: String,
+    /// The feed's own path, without the extension or page.
+    path: String,
+    /// The path of the feed above this one, without the extension.
+    up: &'static str,
+    /// The page of the site that shows the same thing, what the `html` format sends readers to.
+    web: String,
+    kind: FeedKind,
+    format: CatalogFormat,
+    /// The order asked for, kept in the links between pages.
+    sort: Option<Sort>,
+    /// The percent encoded search, only set on search results.
+    search: Option<String>,
+}
+
+impl Feed {
+    fn href(&self, path: &str) -> String {
+        vfmt::format!("{}.{}", path, self.format.ext())
+    }
+
+    fn mime(&self, kind: FeedKind) -> &'static str {
+        match self.format {
+            CatalogFormat::Json => JSON_MIME,
+            _ => kind.mime(),
+        }
+    }
+
+    fn paged_href(&self, page: usize, sort: Option<Sort>) -> String {
+        let mut href = self.href(&self.path);
+
+        let mut separator = '?';
+
+        if let Some(search) = &self.search {
+            href.push(separator);
+            href.push_str("q=");
+            href.push_str(search);
+
+            separator = '&';
+        }
+
+        if let Some(sort) = sort {
+            href.push(separator);
+            href.push_str("sort=");
+            href.push_str(sort.slug());
+
+            separator = '&';
+        }
+
+        if page != 1 {
+            href.push(separator);
+            href.push_str(&vfmt::format!("page={}", page));
+        }
+
+        href
+    }
+
+    /// A link to another feed, in the same format as this one.
+    fn entry(
+        &self,
+        id: String,
+        title: impl Into<String>,
+        updated: &str,
+        path: &str,
+        kind: FeedKind,
+        content: String,
+    ) -> OpdsEntry {
+        OpdsEntry::Navigation(NavigationEntry {
+            id,
+            title: title.into(),
+            updated: updated.to_owned(),
+            href: self.href(path),
+            kind: self.mime(kind),
+            content,
+        })
+    }
+
+    /// The links to the catalog's root, this feed's parent and the pages around this one.
+    fn links(&self, page: usize, pages: usize) -> Vec<FeedLink> {
+        let link = |rel, page| FeedLink {
+            rel,
+            href: self.paged_href(page, self.sort),
+            kind: self.mime(self.kind),
+            templated: false,
+        };
+
+        let mut links = vec![
+            link("self", page),
+            FeedLink {
+                rel: "start",
+                href: self.href(ROOT),
+                kind: self.mime(FeedKind::Navigation),
+                templated: false,
+            },
+        ];
+
+        if self.path != ROOT {
+            links.push(FeedLink {
+                rel: "up",
+                href: self.href(self.up),
+                kind: self.mime(FeedKind::Navigation),
+                templated: false,
+            });
+        }
+
+        // OPDS 2.0 puts the search template right in the feed, Atom points to the description
+        links.push(match self.format {
+            CatalogFormat::Json => FeedLink {
+                rel: "search",
+                href: "/opds/results.json{?q}".to_owned(),
+                kind: JSON_MIME,
+                templated: true,
+            },
+            _ => FeedLink {
+                rel: "search",
+                href: SEARCH.to_owned(),
+                kind: SEARCH_MIME,
+                templated: false,
+            },
+        });
+
+        if pages > 1 {
+            links.push(link("first", 1));
+            links.push(link("last", pages));
+        }
+
+        if page > 1 {
+            links.push(link("previous", page - 1));
+        }
+
+        if page < pages {
+            links.push(link("next", page + 1));
+        }
+
+        links
+    }
+
+    /// Renders a page of the feed, `total` being how many entries there are across every page.
+    fn render(
+        self,
+        page: usize,
+        total: usize,
+        updated: String,
+        entries: Vec<OpdsEntry>,
+        facets: Vec<Facet>,
+    ) -> Result<HttpResponse, pages::Error> {
+        if self.format == CatalogFormat::Html {
+            return Ok(handlers::redirect(&self.web));
+        }
+
+        let pages = total.div_ceil(PAGE_SIZE).max(1);
+
+        if page > pages {
+            return Err(pages::Error::not_found());
+        }
+
+        let mime = self.mime(self.kind);
+
+        let feed = OpdsFeed {
+            links: self.links(page, pages),
+            id: self.id,
+            title: self.title,
+            updated,
+            page,
+            per_page: PAGE_SIZE,
+            total,
+            facets,
+            entries,
+        };
+
+        let body = match self.format {
+            CatalogFormat::Json => feed.to_json(),
+            _ => ::opal::Template::render_into_string(feed)?,
+        };
+
+        Ok(HttpResponse::ok().header(CONTENT_TYPE, mime).body(body))
+    }
+}
+
+/// The entries on a page, pages start at `1`.
+///
+/// A page too far in to even work out where it starts doesn't exist, the rest past the end are left for
+/// [`Feed::render`] to turn away.
+fn paged<T>(
+    entries: impl IntoIterator<Item = T>,
+    page: usize,
+) -> Result<impl Iterator<Item = T>, pages::Error> {
+    let skip = page
+        .checked_sub(1)
+        .and_then(|page| page.checked_mul(PAGE_SIZE))
+        .ok_or(pages::Error::not_found())?;
+
+    Ok(entries.into_iter().skip(skip).take(PAGE_SIZE))
+}
+
+fn count_text(count: usize) -> String {
+    vfmt::format!("{} {}", count, if count == 1 { "story" } else { "stories" })
+}
+
+/// The stories the default filter lets through.
+fn allowed(db: &Database) -> Vec<(&Id, &Story)> {
+    let filter = search::DefaultFilter::new(db.settings(), db.library());
+
+    db.index()
+        .stories
+        .iter()
+        .filter(|(id, story)| filter.allows(id, story))
+        .collect()
+}
+
+/// The most recent update of any of the stories, or now if there are none.
+fn latest<'s>(stories: impl Iterator<Item = &'s Story>) -> String {
+    stories
+        .map(|story| story.info.updated.as_str())
+        .filter(|updated| !updated.is_empty())
+        .max()
+        .map(str::to_owned)
+        .unwrap_or_else(now)
+}
+
+fn story_entry(db: &Database, id: &Id) -> Result<OpdsEntry> {
+    let story = utils::get_story_full(db, id)?;
+
+    let updated = if story.info.updated.is_empty() {
+        now()
+    } else {
+        story.info.updated.clone()
+    };
+
+    let published = if story.info.created.is_empty() {
+        updated.clone()
+    } else {
+        story.info.created.clone()
+    };
+
+    let categories = story
+        .meta
+        .origins
+        .iter()
+        .chain(story.meta.generals.iter())
+        .map(|entity| (entity.id.as_str().to_owned(), entity.text.clone()))
+        .collect();
+
+    let mut acquisitions = vec![(
+        vfmt::format!("/opds/file/{}", id.as_str()),
+        file_mime(story.info.kind),
+    )];
+
+    // html stories can be turned into an epub, which is what most readers actually want
+    if story.info.kind == FileKind::Html {
+        acquisitions.insert(
+            0,
+            (
+                vfmt::format!("/download/{}.epub", id.as_str()),
+                export::epub::MIME,
+            ),
+        );
+    }
+
+    Ok(OpdsEntry::Story(StoryEntry {
+        id: vfmt::format!("urn:varela:story:{}", id.as_str()),
+        title: story.info.title.clone(),
+        updated,
+        published,
+        authors: story
+            .meta
+            .authors
+            .iter()
+            .map(|author| {
+                (
+                    author.text.clone(),
+                    vfmt::format!("/author/{}", author.id.as_str()),
+                )
+            })
+            .collect(),
+        summary: story.info.summary.clone(),
+        categories,
+        read_href: vfmt::format!("/story/{}/1", id.as_str()),
+        acquisitions,
+    }))
+}
+
+fn file_mime(kind: FileKind) -> &'static str {
+    match kind {
+        FileKind::Epub => export::epub::MIME,
+        FileKind::Html => "text/html",
+    }
+}
+
+/// Renders a page of stories, in the order asked for or `default` if none was.
+fn acquisition(
+    db: &Database,
+    mut feed: Feed,
+    page: usize,
+    default: Sort,
+    mut stories: Vec<(&Id, &Story)>,
+) -> Result<HttpResponse, pages::Error> {
+    let sort = feed.sort.unwrap_or(default);
+
+    match sort {
+        Sort::Title => stories.sort_by(|a, b| a.1.info.title.cmp(&b.1.info.title)),
+        Sort::Updated => stories.sort_by(|a, b| b.1.info.updated.cmp(&a.1.info.updated)),
+    }
+
+    // the default order doesn't need to be spelled out in the links
+    if feed.sort == Some(default) {
+        feed.sort = None;
+    }
+
+    let facets = Sort::ALL
+        .into_iter()
+        .map(|s| Facet {
+            group: "Sort",
+            title: s.name(),
+            href: feed.paged_href(1, (s != default).then_some(s)),
+            active: s == sort,
+        })
+        .collect();
+
+    let updated = latest(stories.iter().map(|(_, story)| *story));
+
+    let total = stories.len();
+
+    // only the stories on the page are loaded, the rest only count towards the page links
+    let entries = paged(stories, page)?
+        .map(|(id, _)| story_entry(db, id))
+        .collect::<Result<Vec<_>>>()?;
+
+    feed.render(page, total, updated, entries, facets)
+}
+
 pub fn catalog(
-    db: extractor::Data<Database>,
-    _ext: extractor::ParseParam<ExtParam, CatalogFormat>,
+    db: extractor::Data<RwLock<Database>>,
+    ext: extractor::ParseParam<ExtParam, CatalogFormat>,
 ) -> Result<impl IntoResponse, pages::Error> {
-    let mut stories = db
+    let db = utils::read(&db)?;
+
+    let stories = allowed(&db);
+
+    let updated = latest(stories.iter().map(|(_, story)| *story));
+
+    let count = |kind| {
+        search::entity_counts(db.index(), stories.iter().map(|(_, story)| *story), kind).len()
+    };
+
+    let feed = Feed {
+        id: "urn:varela:feed:root".to_owned(),
+        title: "Varela Catalog".to_owned(),
+        path: ROOT.to_owned(),
+        up: ROOT,
+        web: "/".to_owned(),
+        kind: FeedKind::Navigation,
+        format: *ext,
+        sort: None,
+        search: None,
+    };
+
+    let entries = vec![
+        feed.entry(
+            "urn:varela:feed:recent".to_owned(),
+            "Recently Updated",
+            &updated,
+            "/opds/recent",
+            FeedKind::Acquisition,
+            count_text(stories.len()),
+        ),
+        feed.entry(
+            "urn:varela:feed:fandoms".to_owned(),
+            "By Fandom",
+            &updated,
+            "/opds/fandoms",
+            FeedKind::Navigation,
+            vfmt::format!("{} fandoms", count(EntityKind::Origin)),
+        ),
+        feed.entry(
+            "urn:varela:feed:authors".to_owned(),
+            "By Author",
+            &updated,
+            "/opds/authors",
+            FeedKind::Navigation,
+            vfmt::format!("{} authors", count(EntityKind::Author)),
+        ),
+        feed.entry(
+            "urn:varela:feed:shelves".to_owned(),
+            "Shelves",
+            &updated,
+            "/opds/shelves",
+            FeedKind::Navigation,
+            vfmt::format!("{} shelves", db.library().shelf_names().len()),
+        ),
+    ];
+
+    let total = entries.len();
+
+    feed.render(1, total, updated, entries, vec![])
+}
+
+pub fn catalog_recent(
+    db: extractor::Data<RwLock<Database>>,
+    ext: extractor::ParseParam<ExtParam, CatalogFormat>,
+    page: extractor::OptionalQuery<PageParam>,
+    sort: extractor::OptionalQuery<SortParam>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let page = page_number(page.as_deref())?;
+    let sort = sort_order(sort.as_deref())?;
+
+    let db = utils::read(&db)?;
+
+    let stories = allowed(&db);
+
+    acquisition(
+        &db,
+        Feed {
+            id: "urn:varela:feed:recent".to_owned(),
+            title: "Recently Updated".to_owned(),
+            path: "/opds/recent".to_owned(),
+            up: ROOT,
+            web: "/".to_owned(),
+            kind: FeedKind::Acquisition,
+            format: *ext,
+            sort,
+            search: None,
+        },
+        page,
+        Sort::Updated,
+        stories,
+    )
+}
+
+macro_rules! entity_handlers {
+    ($( $list:ident, $single:ident => $kind:expr , )*) => {
+        $(
+            pub fn $list(
+                db: extractor::Data<RwLock<Database>>,
+                ext: extractor::ParseParam<ExtParam, CatalogFormat>,
+                page: extractor::OptionalQuery<PageParam>,
+            ) -> Result<impl IntoResponse, pages::Error> {
+                entities(&db, $kind, *ext, page_number(page.as_deref())?)
+            }
+
+            pub fn $single(
+                db: extractor::Data<RwLock<Database>>,
+                target: extractor::ParseParam<IdParam, FeedTarget>,
+                page: extractor::OptionalQuery<PageParam>,
+                sort: extractor::OptionalQuery<SortParam>,
+            ) -> Result<impl IntoResponse, pages::Error> {
+                entity(
+                    &db,
+                    $kind,
+                    &target,
+                    page_number(page.as_deref())?,
+                    sort_order(sort.as_deref())?,
+                )
+            }
+        )*
+    };
+}
+
+entity_handlers! {
+    catalog_fandoms, catalog_fandom => EntityKind::Origin,
+    catalog_authors, catalog_author => EntityKind::Author,
+}
+
+/// Lists every entity of a kind, alphabetically, linking to a feed of their stories.
+fn entities(
+    db: &RwLock<Database>,
+    kind: EntityKind,
+    format: CatalogFormat,
+    page: usize,
+) -> Result<HttpResponse, pages::Error> {
+    let db = utils::read(db)?;
+
+    let (_, slug, title, web, _) = KINDS
+        .iter()
+        .find(|(k, ..)| *k == kind)
+        .copied()
+        .ok_or(pages::Error::not_found())?;
+
+    let stories = allowed(&db);
+
+    let updated = latest(stories.iter().map(|(_, story)| *story));
+
+    let mut entities =
+        search::entity_counts(db.index(), stories.iter().map(|(_, story)| *story), kind)
+            .into_iter()
+            .map(|(id, entity, count)| (fuzzy::fold(&entity.text), id, entity, count))
+            .collect::<Vec<_>>();
+
+    entities.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let feed = Feed {
+        id: vfmt::format!("urn:varela:feed:{}", slug),
+        title: title.to_owned(),
+        path: vfmt::format!("/opds/{}", slug),
+        up: ROOT,
+        web: web.to_owned(),
+        kind: FeedKind::Navigation,
+        format,
+        sort: None,
+        search: None,
+    };
+
+    let total = entities.len();
+
+    let entries = paged(entities, page)?
+        .map(|(_, id, entity, count)| {
+            feed.entry(
+                vfmt::format!("urn:varela:feed:{}:{}", slug, id.as_str()),
+                entity.text.clone(),
+                &updated,
+                &vfmt::format!("/opds/{}/{}", slug, id.as_str()),
+                FeedKind::Acquisition,
+                count_text(count),
+            )
+        })
+        .collect();
+
+    feed.render(page, total, updated, entries, vec![])
+}
+
+/// Lists the stories of a single entity, alphabetically unless asked otherwise.
+fn entity(
+    db: &RwLock<Database>,
+    kind: EntityKind,
+    target: &FeedTarget,
+    page: usize,
+    sort: Option<Sort>,
+) -> Result<HttpResponse, pages::Error> {
+    let db = utils::read(db)?;
+
+    let (_, slug, _, _, web) = KINDS
+        .iter()
+        .find(|(k, ..)| *k == kind)
+        .copied()
+        .ok_or(pages::Error::not_found())?;
+
+    let id = Id::from(target.name.clone());
+
+    let entity = db
+        .index()
+        .entities(kind)
+        .get(&id)
+        .ok_or(pages::Error::not_found())?;
+
+    let stories = allowed(&db)
+        .into_iter()
+        .filter(|(_, story)| story.meta.entities(kind).contains(&id))
+        .collect::<Vec<_>>();
+
+    let up = match kind {
+        EntityKind::Author => "/opds/authors",
+        _ => "/opds/fandoms",
+    };
+
+    acquisition(
+        &db,
+        Feed {
+            id: vfmt::format!("urn:varela:feed:{}:{}", slug, id.as_str()),
+            title: entity.text.clone(),
+            path: vfmt::format!("/opds/{}/{}", slug, id.as_str()),
+            up,
+            web: vfmt::format!("{}/{}", web, id.as_str()),
+            kind: FeedKind::Acquisition,
+            format: target.format,
+            sort,
+            search: None,
+        },
+        page,
+        Sort::Title,
+        stories,
+    )
+}
+
+pub fn catalog_shelves(
+    db: extractor::Data<RwLock<Database>>,
+    ext: extractor::ParseParam<ExtParam, CatalogFormat>,
+    page: extractor::OptionalQuery<PageParam>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let page = page_number(page.as_deref())?;
+
+    let db = utils::read(&db)?;
+
+    let library = db.library();
+
+    let updated = latest(db.index().stories.values());
+
+    let feed = Feed {
+        id: "urn:varela:feed:shelves".to_owned(),
+        title: "Shelves".to_owned(),
+        path: "/opds/shelves".to_owned(),
+        up: ROOT,
+        web: "/shelves".to_owned(),
+        kind: FeedKind::Navigation,
+        format: *ext,
+        sort: None,
+        search: None,
+    };
+
+    let names = library.shelf_names();
+
+    let total = names.len();
+
+    let entries = paged(names, page)?
+        .map(|name| {
+            let encoded = utf8_percent_encode(name, NON_ALPHANUMERIC).to_string();
+
+            let count = library
+                .shelf(name)
+                .map(|stories| stories.len())
+                .unwrap_or(0);
+
+            feed.entry(
+                vfmt::format!("urn:varela:feed:shelves:{}", encoded),
+                library::shelf_text(name),
+                &updated,
+                &vfmt::format!("/opds/shelves/{}", encoded),
+                FeedKind::Acquisition,
+                count_text(count),
+            )
+        })
+        .collect();
+
+    feed.render(page, total, updated, entries, vec![])
+}
+
+pub fn catalog_shelf(
+    db: extractor::Data<RwLock<Database>>,
+    target: extractor::ParseParam<NameParam, FeedTarget>,
+    page: extractor::OptionalQuery<PageParam>,
+    sort: extractor::OptionalQuery<SortParam>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let page = page_number(page.as_deref())?;
+    let sort = sort_order(sort.as_deref())?;
+
+    let db = utils::read(&db)?;
+
+    let name = percent_decode(target.name.as_bytes()).decode_utf8_lossy();
+
+    let ids = db.library().shelf(&name).ok_or(pages::Error::not_found())?;
+
+    let stories = ids
+        .into_iter()
+        .filter_map(|id| db.index().stories.get_key_value(id))
+        .collect::<Vec<_>>();
+
+    let encoded = utf8_percent_encode(&name, NON_ALPHANUMERIC).to_string();
+
+    acquisition(
+        &db,
+        Feed {
+            id: vfmt::format!("urn:varela:feed:shelves:{}", encoded),
+            title: library::shelf_text(&name).to_owned(),
+            path: vfmt::format!("/opds/shelves/{}", encoded),
+            up: "/opds/shelves",
+            web: vfmt::format!("/shelf/{}", encoded),
+            kind: FeedKind::Acquisition,
+            format: target.format,
+            sort,
+            search: None,
+        },
+        page,
+        Sort::Title,
+        stories,
+    )
+}
+
+pub fn catalog_search(
+    host: extractor::OptionalHeader<HostKey>,
+) -> Result<impl IntoResponse, pages::Error> {
+    // the server only speaks plain HTTP, anything in front of it can rewrite the links if it needs to
+    let base = host
+        .as_deref()
+        .map(|host| vfmt::format!("http://{}", host))
+        .unwrap_or_default();
+
+    Ok(HttpResponse::ok()
+        .header(CONTENT_TYPE, SEARCH_MIME)
+        .body(::opal::Template::render_into_string(OpenSearch { base })?))
+}
+
+/// Runs a search the same way as `/search`, returning the matches as an acquisition feed.
+pub fn catalog_results(
+    db: extractor::Data<RwLock<Database>>,
+    ext: extractor::ParseParam<ExtParam, CatalogFormat>,
+    search: extractor::OptionalQuery<QParam>,
+    page: extractor::OptionalQuery<PageParam>,
+    sort: extractor::OptionalQuery<SortParam>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let page = page_number(page.as_deref())?;
+    let sort = sort_order(sort.as_deref())?;
+
+    let search = search.as_deref().unwrap_or_default().trim();
+
+    let db = utils::read(&db)?;
+
+    let filter = search::DefaultFilter::new(db.settings(), db.library());
+
+    let stories = search::search(&db, search)
+        .into_iter()
+        .filter_map(|id| db.index().stories.get_key_value(&id))
+        .filter(|(id, story)| filter.allows(id, story))
+        .collect::<Vec<_>>();
+
+    let encoded = utf8_percent_encode(search, NON_ALPHANUMERIC).to_string();
+
+    acquisition(
+        &db,
+        Feed {
+            id: vfmt::format!("urn:varela:feed:search:{}", encoded),
+            title: vfmt::format!("Search: {}", search),
+            path: "/opds/results".to_owned(),
+            up: ROOT,
+            web: vfmt::format!("/search?search={}", encoded),
+            kind: FeedKind::Acquisition,
+            format: *ext,
+            sort,
+            search: Some(encoded),
+        },
+        page,
+        Sort::Title,
+        stories,
+    )
+}
+
+/// Sends the story's original file, what the acquisition links in the catalog point to.
+pub fn catalog_file(
+    db: extractor::Data<RwLock<Database>>,
+    id: extractor::ParseParam<IdParam, Id>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
+    let story = db
         .index()
         .stories
-        .iter()
-        .filter(|(_, s)| s.info.kind == FileKind::Epub)
-        .map(|(id, _)| utils::get_story_full(&db, id).map(|story| Existing::new(id.clone(), story)))
-        .collect::<Result<Vec<_>>>()?;
+        .get(&id)
+        .ok_or(pages::Error::not_found())?;
 
-    stories.sort_by(|a, b| a.info.updated.cmp(&b.info.updated));
+    let file = db.get_file(&id)?;
 
-    let updated = stories
-        .first()
-        .map(|story| story.info.updated.clone())
-        .unwrap_or_else(|| humantime::format_rfc3339(std::time::SystemTime::now()).to_string());
+    let format = match story.info.kind {
+        FileKind::Epub => export::ExportFormat::Epub,
+        FileKind::Html => export::ExportFormat::Html,
+    };
 
     Ok(HttpResponse::ok()
-        .header(CONTENT_TYPE, "application/atom+xml")
-        .body(::opal::Template::render_into_string(OpdsFeed::new(
-            updated, stories,
-        ))?))
+        .header(CONTENT_TYPE, file_mime(story.info.kind))
+        .header(
+            CONTENT_DISPOSITION,
+            handlers::export::attachment(&story.info.title, format),
+        )
+        .body(file.to_vec()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn feed(format: CatalogFormat, sort: Option<Sort>) -> Feed {
+        Feed {
+            id: "urn:varela:feed:recent".to_owned(),
+            title: "Recently Updated".to_owned(),
+            path: "/opds/recent".to_owned(),
+            up: ROOT,
+            web: "/".to_owned(),
+            kind: FeedKind::Acquisition,
+            format,
+            sort,
+            search: None,
+        }
+    }
+
+    fn rels(links: &[FeedLink]) -> Vec<(&'static str, &str)> {
+        links
+            .iter()
+            .map(|link| (link.rel, link.href.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_single_page_links() {
+        assert_eq!(
+            vec![
+                ("self", "/opds/recent.xml"),
+                ("start", "/opds/root.xml"),
+                ("up", "/opds/root.xml"),
+                ("search", "/opds/search.xml"),
+            ],
+            rels(&feed(CatalogFormat::Atom, None).links(1, 1))
+        );
+    }
+
+    #[test]
+    fn test_middle_page_links() {
+        assert_eq!(
+            vec![
+                ("self", "/opds/recent.json?sort=title&page=2"),
+                ("start", "/opds/root.json"),
+                ("up", "/opds/root.json"),
+                ("search", "/opds/results.json{?q}"),
+                ("first", "/opds/recent.json?sort=title"),
+                ("last", "/opds/recent.json?sort=title&page=3"),
+                ("previous", "/opds/recent.json?sort=title"),
+                ("next", "/opds/recent.json?sort=title&page=3"),
+            ],
+            rels(&feed(CatalogFormat::Json, Some(Sort::Title)).links(2, 3))
+        );
+    }
+
+    #[test]
+    fn test_search_links() {
+        let feed = Feed {
+            search: Some("author%3Atesty".to_owned()),
+            ..feed(CatalogFormat::Atom, None)
+        };
+
+        assert_eq!(
+            "/opds/recent.xml?q=author%3Atesty&sort=updated&page=2",
+            feed.paged_href(2, Some(Sort::Updated))
+        );
+        assert_eq!(
+            "/opds/recent.xml?q=author%3Atesty",
+            feed.paged_href(1, None)
+        );
+    }
+
+    #[test]
+    fn test_feed_target() {
+        let target = "zVrtpwkN.json".parse::<FeedTarget>().ok().unwrap();
+
+        assert_eq!("zVrtpwkN", target.name);
+        assert!(target.format == CatalogFormat::Json);
+
+        assert!("zVrtpwkN".parse::<FeedTarget>().is_err());
+        assert!("zVrtpwkN.pdf".parse::<FeedTarget>().is_err());
+    }
+
+    #[test]
+    fn test_paged() {
+        let paged = |page| {
+            paged(0..120, page)
+                .ok()
+                .map(|entries| entries.collect::<Vec<_>>())
+        };
+
+        assert_eq!(vec![50, 51], paged(2).unwrap()[..2]);
+        assert_eq!(Some(20), paged(3).map(|entries| entries.len()));
+        assert_eq!(Some(0), paged(4).map(|entries| entries.len()));
+        assert_eq!(None, paged(usize::MAX));
+    }
+
+    #[test]
+    fn test_json_feed() {
+        let feed = feed(CatalogFormat::Json, None);
+
+        let opds = OpdsFeed {
+            id: feed.id.clone(),
+            title: feed.title.clone(),
+            updated: "2022-03-14T10:00:00Z".to_owned(),
+            page: 1,
+            per_page: PAGE_SIZE,
+            total: 1,
+            links: feed.links(1, 1),
+            facets: vec![Facet {
+                group: "Sort",
+                title: "Title",
+                href: feed.paged_href(1, Some(Sort::Title)),
+                active: true,
+            }],
+            entries: vec![OpdsEntry::Story(StoryEntry {
+                id: "urn:varela:story:1".to_owned(),
+                title: "A \"Story\"".to_owned(),
+                updated: "2022-03-14T10:00:00Z".to_owned(),
+                published: "2022-03-01T10:00:00Z".to_owned(),
+                authors: vec![("someone".to_owned(), "/author/2".to_owned())],
+                summary: "<p>summary</p>".to_owned(),
+                categories: vec![],
+                read_href: "/story/1/1".to_owned(),
+                acquisitions: vec![("/opds/file/1".to_owned(), "text/html")],
+            })],
+        };
+
+        let json = opds.to_json();
+
+        assert!(json.starts_with(r#"{"metadata":{"identifier":"urn:varela:feed:recent","#));
+        assert!(json.contains(r#""facets":[{"metadata":{"title":"Sort"},"links":[{"rel":"self","title":"Title","href":"/opds/recent.json?sort=title","type":"application/opds+json"}]}]"#));
+        assert!(json.contains(r#""publications":[{"metadata":{"@type":"http://schema.org/Book","identifier":"urn:varela:story:1","title":"A \"Story\"","#));
+        assert!(!json.contains(r#""navigation""#));
+    }
 }
@@ -0,0 +1,49 @@
+use std::sync::RwLock;
+
+use common::{database::Database, utils::random_index};
+use enrgy::{extractor, response::IntoResponse};
+
+use crate::{handlers, search, templates::pages, utils};
+
+/// Sends the reader to a random story, narrowed down by the same query as `/search2`.
+///
+/// Adding `chapter=random` to the query picks a random chapter of the story rather than the first.
+pub fn random(
+    db: extractor::Data<RwLock<Database>>,
+    query: extractor::OptionalRawQuery,
+) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
+    let query = search::parse_query(query.as_deref().unwrap_or_default());
+
+    let filter = search::DefaultFilter::new(db.settings(), db.library());
+
+    let mut stories = db
+        .index()
+        .stories
+        .iter()
+        .filter(|(id, story)| filter.allows(id, story))
+        .collect::<Vec<_>>();
+
+    search::filter(&query[..], db.library(), &mut stories);
+
+    let (id, story) = random_index(stories.len())
+        .map(|i| stories[i])
+        .ok_or(pages::Error::not_found())?;
+
+    let random_chapter = query
+        .iter()
+        .any(|(key, value)| key == "chapter" && value == "random");
+
+    let chapter = if random_chapter {
+        random_index(story.chapters.len()).map_or(1, |i| i + 1)
+    } else {
+        1
+    };
+
+    Ok(handlers::redirect(&vfmt::format!(
+        "/story/{}/{}",
+        id.as_str(),
+        chapter
+    )))
+}
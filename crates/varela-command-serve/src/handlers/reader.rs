@@ -0,0 +1,40 @@
+use enrgy::{
+    extractor,
+    http::{headers::SET_COOKIE, HttpResponse},
+};
+
+use crate::{
+    handlers::{self, RefererKey},
+    reader::{Preferences, COOKIE},
+    templates::pages,
+};
+
+/// Saves the reader settings panel into a cookie, or clears it when reset.
+pub fn reader_post(
+    referer: extractor::OptionalHeader<RefererKey>,
+    body: extractor::Body,
+) -> Result<HttpResponse, pages::Error> {
+    let form = enrgy::http::encoding::form::parse(&body).collect::<Vec<_>>();
+
+    let action = form
+        .iter()
+        .find(|(key, _)| key == "action")
+        .map(|(_, value)| value.as_ref());
+
+    let cookie = match action {
+        Some("save") => vfmt::format!(
+            "{}={}; Path=/; Max-Age=31536000; SameSite=Lax",
+            COOKIE,
+            Preferences::from_pairs(form.iter().map(|(k, v)| (k.as_ref(), v.as_ref()))).to_cookie()
+        ),
+        Some("reset") => vfmt::format!("{}=; Path=/; Max-Age=0; SameSite=Lax", COOKIE),
+        _ => return Err(pages::Error::bad_request()),
+    };
+
+    let location = referer
+        .as_deref()
+        .and_then(handlers::local_path)
+        .unwrap_or("/");
+
+    Ok(handlers::redirect(location).header(SET_COOKIE, cookie))
+}
@@ -0,0 +1,103 @@
+use std::sync::RwLock;
+
+use common::{database::Database, models::SavedSearch, prelude::*};
+use enrgy::{extractor, response::IntoResponse};
+
+use crate::{
+    handlers::{self, Template},
+    search,
+    templates::{pages, Layout, Width},
+    utils,
+};
+
+pub fn saved(db: extractor::Data<RwLock<Database>>) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
+    let settings = db.settings();
+
+    let searches = settings
+        .saved_searches
+        .iter()
+        .map(|saved| pages::saved::SavedEntry {
+            name: saved.name.clone(),
+            query: saved.query.clone(),
+            count: search::count(&db, &saved.query),
+            default: settings.default_filter.as_ref() == Some(&saved.name),
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Template(Layout::new(
+        Width::Slim,
+        settings.theme,
+        "saved searches",
+        None,
+        pages::Saved::new(searches),
+    )))
+}
+
+pub fn saved_post(
+    db: extractor::Data<RwLock<Database>>,
+    body: extractor::Body,
+) -> Result<impl IntoResponse, pages::Error> {
+    let form = enrgy::http::encoding::form::parse(&body).collect::<Vec<_>>();
+
+    let field = |key: &str| {
+        form.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.trim())
+    };
+
+    let action = field("action").ok_or(pages::Error::bad_request())?;
+    let name = field("name")
+        .filter(|name| !name.is_empty())
+        .ok_or(pages::Error::bad_request())?;
+
+    let mut db = utils::write(&db)?;
+
+    let settings = db.settings_mut();
+
+    match action {
+        "save" => {
+            let query = field("query").unwrap_or_default();
+
+            // allow whole `/search2?...` urls to be pasted in
+            let query = query.split_once('?').map_or(query, |(_, query)| query);
+
+            let saved = SavedSearch {
+                name: name.to_owned(),
+                query: query.to_owned(),
+            };
+
+            match settings.saved_searches.iter_mut().find(|s| s.name == name) {
+                Some(existing) => *existing = saved,
+                None => settings.saved_searches.push(saved),
+            }
+        }
+        "delete" => {
+            settings.saved_searches.retain(|saved| saved.name != name);
+
+            if settings.default_filter.as_deref() == Some(name) {
+                settings.default_filter = None;
+            }
+        }
+        "default" => {
+            if !settings
+                .saved_searches
+                .iter()
+                .any(|saved| saved.name == name)
+            {
+                return Err(pages::Error::not_found());
+            }
+
+            settings.default_filter = Some(name.to_owned());
+        }
+        "clear" => {
+            settings.default_filter = None;
+        }
+        _ => return Err(pages::Error::bad_request()),
+    }
+
+    db.save()?;
+
+    Ok(handlers::redirect("/saved"))
+}
@@ -1,3 +1,5 @@
+use std::sync::RwLock;
+
 use common::{database::Database, prelude::*};
 use enrgy::{extractor, response::IntoResponse};
 
@@ -15,11 +17,24 @@ impl enrgy::extractor::query::QueryKey for SearchParam {
 }
 
 pub fn search(
-    db: extractor::Data<Database>,
+    db: extractor::Data<RwLock<Database>>,
     search: extractor::Query<SearchParam>,
     query: extractor::RawQuery,
 ) -> Result<impl IntoResponse, pages::Error> {
-    let ids = search::search(&db, &search);
+    let db = utils::read(&db)?;
+
+    let filter = search::DefaultFilter::new(db.settings(), db.library());
+
+    let mut ids = search::search(&db, &search);
+
+    ids.retain(|id| {
+        db.index()
+            .stories
+            .get(id)
+            .is_some_and(|story| filter.allows(id, story))
+    });
+
+    let suggestions = search::suggest(&db, &search);
 
     let query = enrgy::http::encoding::percent::utf8_percent_encode(
         &query,
@@ -29,50 +44,59 @@ pub fn search(
 
     let mut stories = ids
         .iter()
-        .map(|id| {
-            utils::get_story_full(&db, id).and_then(|story| {
-                partials::StoryPartial::new(id.clone(), story, Some(query.clone()))
-            })
-        })
+        .map(|id| partials::StoryPartial::load(&db, id, Some(query.clone())))
         .collect::<Result<Vec<_>>>()?;
 
     stories.sort_by(|a, b| a.title().cmp(b.title()));
 
+    let page = if suggestions.is_empty() {
+        pages::Index::new(stories)
+    } else {
+        pages::Index::with_suggestions(stories, partials::DidYouMean::new(&search, suggestions))
+    };
+
     Ok(Template(Layout::new(
         Width::Slim,
         db.settings().theme,
         "search",
         Some(query),
-        pages::Index::new(stories),
+        page,
     )))
 }
 
 pub fn search_v2(
-    db: extractor::Data<Database>,
-    query: extractor::Query<SearchParam>,
+    db: extractor::Data<RwLock<Database>>,
+    query: extractor::OptionalRawQuery,
 ) -> Result<impl IntoResponse, pages::Error> {
-    let mut stories = db.index().stories.iter().collect::<Vec<_>>();
+    let db = utils::read(&db)?;
 
-    let parsed_query = enrgy::http::encoding::form::parse(query.trim_start_matches('?').as_bytes())
+    let query = query.as_deref().unwrap_or_default();
+
+    let filter = search::DefaultFilter::new(db.settings(), db.library());
+
+    let mut stories = db
+        .index()
+        .stories
+        .iter()
+        .filter(|(id, story)| filter.allows(id, story))
         .collect::<Vec<_>>();
 
-    let stats = search::search_v2(&parsed_query[..], &mut stories)
-        .fill(db.index())
-        .ok_or_else(|| anyhow!("Unable to fill out stats, an entity does not exist somewhere"))?;
+    let parsed_query = search::parse_query(query);
+
+    let stats = search::search_v2(&parsed_query[..], db.library(), &mut stories)
+        .fill(db.index(), &parsed_query[..]);
+
+    let raw_query = query.to_owned();
 
     let query = enrgy::http::encoding::percent::utf8_percent_encode(
-        &query,
+        query,
         enrgy::http::encoding::percent::CONTROLS,
     )
     .to_string();
 
     let mut stories = stories
         .into_iter()
-        .map(|(id, _)| {
-            utils::get_story_full(&db, id).and_then(|story| {
-                partials::StoryPartial::new(id.clone(), story, Some(query.clone()))
-            })
-        })
+        .map(|(id, _)| partials::StoryPartial::load(&db, id, Some(query.clone())))
         .collect::<Result<Vec<_>>>()?;
 
     stories.sort_by(|a, b| a.title().cmp(b.title()));
@@ -82,6 +106,6 @@ pub fn search_v2(
         db.settings().theme,
         "search",
         Some(query),
-        pages::Search::new(stories, stats),
+        pages::Search::new(stories, stats, raw_query),
     )))
 }
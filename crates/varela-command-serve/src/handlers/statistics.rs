@@ -0,0 +1,113 @@
+use std::sync::RwLock;
+
+use common::{database::Database, models::Id};
+use enrgy::{extractor, response::IntoResponse};
+
+use crate::{
+    handlers::Template,
+    statistics::{self, ReadingStatistics},
+    templates::{
+        pages::{
+            self,
+            statistics::{StatisticsGroup, StatisticsRow},
+        },
+        partials::{Contrast, Link},
+        Layout, Width,
+    },
+    utils::{self, IntoReadable as _},
+};
+
+fn readable(number: usize) -> String {
+    vfmt::uDisplay::to_string(&number.into_readable())
+}
+
+fn group(name: &'static str, rows: Vec<(Result<Link, String>, usize)>) -> StatisticsGroup {
+    let max = rows
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    StatisticsGroup {
+        name,
+        rows: rows
+            .into_iter()
+            .map(|(label, count)| StatisticsRow {
+                label,
+                count: readable(count),
+                width: count * 100 / max,
+            })
+            .collect(),
+    }
+}
+
+fn entities(prefix: &str, entities: &[(Id, String, usize)]) -> Vec<(Result<Link, String>, usize)> {
+    entities
+        .iter()
+        .map(|(id, text, count)| {
+            let href = vfmt::format!("{}/{}", prefix, id.as_str());
+
+            (Ok(Link::new(Contrast::High, href, text.clone())), *count)
+        })
+        .collect()
+}
+
+pub fn statistics(
+    db: extractor::Data<RwLock<Database>>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
+    let index = statistics::cached(db.index())?;
+    let reading = ReadingStatistics::new(db.index(), db.library());
+
+    let totals = vec![
+        ("works", readable(index.works)),
+        ("chapters", readable(index.chapters)),
+        ("words", readable(index.words)),
+        ("authors", readable(index.authors)),
+        ("fandoms", readable(index.fandoms)),
+        ("words read", readable(reading.words_read)),
+    ];
+
+    let mut progress = vec![(Err("in progress".to_owned()), reading.in_progress)];
+
+    progress.extend(
+        reading
+            .statuses
+            .iter()
+            .map(|(status, count)| (Err(status.name().to_owned()), *count)),
+    );
+
+    let groups = vec![
+        group("top fandoms", entities("/origin", &index.top_fandoms)),
+        group("top characters", entities("/tag", &index.top_characters)),
+        group("top pairings", entities("/tag", &index.top_pairings)),
+        group(
+            "ratings",
+            index
+                .ratings
+                .iter()
+                .map(|(rating, count)| (Err(rating.name().to_owned()), *count))
+                .collect(),
+        ),
+        group("warnings", entities("/tag", &index.warnings)),
+        group(
+            "added per month",
+            index
+                .months
+                .iter()
+                .map(|(month, count)| (Err(month.clone()), *count))
+                .collect(),
+        ),
+        group("reading", progress),
+    ];
+
+    Ok(Template(Layout::new(
+        Width::Slim,
+        db.settings().theme,
+        "stats",
+        None,
+        pages::Statistics { totals, groups },
+    )))
+}
@@ -1,12 +1,29 @@
-use common::{database::Database, models::Id, prelude::*};
-use enrgy::{extractor, response::IntoResponse};
+use std::{
+    sync::RwLock,
+    time::{Duration, SystemTime},
+};
+
+use common::{
+    database::Database,
+    models::{Id, Progress},
+    prelude::*,
+};
+use enrgy::{
+    extractor,
+    http::{headers::LINK, HttpResponse, StatusCode},
+    response::IntoResponse,
+};
 
 use crate::{
-    handlers::Template,
+    handlers::{CookieKey, Template},
+    reader::Preferences,
     templates::{pages, partials, Layout, Width},
     utils,
 };
 
+/// The reader sends its position every few seconds, so it's only written to disk this often.
+const PROGRESS_SAVE: Duration = Duration::from_secs(30);
+
 pub struct IdParam;
 
 impl enrgy::extractor::param::ParamKey for IdParam {
@@ -20,26 +37,177 @@ impl enrgy::extractor::param::ParamKey for ChapterParam {
 }
 
 pub fn story(
-    db: extractor::Data<Database>,
+    db: extractor::Data<RwLock<Database>>,
     id: extractor::ParseParam<IdParam, Id>,
     index: extractor::Param<ChapterParam>,
+    cookies: extractor::OptionalHeader<CookieKey>,
 ) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
     let index: usize = index.parse().map_err(anyhow::Error::from)?;
 
     let story = utils::get_story_full(&db, &id)?;
-    let chapter = db.get_chapter_body(&id, index)?;
+    let body = utils::chapter_body(&db, &id, &story, index)?;
+
+    // only jump back down the page when the saved position is for this chapter
+    let resume = db
+        .library()
+        .progress
+        .get(&id)
+        .filter(|progress| progress.chapter == index)
+        .map(|progress| progress.position);
 
-    let body = Layout::new(
+    let title = story.info.title.clone();
+
+    let page = pages::Chapter {
+        resume,
+        ..pages::Chapter::new(
+            partials::StoryPartial::new(id.clone(), story, None)?.with_library(db.library()),
+            body,
+            partials::ReaderPanel::new(Preferences::from_cookies(cookies.as_deref())),
+            index,
+        )
+    };
+
+    let link = page.pagination.link_header();
+
+    let mut res = Template(Layout::new(
         Width::Slim,
         db.settings().theme,
-        story.info.title.clone(),
+        title,
         None,
-        pages::Chapter::new(
-            partials::StoryPartial::new(id.clone(), story, None)?,
-            chapter,
-            index,
+        page,
+    ))
+    .into_response();
+
+    if let Some(link) = link {
+        res = res.header(LINK, link);
+    }
+
+    Ok(res)
+}
+
+/// Lists a story's chapters along with their summaries.
+pub fn story_contents(
+    db: extractor::Data<RwLock<Database>>,
+    id: extractor::ParseParam<IdParam, Id>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
+    let story = utils::get_story_full(&db, &id)?;
+
+    let current = db
+        .library()
+        .progress
+        .get(&id)
+        .map(|progress| progress.chapter);
+
+    let chapters = story
+        .chapters
+        .iter()
+        .enumerate()
+        .map(|(i, chapter)| pages::contents::ContentsEntry {
+            number: i + 1,
+            title: chapter.title.clone(),
+            summary: chapter.summary.clone(),
+            current: current == Some(i + 1),
+        })
+        .collect();
+
+    let title = story.info.title.clone();
+
+    Ok(Template(Layout::new(
+        Width::Slim,
+        db.settings().theme,
+        title,
+        None,
+        pages::Contents::new(
+            partials::StoryPartial::new(id.clone(), story, None)?.with_library(db.library()),
+            chapters,
+        ),
+    )))
+}
+
+/// Shows every chapter of a story, with their notes, as a single page.
+pub fn story_all(
+    db: extractor::Data<RwLock<Database>>,
+    id: extractor::ParseParam<IdParam, Id>,
+    cookies: extractor::OptionalHeader<CookieKey>,
+) -> Result<impl IntoResponse, pages::Error> {
+    let db = utils::read(&db)?;
+
+    let story = utils::get_story_full(&db, &id)?;
+
+    let chapters = story
+        .chapters
+        .iter()
+        .enumerate()
+        .map(|(i, chapter)| {
+            utils::chapter_body(&db, &id, &story, i + 1).map(|body| (chapter.title.clone(), body))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let title = story.info.title.clone();
+
+    Ok(Template(Layout::new(
+        Width::Slim,
+        db.settings().theme,
+        title,
+        None,
+        pages::Work::new(
+            partials::StoryPartial::new(id.clone(), story, None)?.with_library(db.library()),
+            chapters,
+            partials::ReaderPanel::new(Preferences::from_cookies(cookies.as_deref())),
         ),
+    )))
+}
+
+/// Records how far into a story the reader is, sent by the chapter page as they scroll.
+pub fn story_progress(
+    db: extractor::Data<RwLock<Database>>,
+    id: extractor::ParseParam<IdParam, Id>,
+    body: extractor::Body,
+) -> Result<impl IntoResponse, pages::Error> {
+    let form = enrgy::http::encoding::form::parse(&body).collect::<Vec<_>>();
+
+    let field = |key: &str| -> Result<usize, pages::Error> {
+        form.iter()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, value)| value.trim().parse().ok())
+            .ok_or(pages::Error::bad_request())
+    };
+
+    let chapter = field("chapter")?;
+    let position = field("position")?.min(Progress::POSITION_MAX);
+
+    let mut db = utils::write(&db)?;
+
+    let chapters = db
+        .index()
+        .stories
+        .get(&id)
+        .map(|story| story.chapters.len())
+        .ok_or(pages::Error::not_found())?;
+
+    if chapter == 0 || chapter > chapters {
+        return Err(pages::Error::bad_request());
+    }
+
+    db.library_mut().progress.insert(
+        id.clone(),
+        Progress {
+            chapter,
+            position,
+            updated: humantime::format_rfc3339(SystemTime::now()).to_string(),
+        },
     );
 
-    Ok(Template(body))
+    if let Some(bookmark) = db.library_mut().bookmarks.get_mut(&id) {
+        bookmark.updated = false;
+    }
+
+    // whatever's left unsaved is written by the next save, or when the server stops
+    db.save_throttled(PROGRESS_SAVE)?;
+
+    Ok(HttpResponse::new(StatusCode::NO_CONTENT))
 }
@@ -0,0 +1,49 @@
+//! Just enough JSON writing for the API endpoints, without pulling in a serializer.
+
+/// Writes `text` as a quoted and escaped JSON string.
+pub fn string(out: &mut String, text: &str) {
+    out.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                out.push_str("\\u00");
+                out.push(char::from_digit((c as u32) >> 4, 16).unwrap_or('0'));
+                out.push(char::from_digit((c as u32) & 0xF, 16).unwrap_or('0'));
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+/// Writes an object key, including the trailing colon.
+pub fn key(out: &mut String, key: &str) {
+    string(out, key);
+    out.push(':');
+}
+
+/// Writes an unsigned number.
+pub fn number(out: &mut String, number: usize) {
+    out.push_str(&vfmt::uDisplay::to_string(&number));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_string_escapes() {
+        let mut out = String::new();
+
+        string(&mut out, "a \"quoted\" \\ line\n\u{1}");
+
+        assert_eq!(r#""a \"quoted\" \\ line\n\u0001""#, out);
+    }
+}
@@ -1,18 +1,26 @@
 #![allow(incomplete_features)]
 #![feature(adt_const_params, decl_macro, generic_const_exprs)]
 
+pub mod export;
 mod handlers;
 mod templates;
 
+mod ao3;
 mod filters;
+mod fuzzy;
+mod json;
+mod queue;
+mod reader;
 mod router;
 mod search;
+mod statistics;
 mod template;
+mod updates;
 mod utils;
 
 use std::{
     net::{Ipv4Addr, SocketAddr},
-    sync::Arc,
+    sync::{Arc, RwLock},
     time::Instant,
 };
 
@@ -58,7 +66,7 @@ pub fn run(mut args: common::Args) -> Result<()> {
 
     let addr: SocketAddr = (host, port).into();
 
-    let database = Arc::new({
+    let database = Arc::new(RwLock::new({
         let mut db = Database::open()?;
 
         trace!("with {} stories", db.index().stories.len().bright_purple());
@@ -66,22 +74,61 @@ pub fn run(mut args: common::Args) -> Result<()> {
         db.lock_data()?;
 
         db
-    });
+    }));
+
+    let (waker, worker) = queue::spawn(database.clone())?;
+    let waker = Arc::new(waker);
 
     let server = Server::new()
         .data(database.clone())
+        .data(waker.clone())
         .service(route::get("/").to(handlers::index))
         .service(route::get("/download").to(handlers::download_get))
         .service(route::post("/download").to(handlers::download_post))
+        .service(route::get("/download/:id").to(handlers::export))
+        .service(route::get("/downloads").to(handlers::downloads))
+        .service(route::post("/downloads").to(handlers::downloads_post))
+        .service(route::get("/story/:id").to(handlers::story_contents))
+        .service(route::get("/story/:id/all").to(handlers::story_all))
         .service(route::get("/story/:id/:chapter").to(handlers::story))
+        .service(route::post("/story/:id/progress").to(handlers::story_progress))
+        .service(route::post("/story/:id/library").to(handlers::story_library))
+        .service(route::post("/reader").to(handlers::reader_post))
         .service(route::get("/search").to(handlers::search))
         .service(route::get("/search2").to(handlers::search_v2))
+        .service(route::get("/search2/download").to(handlers::search_download))
         .service(route::get("/style.css").to(handlers::style))
+        .service(route::get("/custom.css").to(handlers::custom_style))
         .service(route::get("/favicon.ico").to(handlers::favicon))
+        .service(route::get("/fandoms").to(handlers::fandoms))
+        .service(route::get("/authors").to(handlers::authors))
+        .service(route::get("/characters").to(handlers::characters))
+        .service(route::get("/pairings").to(handlers::pairings))
+        .service(route::get("/warnings").to(handlers::warnings))
+        .service(route::get("/tags").to(handlers::tags))
         .service(route::get("/author/:id").to(handlers::entity))
         .service(route::get("/origin/:id").to(handlers::entity))
         .service(route::get("/tag/:id").to(handlers::entity))
         .service(route::get("/opds/root.:ext").to(handlers::catalog))
+        .service(route::get("/opds/recent.:ext").to(handlers::catalog_recent))
+        .service(route::get("/opds/search.xml").to(handlers::catalog_search))
+        .service(route::get("/opds/results.:ext").to(handlers::catalog_results))
+        .service(route::get("/opds/fandoms.:ext").to(handlers::catalog_fandoms))
+        .service(route::get("/opds/fandoms/:id").to(handlers::catalog_fandom))
+        .service(route::get("/opds/authors.:ext").to(handlers::catalog_authors))
+        .service(route::get("/opds/authors/:id").to(handlers::catalog_author))
+        .service(route::get("/opds/shelves.:ext").to(handlers::catalog_shelves))
+        .service(route::get("/opds/shelves/:name").to(handlers::catalog_shelf))
+        .service(route::get("/opds/file/:id").to(handlers::catalog_file))
+        .service(route::get("/saved").to(handlers::saved))
+        .service(route::post("/saved").to(handlers::saved_post))
+        .service(route::get("/shelves").to(handlers::shelves))
+        .service(route::get("/stats").to(handlers::statistics))
+        .service(route::get("/random").to(handlers::random))
+        .service(route::post("/shelves").to(handlers::shelves_post))
+        .service(route::get("/shelf/:name").to(handlers::shelf))
+        .service(route::get("/shelf/:name/download").to(handlers::shelf_download))
+        .service(route::get("/api/complete").to(handlers::complete))
         .default(route::to(|| -> enrgy::http::HttpResponse {
             crate::res!(404)
         }))
@@ -92,7 +139,23 @@ pub fn run(mut args: common::Args) -> Result<()> {
 
     server.run()?;
 
-    if let Some(mut database) = Arc::into_inner(database) {
+    waker.stop();
+
+    if worker.join().is_err() {
+        error!("download worker panicked");
+    }
+
+    // the last of the reading progress is only saved now
+    database
+        .read()
+        .map_err(|err| anyhow!("unable to get lock on database: {}", err))?
+        .save_dirty()?;
+
+    if let Some(database) = Arc::into_inner(database) {
+        let mut database = database
+            .into_inner()
+            .map_err(|err| anyhow!("unable to get lock on database: {}", err))?;
+
         database.unlock_data()?;
     } else {
         error!("unable to unwrap database, not unlocking data");
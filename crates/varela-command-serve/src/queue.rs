@@ -0,0 +1,524 @@
+//! Downloading works in the background, so requests never wait on AO3.
+//!
+//! The queue is kept in the index. A download that was part way through when the server stopped is
+//! started over when it starts again.
+//!
+//! The worker also checks followed works for updates every so often, queuing the ones that have them. The
+//! first check waits a while after starting, and works are checked one at a time with a pause between
+//! them, as the archive limits how often it can be asked for pages.
+//!
+//! Changes to the index that were left to be saved later, like reading progress, are saved by the worker
+//! too.
+
+use std::{
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+
+use common::{
+    database::Database,
+    models::{Download, DownloadStatus, Id},
+    prelude::*,
+};
+
+use format_ao3::site::ARCHIVE;
+
+use crate::{ao3, updates, utils};
+
+/// How many times a download is tried before it's marked as failed.
+pub const MAX_ATTEMPTS: usize = 5;
+
+/// How long to wait after the first failed attempt, doubled for every one after it.
+const BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// How long the worker sleeps when there's nothing to do and nothing wakes it.
+const POLL: Duration = Duration::from_secs(60);
+
+/// How long a change left for the worker to save can go unsaved.
+const FLUSH: Duration = Duration::from_secs(10);
+
+enum Signal {
+    Wake,
+    Check,
+    Stop,
+}
+
+/// What the worker was told while it was pausing between works, other than to stop.
+#[derive(Debug, Default, PartialEq)]
+struct Pending {
+    wake: bool,
+    check: bool,
+}
+
+/// Lets handlers tell the worker there's something new in the queue.
+pub struct Waker(Sender<Signal>);
+
+impl Waker {
+    pub fn wake(&self) {
+        // the worker only goes away once it's been told to stop, there's nothing to wake then
+        let _ = self.0.send(Signal::Wake);
+    }
+
+    /// Checks followed works for updates now, rather than waiting for the next check.
+    pub fn check(&self) {
+        let _ = self.0.send(Signal::Check);
+    }
+
+    /// Stops the worker once it's finished with the download it's on, if any.
+    pub fn stop(&self) {
+        let _ = self.0.send(Signal::Stop);
+    }
+}
+
+/// Starts the worker, putting back any download that was interrupted by the server stopping.
+pub fn spawn(db: Arc<RwLock<Database>>) -> Result<(Waker, JoinHandle<()>)> {
+    {
+        let mut db = utils::write(&db)?;
+
+        let mut interrupted = false;
+
+        for download in db.downloads_mut() {
+            if download.status == DownloadStatus::Downloading {
+                download.status = DownloadStatus::Queued;
+                interrupted = true;
+            }
+        }
+
+        if interrupted {
+            db.save()?;
+        }
+    }
+
+    let (sender, receiver) = mpsc::channel();
+
+    let handle = thread::Builder::new()
+        .name("downloads".to_string())
+        .spawn(move || work(&db, &receiver))?;
+
+    Ok((Waker(sender), handle))
+}
+
+/// Adds the work the link is to to the queue, returning its ID.
+///
+/// A work that was already downloaded, or failed to be, is queued again. Links to a chapter without the work
+/// in them are looked up on the archive first, which is the only time a request waits on it.
+pub fn push(db: &RwLock<Database>, url: &str) -> Result<String> {
+    let work = ao3::resolve_work(ARCHIVE, url)?;
+
+    let mut db = utils::write(db)?;
+
+    enqueue(db.downloads_mut(), url, &work, SystemTime::now());
+
+    db.save()?;
+
+    Ok(work)
+}
+
+/// Removes a download from the queue, unless it's being downloaded right now.
+pub fn remove(db: &RwLock<Database>, work: &str) -> Result<()> {
+    let mut db = utils::write(db)?;
+
+    db.downloads_mut()
+        .retain(|download| download.work != work || download.status == DownloadStatus::Downloading);
+
+    db.save()?;
+
+    Ok(())
+}
+
+/// Removes every download that has been indexed.
+pub fn clear(db: &RwLock<Database>) -> Result<()> {
+    let mut db = utils::write(db)?;
+
+    db.downloads_mut()
+        .retain(|download| download.status != DownloadStatus::Indexed);
+
+    db.save()?;
+
+    Ok(())
+}
+
+fn work(db: &RwLock<Database>, signals: &Receiver<Signal>) {
+    let mut check_at = Instant::now() + updates::FIRST;
+
+    loop {
+        if check_at <= Instant::now() {
+            let mut stopped = false;
+            let mut pending = Pending::default();
+
+            let checked = updates::check(db, ARCHIVE, || {
+                // a check can take a while, progress shouldn't wait on it to be saved
+                flush(db);
+
+                stopped = !pause(signals, updates::SPACING, &mut pending);
+
+                // a download that was queued in the meantime shouldn't wait on the whole check either
+                if !stopped && std::mem::take(&mut pending.wake) {
+                    step(db);
+                }
+
+                !stopped
+            });
+
+            if let Err(err) = checked {
+                error!(target: "updates", "{}", format!("{:#}", err));
+            }
+
+            if stopped {
+                break;
+            }
+
+            // a check asked for part way through might be for a work the check had already gone past
+            check_at = if pending.check {
+                Instant::now()
+            } else {
+                Instant::now() + updates::INTERVAL
+            };
+
+            continue;
+        }
+
+        let wait = step(db);
+
+        flush(db);
+
+        let check_in = check_at.saturating_duration_since(Instant::now());
+
+        match signals.recv_timeout(wait.min(check_in).min(FLUSH)) {
+            Ok(Signal::Wake) | Err(RecvTimeoutError::Timeout) => {}
+            Ok(Signal::Check) => check_at = Instant::now(),
+            Ok(Signal::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    flush(db);
+}
+
+/// Downloads the next work that's due, if any, giving back how long until there's another.
+fn step(db: &RwLock<Database>) -> Duration {
+    match take(db) {
+        Ok(Next::Download(work)) => {
+            let result =
+                ao3::fetch_work(ARCHIVE, &work).and_then(|text| ao3::save_work(db, &work, &text));
+
+            if let Err(err) = finish(db, &work, result) {
+                error!(target: "download", "{}", format!("{:#}", err));
+            }
+
+            Duration::ZERO
+        }
+        Ok(Next::Wait(wait)) => wait,
+        Err(err) => {
+            error!(target: "download", "{}", format!("{:#}", err));
+
+            POLL
+        }
+    }
+}
+
+/// Waits for a while, giving back `false` if the worker was told to stop in the meantime.
+///
+/// Anything else it's told is kept in `pending` for once the wait is over.
+fn pause(signals: &Receiver<Signal>, duration: Duration, pending: &mut Pending) -> bool {
+    let until = Instant::now() + duration;
+
+    loop {
+        let left = until.saturating_duration_since(Instant::now());
+
+        match signals.recv_timeout(left) {
+            Ok(Signal::Wake) => pending.wake = true,
+            Ok(Signal::Check) => pending.check = true,
+            Err(RecvTimeoutError::Timeout) => return true,
+            Ok(Signal::Stop) | Err(RecvTimeoutError::Disconnected) => return false,
+        }
+    }
+}
+
+fn flush(db: &RwLock<Database>) {
+    if let Err(err) = utils::read(db).and_then(|db| db.save_dirty()) {
+        error!(target: "download", "{}", format!("{:#}", err));
+    }
+}
+
+/// Marks the next download that's due as being downloaded.
+fn take(db: &RwLock<Database>) -> Result<Next> {
+    let mut db = utils::write(db)?;
+
+    let next = next(db.downloads_mut(), SystemTime::now());
+
+    if let Next::Download(_) = next {
+        db.save()?;
+    }
+
+    Ok(next)
+}
+
+fn finish(db: &RwLock<Database>, work: &str, result: Result<Id>) -> Result<()> {
+    let mut db = utils::write(db)?;
+
+    let download = match db
+        .downloads_mut()
+        .iter_mut()
+        .find(|download| download.work == work)
+    {
+        Some(download) => download,
+        // removed from the queue while it was downloading
+        None => return Ok(()),
+    };
+
+    let result = result.map_err(|err| format!("{:#}", err));
+
+    if let Err(err) = &result {
+        warn!(
+            target: "download",
+            "attempt {} at downloading work {} failed: {}",
+            download.attempts,
+            work,
+            err
+        );
+    }
+
+    complete(download, result, SystemTime::now());
+
+    db.save()?;
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum Next {
+    /// The ID of the work to download.
+    Download(String),
+    /// How long until a failed download can be tried again.
+    Wait(Duration),
+}
+
+fn enqueue(downloads: &mut Vec<Download>, url: &str, work: &str, now: SystemTime) {
+    match downloads.iter_mut().find(|download| download.work == work) {
+        Some(download) if download.status.is_done() => {
+            download.url = url.to_string();
+            download.status = DownloadStatus::Queued;
+            download.error = None;
+            download.attempts = 0;
+            download.updated = timestamp(now);
+            download.retry_at = None;
+        }
+        // already waiting, or being downloaded right now
+        Some(_) => {}
+        None => downloads.push(Download {
+            url: url.to_string(),
+            work: work.to_string(),
+            status: DownloadStatus::Queued,
+            error: None,
+            attempts: 0,
+            updated: timestamp(now),
+            retry_at: None,
+            story: None,
+        }),
+    }
+}
+
+fn next(downloads: &mut [Download], now: SystemTime) -> Next {
+    let mut wait = POLL;
+
+    for download in downloads {
+        if download.status != DownloadStatus::Queued {
+            continue;
+        }
+
+        // a time that can't be read is treated as already passed, rather than holding up the download forever
+        let retry_at = download
+            .retry_at
+            .as_deref()
+            .and_then(|retry_at| humantime::parse_rfc3339(retry_at).ok());
+
+        match retry_at.and_then(|retry_at| retry_at.duration_since(now).ok()) {
+            Some(left) if !left.is_zero() => wait = wait.min(left),
+            _ => {
+                download.status = DownloadStatus::Downloading;
+                download.attempts += 1;
+                download.updated = timestamp(now);
+
+                return Next::Download(download.work.clone());
+            }
+        }
+    }
+
+    Next::Wait(wait)
+}
+
+fn complete(download: &mut Download, result: Result<Id, String>, now: SystemTime) {
+    download.updated = timestamp(now);
+
+    match result {
+        Ok(id) => {
+            download.status = DownloadStatus::Indexed;
+            download.error = None;
+            download.retry_at = None;
+            download.story = Some(id);
+        }
+        Err(err) if download.attempts >= MAX_ATTEMPTS => {
+            download.status = DownloadStatus::Failed;
+            download.error = Some(err);
+            download.retry_at = None;
+        }
+        Err(err) => {
+            download.status = DownloadStatus::Queued;
+            download.error = Some(err);
+            download.retry_at = Some(timestamp(now + backoff(download.attempts)));
+        }
+    }
+}
+
+/// How long to wait before trying again after the given number of attempts.
+fn backoff(attempts: usize) -> Duration {
+    let factor = u32::try_from(attempts.saturating_sub(1))
+        .ok()
+        .and_then(|shift| 1u32.checked_shl(shift))
+        .unwrap_or(u32::MAX);
+
+    BACKOFF.saturating_mul(factor).min(MAX_BACKOFF)
+}
+
+fn timestamp(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const URL: &str = "https://archiveofourown.org/works/1";
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(Duration::from_secs(30), backoff(1));
+        assert_eq!(Duration::from_secs(60), backoff(2));
+        assert_eq!(Duration::from_secs(480), backoff(5));
+        assert_eq!(MAX_BACKOFF, backoff(10));
+        assert_eq!(MAX_BACKOFF, backoff(usize::MAX));
+    }
+
+    #[test]
+    fn test_pause() {
+        let (sender, signals) = mpsc::channel();
+        let mut pending = Pending::default();
+
+        assert!(pause(&signals, Duration::from_millis(10), &mut pending));
+        assert_eq!(Pending::default(), pending);
+
+        sender.send(Signal::Wake).unwrap();
+        assert!(pause(&signals, Duration::from_millis(10), &mut pending));
+        assert_eq!(
+            Pending {
+                wake: true,
+                check: false
+            },
+            pending
+        );
+
+        sender.send(Signal::Check).unwrap();
+        sender.send(Signal::Stop).unwrap();
+        assert!(!pause(&signals, Duration::from_secs(60), &mut pending));
+        assert_eq!(
+            Pending {
+                wake: true,
+                check: true
+            },
+            pending
+        );
+
+        drop(sender);
+        assert!(!pause(&signals, Duration::from_secs(60), &mut pending));
+    }
+
+    #[test]
+    fn test_enqueue() {
+        let mut downloads = Vec::new();
+
+        enqueue(&mut downloads, URL, "1", at(0));
+        enqueue(&mut downloads, "https://ao3.org/works/1", "1", at(1));
+
+        assert_eq!(1, downloads.len());
+        assert_eq!(URL, downloads[0].url);
+
+        downloads[0].status = DownloadStatus::Failed;
+        downloads[0].attempts = MAX_ATTEMPTS;
+        downloads[0].error = Some("request timed out".to_string());
+
+        enqueue(&mut downloads, URL, "1", at(2));
+
+        assert_eq!(DownloadStatus::Queued, downloads[0].status);
+        assert_eq!(0, downloads[0].attempts);
+        assert_eq!(None, downloads[0].error);
+        assert_eq!("1970-01-01T00:00:02Z", downloads[0].updated);
+    }
+
+    #[test]
+    fn test_retry() {
+        let mut downloads = Vec::new();
+
+        enqueue(&mut downloads, URL, "1", at(0));
+
+        assert_eq!(Next::Download("1".to_string()), next(&mut downloads, at(0)));
+        assert_eq!(DownloadStatus::Downloading, downloads[0].status);
+        assert_eq!(Next::Wait(POLL), next(&mut downloads, at(0)));
+
+        complete(
+            &mut downloads[0],
+            Err("request timed out".to_string()),
+            at(10),
+        );
+
+        assert_eq!(DownloadStatus::Queued, downloads[0].status);
+        assert_eq!(
+            Some("1970-01-01T00:00:40Z"),
+            downloads[0].retry_at.as_deref()
+        );
+
+        assert_eq!(
+            Next::Wait(Duration::from_secs(20)),
+            next(&mut downloads, at(20))
+        );
+        assert_eq!(
+            Next::Download("1".to_string()),
+            next(&mut downloads, at(40))
+        );
+        assert_eq!(2, downloads[0].attempts);
+
+        complete(&mut downloads[0], Ok(Id::from("a".to_string())), at(50));
+
+        assert_eq!(DownloadStatus::Indexed, downloads[0].status);
+        assert_eq!(None, downloads[0].error);
+        assert_eq!(Some("a"), downloads[0].story.as_ref().map(Id::as_str));
+        assert_eq!(Next::Wait(POLL), next(&mut downloads, at(50)));
+    }
+
+    #[test]
+    fn test_give_up() {
+        let mut downloads = Vec::new();
+
+        enqueue(&mut downloads, URL, "1", at(0));
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            let now = at(attempt as u64 * 60 * 60);
+
+            assert_eq!(Next::Download("1".to_string()), next(&mut downloads, now));
+
+            complete(&mut downloads[0], Err("not found".to_string()), now);
+        }
+
+        assert_eq!(DownloadStatus::Failed, downloads[0].status);
+        assert_eq!(Some("not found"), downloads[0].error.as_deref());
+        assert_eq!(Next::Wait(POLL), next(&mut downloads, at(60 * 60 * 24)));
+    }
+}
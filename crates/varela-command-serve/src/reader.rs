@@ -0,0 +1,226 @@
+//! Reading preferences, kept in a cookie so that they follow the browser rather than the library.
+
+use enrgy::http::encoding::form;
+
+/// The name of the cookie the preferences are stored in.
+pub const COOKIE: &str = "reader";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Sans,
+    Serif,
+    Mono,
+}
+
+impl Font {
+    pub const ALL: [Font; 3] = [Font::Sans, Font::Serif, Font::Mono];
+
+    pub const fn slug(self) -> &'static str {
+        match self {
+            Font::Sans => "sans",
+            Font::Serif => "serif",
+            Font::Mono => "mono",
+        }
+    }
+
+    pub fn from_slug(slug: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|font| font.slug() == slug)
+    }
+
+    const fn family(self) -> &'static str {
+        match self {
+            Font::Sans => "system-ui,-apple-system,Segoe UI,Roboto,Helvetica,Arial,sans-serif",
+            Font::Serif => "Georgia,Cambria,Times New Roman,Times,serif",
+            Font::Mono => "ui-monospace,SFMono-Regular,Menlo,Consolas,Liberation Mono,monospace",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preferences {
+    pub font: Font,
+    /// The font size, in pixels.
+    pub size: usize,
+    /// The line height, in tenths of the font size.
+    pub line_height: usize,
+    /// The widest the text can get, in characters, or `0` to fill the page.
+    pub width: usize,
+    pub justify: bool,
+    /// The space between paragraphs, in tenths of the font size.
+    pub spacing: usize,
+}
+
+impl Preferences {
+    pub const SIZES: [usize; 6] = [14, 16, 18, 20, 22, 24];
+    pub const LINE_HEIGHTS: [usize; 4] = [12, 15, 18, 21];
+    pub const WIDTHS: [usize; 5] = [0, 50, 65, 80, 100];
+    pub const SPACINGS: [usize; 4] = [0, 5, 10, 15];
+
+    /// Reads the preferences out of a `Cookie` header, unknown or missing values are left as their defaults.
+    pub fn from_cookies(header: Option<&str>) -> Self {
+        header
+            .into_iter()
+            .flat_map(|header| header.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, _)| *name == COOKIE)
+            .map(|(_, value)| {
+                Self::from_pairs(
+                    form::parse(value.as_bytes()).map(|(k, v)| (k.into_owned(), v.into_owned())),
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    /// Reads the preferences from form encoded pairs, unknown or missing values are left as their defaults.
+    pub fn from_pairs<K, V>(pairs: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut preferences = Self::default();
+
+        let pick = |allowed: &[usize], value: &str| {
+            value.parse().ok().filter(|value| allowed.contains(value))
+        };
+
+        for (key, value) in pairs {
+            let value = value.as_ref();
+
+            match key.as_ref() {
+                "font" => {
+                    if let Some(font) = Font::from_slug(value) {
+                        preferences.font = font;
+                    }
+                }
+                "size" => {
+                    if let Some(size) = pick(&Self::SIZES, value) {
+                        preferences.size = size;
+                    }
+                }
+                "line" => {
+                    if let Some(line_height) = pick(&Self::LINE_HEIGHTS, value) {
+                        preferences.line_height = line_height;
+                    }
+                }
+                "width" => {
+                    if let Some(width) = pick(&Self::WIDTHS, value) {
+                        preferences.width = width;
+                    }
+                }
+                "justify" => preferences.justify = value == "on",
+                "spacing" => {
+                    if let Some(spacing) = pick(&Self::SPACINGS, value) {
+                        preferences.spacing = spacing;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        preferences
+    }
+
+    /// The value to store in the cookie, readable by [`Preferences::from_cookies`].
+    pub fn to_cookie(self) -> String {
+        vfmt::format!(
+            "font={}&size={}&line={}&width={}&justify={}&spacing={}",
+            self.font.slug(),
+            self.size,
+            self.line_height,
+            self.width,
+            if self.justify { "on" } else { "off" },
+            self.spacing,
+        )
+    }
+
+    /// The styles applied to the `.reader` text of the chapter pages.
+    pub fn css(self) -> String {
+        let mut css = vfmt::format!(
+            ".reader{{font-family:{};font-size:{}px;line-height:{}.{};",
+            self.font.family(),
+            self.size,
+            self.line_height / 10,
+            self.line_height % 10,
+        );
+
+        if self.width != 0 {
+            css.push_str(&vfmt::format!(
+                "max-width:{}ch;margin-left:auto;margin-right:auto;",
+                self.width
+            ));
+        }
+
+        if self.justify {
+            css.push_str("text-align:justify;");
+        }
+
+        css.push_str(&vfmt::format!(
+            "}}.reader>p{{padding-bottom:{}.{}em}}",
+            self.spacing / 10,
+            self.spacing % 10,
+        ));
+
+        css
+    }
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            font: Font::Sans,
+            size: 16,
+            line_height: 15,
+            width: 0,
+            justify: false,
+            spacing: 5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cookie_round_trip() {
+        let preferences = Preferences {
+            font: Font::Serif,
+            size: 20,
+            line_height: 18,
+            width: 65,
+            justify: true,
+            spacing: 10,
+        };
+
+        let header = vfmt::format!("theme=dark; {}={}", COOKIE, preferences.to_cookie());
+
+        assert_eq!(preferences, Preferences::from_cookies(Some(&header)));
+    }
+
+    #[test]
+    fn test_invalid_values_are_ignored() {
+        let preferences = Preferences::from_cookies(Some("reader=font=comic&size=200&line=18"));
+
+        assert_eq!(
+            Preferences {
+                line_height: 18,
+                ..Preferences::default()
+            },
+            preferences
+        );
+
+        assert_eq!(Preferences::default(), Preferences::from_cookies(None));
+    }
+
+    #[test]
+    fn test_css() {
+        let css = Preferences {
+            width: 65,
+            ..Preferences::default()
+        }
+        .css();
+
+        assert!(css.contains("font-size:16px;line-height:1.5;max-width:65ch;"));
+        assert!(css.ends_with(".reader>p{padding-bottom:0.5em}"));
+    }
+}
@@ -1,18 +1,52 @@
 use std::{
     borrow::{Borrow as _, Cow},
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     hash::Hash,
+    ops::Range,
 };
 
 use common::{
     database::Database,
-    models::{Entity, Id, Index, Rating, Story},
+    models::{
+        Bookmark, Entity, EntityKind, Id, Index, Library, Rating, Settings, Status, Story,
+        StoryMeta,
+    },
 };
 
+use crate::fuzzy;
+
 pub fn search_v2<'s>(
-    query: &[(Cow<'s, str>, Cow<'s, str>)],
+    query: &[(Cow<'_, str>, Cow<'_, str>)],
+    library: &Library,
     stories: &mut Vec<(&'s Id, &'s Story)>,
 ) -> Stats<'s> {
+    filter(query, library, stories);
+
+    Stats::new(&stories[..], library)
+}
+
+/// Counts the stories a saved `/search2` query would list, the default filter included.
+pub fn count(database: &Database, query: &str) -> usize {
+    let default = DefaultFilter::new(database.settings(), database.library());
+
+    let mut stories = database
+        .index()
+        .stories
+        .iter()
+        .filter(|(id, story)| default.allows(id, story))
+        .collect::<Vec<_>>();
+
+    filter(&parse_query(query)[..], database.library(), &mut stories);
+
+    stories.len()
+}
+
+/// Removes the stories that don't match a parsed `/search2` query.
+pub fn filter<'s>(
+    query: &[(Cow<'_, str>, Cow<'_, str>)],
+    library: &Library,
+    stories: &mut Vec<(&'s Id, &'s Story)>,
+) {
     // modified version of [`Iterator::partition`] to remove [`Default`] bounds
     #[inline]
     fn partition<I, B, F>(iter: I, f: F) -> (Vec<B>, Vec<B>)
@@ -60,48 +94,196 @@ pub fn search_v2<'s>(
         |(key, _value)| Group::match_include(key.borrow()),
     );
 
-    let include = Group::from(include);
-    let exclude = Group::from(exclude);
+    let include = Group::new(include, library);
+    let exclude = Group::new(exclude, library);
 
     include.filter(stories, true);
     exclude.filter(stories, false);
-
-    Stats::new(&stories[..])
 }
 
 pub struct Stats<'m> {
     ratings: Vec<(Rating, usize)>,
-    warnings: Vec<(&'m Id, usize)>,
-    categories: Vec<(&'m Id, usize)>,
-    origins: Vec<(&'m Id, usize)>,
-    pairings: Vec<(&'m Id, usize)>,
-    characters: Vec<(&'m Id, usize)>,
-    generals: Vec<(&'m Id, usize)>,
+    entities: Vec<(StatKind, Vec<(&'m Id, usize)>)>,
+    /// Counts for the facets that come from the reader's library, keyed by their query value.
+    library: Vec<(StatKind, Vec<(String, usize)>)>,
+}
+
+/// The kinds of facet counted for a search, in the order they are shown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StatKind {
+    Rating,
+    Warning,
+    Category,
+    Origin,
+    Author,
+    Pairing,
+    Character,
+    General,
+    Shelf,
+    UserRating,
+    UserTag,
 }
 
-pub struct StatKind {}
+impl StatKind {
+    const ENTITIES: [StatKind; 7] = [
+        StatKind::Warning,
+        StatKind::Category,
+        StatKind::Origin,
+        StatKind::Author,
+        StatKind::Pairing,
+        StatKind::Character,
+        StatKind::General,
+    ];
+
+    const LIBRARY: [StatKind; 3] = [StatKind::Shelf, StatKind::UserRating, StatKind::UserTag];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            StatKind::Rating => "ratings",
+            StatKind::Warning => "warnings",
+            StatKind::Category => "categories",
+            StatKind::Origin => "origins",
+            StatKind::Author => "authors",
+            StatKind::Pairing => "pairings",
+            StatKind::Character => "characters",
+            StatKind::General => "generals",
+            StatKind::Shelf => "shelves",
+            StatKind::UserRating => "my ratings",
+            StatKind::UserTag => "my tags",
+        }
+    }
+
+    /// The `/search2` query key that requires the facet's value.
+    pub const fn include_key(self) -> &'static str {
+        match self {
+            StatKind::Rating => "ir",
+            StatKind::Warning => "iw",
+            StatKind::Category => "ict",
+            StatKind::Origin => "io",
+            StatKind::Author => "ia",
+            StatKind::Pairing => "ip",
+            StatKind::Character => "ich",
+            StatKind::General => "ig",
+            StatKind::Shelf => "is",
+            StatKind::UserRating => "iu",
+            StatKind::UserTag => "it",
+        }
+    }
+
+    /// The `/search2` query key that forbids the facet's value.
+    pub const fn exclude_key(self) -> &'static str {
+        match self {
+            StatKind::Rating => "er",
+            StatKind::Warning => "ew",
+            StatKind::Category => "ect",
+            StatKind::Origin => "eo",
+            StatKind::Author => "ea",
+            StatKind::Pairing => "ep",
+            StatKind::Character => "ech",
+            StatKind::General => "eg",
+            StatKind::Shelf => "es",
+            StatKind::UserRating => "eu",
+            StatKind::UserTag => "et",
+        }
+    }
+
+    /// The kind of entity the facet's values are, if they're entities at all.
+    const fn kind(self) -> Option<EntityKind> {
+        match self {
+            StatKind::Rating | StatKind::Shelf | StatKind::UserRating | StatKind::UserTag => None,
+            StatKind::Warning => Some(EntityKind::Warning),
+            StatKind::Category => Some(EntityKind::Category),
+            StatKind::Origin => Some(EntityKind::Origin),
+            StatKind::Author => Some(EntityKind::Author),
+            StatKind::Pairing => Some(EntityKind::Pairing),
+            StatKind::Character => Some(EntityKind::Character),
+            StatKind::General => Some(EntityKind::General),
+        }
+    }
+
+    fn ids(self, meta: &StoryMeta) -> &[Id] {
+        self.kind().map(|kind| meta.entities(kind)).unwrap_or(&[])
+    }
+
+    fn entities(self, index: &Index) -> Option<&HashMap<Id, Entity>> {
+        self.kind().map(|kind| index.entities(kind))
+    }
+
+    /// Lists the values of a library facet with the stories that have each of them.
+    fn members(self, library: &Library) -> Vec<(String, Vec<&Id>)> {
+        match self {
+            StatKind::Shelf => library
+                .shelf_names()
+                .into_iter()
+                .map(|name| (name.to_owned(), self.lookup(library, name)))
+                .collect(),
+            StatKind::UserRating => (1..=Bookmark::RATING_MAX)
+                .map(|rating| (vfmt::format!("{}", rating), library.rated(rating)))
+                .collect(),
+            StatKind::UserTag => library
+                .tags()
+                .into_iter()
+                .map(|tag| (tag.to_owned(), library.tagged(tag)))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Gets the stories with a library facet's value, unknown values have no stories.
+    fn lookup<'l>(self, library: &'l Library, value: &str) -> Vec<&'l Id> {
+        match self {
+            StatKind::Shelf => library.shelf(value).unwrap_or_default(),
+            StatKind::UserRating => value
+                .parse()
+                .map(|rating| library.rated(rating))
+                .unwrap_or_default(),
+            StatKind::UserTag => library.tagged(value),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The text shown for a library facet's value.
+    fn text(self, value: &str) -> String {
+        match self {
+            StatKind::Shelf => Status::from_slug(value)
+                .map(Status::name)
+                .unwrap_or(value)
+                .to_owned(),
+            StatKind::UserRating => match value.parse::<usize>() {
+                Ok(rating) if rating <= Bookmark::RATING_MAX => "\u{2605}".repeat(rating),
+                _ => value.to_owned(),
+            },
+            _ => value.to_owned(),
+        }
+    }
+}
 
 pub struct FilledStats {
-    pub ratings: Vec<(Rating, usize)>,
-    pub warnings: Vec<(Entity, usize)>,
-    pub categories: Vec<(Entity, usize)>,
-    pub origins: Vec<(Entity, usize)>,
-    pub pairings: Vec<(Entity, usize)>,
-    pub characters: Vec<(Entity, usize)>,
-    pub generals: Vec<(Entity, usize)>,
+    pub facets: Vec<Facet>,
 }
 
-impl<'m> Stats<'m> {
-    fn new(stories: &[(&'m Id, &'m Story)]) -> Stats<'m> {
-        let mut ratings = HashMap::<Rating, usize>::new();
+pub struct Facet {
+    pub kind: StatKind,
+    pub entries: Vec<FacetEntry>,
+}
 
-        let mut warnings = HashMap::<&Id, usize>::new();
-        let mut categories = HashMap::<&Id, usize>::new();
-        let mut origins = HashMap::<&Id, usize>::new();
-        let mut pairings = HashMap::<&Id, usize>::new();
-        let mut characters = HashMap::<&Id, usize>::new();
-        let mut generals = HashMap::<&Id, usize>::new();
+impl Facet {
+    /// How many entries are shown before the rest are tucked behind "show more".
+    pub const PREVIEW: usize = 10;
+}
+
+pub struct FacetEntry {
+    /// The value given to the facet's query keys, an entity's id or a rating's class.
+    pub value: String,
+    pub text: String,
+    /// The number of stories matching the current filter that have this value.
+    pub count: usize,
+    pub included: bool,
+    pub excluded: bool,
+}
 
+impl<'m> Stats<'m> {
+    fn new(stories: &[(&'m Id, &'m Story)], library: &Library) -> Stats<'m> {
         fn inc<K>(map: &mut HashMap<K, usize>, entry: K)
         where
             K: Eq + Hash,
@@ -111,78 +293,219 @@ impl<'m> Stats<'m> {
                 .or_insert(1);
         }
 
+        let mut ratings = HashMap::<Rating, usize>::new();
+
         for (_, story) in stories {
-            let meta = &story.meta;
-
-            inc(&mut ratings, meta.rating);
-
-            let lists = vec![
-                (&mut warnings, &meta.warnings),
-                (&mut categories, &meta.categories),
-                (&mut origins, &meta.origins),
-                (&mut pairings, &meta.pairings),
-                (&mut characters, &meta.characters),
-                (&mut generals, &meta.generals),
-            ];
-
-            for (map, list) in lists {
-                for entry in list {
-                    inc(map, entry);
+            inc(&mut ratings, story.meta.rating);
+        }
+
+        let entities = StatKind::ENTITIES
+            .into_iter()
+            .map(|kind| {
+                let mut map = HashMap::<&Id, usize>::new();
+
+                for (_, story) in stories {
+                    for entry in kind.ids(&story.meta) {
+                        inc(&mut map, entry);
+                    }
                 }
+
+                (kind, map.into_iter().collect::<Vec<_>>())
+            })
+            .collect();
+
+        let matched = stories.iter().map(|(id, _)| *id).collect::<HashSet<_>>();
+
+        let library = StatKind::LIBRARY
+            .into_iter()
+            .map(|kind| {
+                let counts = kind
+                    .members(library)
+                    .into_iter()
+                    .filter_map(|(value, members)| {
+                        let count = members.iter().filter(|id| matched.contains(*id)).count();
+
+                        (count != 0).then_some((value, count))
+                    })
+                    .collect();
+
+                (kind, counts)
+            })
+            .collect();
+
+        Stats {
+            ratings: ratings.into_iter().collect(),
+            entities,
+            library,
+        }
+    }
+
+    /// Resolves the counted ids, marking the values the query includes or excludes.
+    ///
+    /// Values in the query are always listed, even when no story matches them anymore, so that
+    /// they can be unchecked. Ids that aren't in the index are left out.
+    pub fn fill(self, index: &'m Index, query: &[(Cow<'_, str>, Cow<'_, str>)]) -> FilledStats {
+        fn entry(
+            kind: StatKind,
+            query: &[(Cow<'_, str>, Cow<'_, str>)],
+            value: &str,
+            text: &str,
+            count: usize,
+        ) -> FacetEntry {
+            let active = |key: &str| query.iter().any(|(k, v)| k == key && v == value);
+
+            FacetEntry {
+                value: value.to_owned(),
+                text: text.to_owned(),
+                count,
+                included: active(kind.include_key()),
+                excluded: active(kind.exclude_key()),
             }
         }
 
-        fn to_top_list<K>(map: HashMap<K, usize>) -> Vec<(K, usize)>
-        where
-            K: Eq + Hash,
-        {
-            let mut list = map.into_iter().collect::<Vec<_>>();
+        fn queried<'q>(
+            kind: StatKind,
+            query: &'q [(Cow<'_, str>, Cow<'_, str>)],
+        ) -> impl Iterator<Item = &'q str> {
+            query
+                .iter()
+                .filter(move |(key, _)| key == kind.include_key() || key == kind.exclude_key())
+                .map(|(_, value)| value.as_ref())
+        }
 
-            list.sort_by(|a, b| a.1.cmp(&b.1).reverse());
+        let mut facets = Vec::with_capacity(self.entities.len() + 2);
+
+        let mut ratings = self
+            .ratings
+            .into_iter()
+            .map(|(rating, count)| {
+                entry(
+                    StatKind::Rating,
+                    query,
+                    rating.class(),
+                    rating.name(),
+                    count,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for value in queried(StatKind::Rating, query) {
+            let rating = RATINGS
+                .into_iter()
+                .find(|rating| rating.class() == value || rating.symbol() == value);
+
+            if let Some(rating) = rating {
+                if !ratings.iter().any(|entry| entry.value == rating.class()) {
+                    ratings.push(entry(
+                        StatKind::Rating,
+                        query,
+                        rating.class(),
+                        rating.name(),
+                        0,
+                    ));
+                }
+            }
+        }
 
-            list.truncate(10);
+        facets.push(Facet {
+            kind: StatKind::Rating,
+            entries: ratings,
+        });
 
-            list
+        for (kind, list) in self.entities {
+            let Some(tree) = kind.entities(index) else {
+                continue;
+            };
+
+            let mut entries = list
+                .into_iter()
+                .filter_map(|(id, count)| {
+                    tree.get(id)
+                        .map(|entity| entry(kind, query, id.as_str(), &entity.text, count))
+                })
+                .collect::<Vec<_>>();
+
+            for value in queried(kind, query) {
+                if entries.iter().any(|entry| entry.value == value) {
+                    continue;
+                }
+
+                if let Some(entity) = tree.get(&Id::from(value.to_owned())) {
+                    entries.push(entry(kind, query, value, &entity.text, 0));
+                }
+            }
+
+            facets.push(Facet { kind, entries });
         }
 
-        Stats {
-            ratings: to_top_list(ratings),
-            warnings: to_top_list(warnings),
-            categories: to_top_list(categories),
-            origins: to_top_list(origins),
-            pairings: to_top_list(pairings),
-            characters: to_top_list(characters),
-            generals: to_top_list(generals),
+        for (kind, counts) in self.library {
+            let mut entries = counts
+                .iter()
+                .map(|(value, count)| entry(kind, query, value, &kind.text(value), *count))
+                .collect::<Vec<_>>();
+
+            for value in queried(kind, query) {
+                if !entries.iter().any(|entry| entry.value == value) {
+                    entries.push(entry(kind, query, value, &kind.text(value), 0));
+                }
+            }
+
+            facets.push(Facet { kind, entries });
+        }
+
+        for facet in &mut facets {
+            facet
+                .entries
+                .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.text.cmp(&b.text)));
         }
+
+        FilledStats { facets }
     }
+}
 
-    pub fn fill(self, index: &'m Index) -> Option<FilledStats> {
-        fn fill<'m>(
-            list: Vec<(&'m Id, usize)>,
-            tree: &'m HashMap<Id, Entity>,
-        ) -> Option<Vec<(Entity, usize)>> {
-            list.into_iter()
-                .map(|(id, count)| tree.get(id).map(|entity| (entity.clone(), count)))
-                .collect::<Option<Vec<_>>>()
-        }
-
-        Some(FilledStats {
-            ratings: self.ratings,
-            warnings: fill(self.warnings, &index.warnings)?,
-            categories: fill(self.categories, &index.categories)?,
-            origins: fill(self.origins, &index.origins)?,
-            pairings: fill(self.pairings, &index.pairings)?,
-            characters: fill(self.characters, &index.characters)?,
-            generals: fill(self.generals, &index.generals)?,
-        })
+const RATINGS: [Rating; 6] = [
+    Rating::Explicit,
+    Rating::Mature,
+    Rating::Teen,
+    Rating::General,
+    Rating::NotRated,
+    Rating::Unknown,
+];
+
+/// The exclusions of the saved search set as the default filter, applied to every listing page.
+pub struct DefaultFilter(Group);
+
+impl DefaultFilter {
+    /// Creates the filter from the settings, allowing every story if no default is set.
+    pub fn new(settings: &Settings, library: &Library) -> Self {
+        let saved = settings.default_filter.as_ref().and_then(|name| {
+            settings
+                .saved_searches
+                .iter()
+                .find(|saved| &saved.name == name)
+        });
+
+        let exclude = saved
+            .map(|saved| {
+                parse_query(&saved.query)
+                    .into_iter()
+                    .filter(|(key, _)| Group::match_exclude(key))
+                    .map(|(key, value)| (key, Id::from(value.into_owned())))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        DefaultFilter(Group::new(exclude, library))
+    }
+
+    pub fn allows(&self, id: &Id, story: &Story) -> bool {
+        self.0.allows(id, story, false)
     }
 }
 
-pub enum EntityKind {
-    Origin,
-    Pairing,
-    Character,
-    General,
+/// Parses a `/search2` query string into its key and value pairs.
+pub fn parse_query(query: &str) -> Vec<(Cow<'_, str>, Cow<'_, str>)> {
+    enrgy::http::encoding::form::parse(query.trim_start_matches('?').as_bytes()).collect()
 }
 
 #[derive(Default)]
@@ -191,84 +514,117 @@ struct Group {
     warnings: Option<Vec<Id>>,
     categories: Option<Vec<Id>>,
     origins: Option<Vec<Id>>,
+    authors: Option<Vec<Id>>,
     characters: Option<Vec<Id>>,
     pairings: Option<Vec<Id>>,
     generals: Option<Vec<Id>>,
+    /// the stories on each of the shelves and with each of the reader's tags, looked up when
+    /// the group is made
+    memberships: Option<Vec<Vec<Id>>>,
+    /// the stories with any of the reader's ratings
+    user_ratings: Option<Vec<Id>>,
 }
 
 impl Group {
     #[inline]
     fn match_include(text: &str) -> bool {
-        matches!(text, "ir" | "iw" | "ict" | "io" | "ich" | "ip" | "ig")
+        matches!(
+            text,
+            "ir" | "iw" | "ict" | "io" | "ia" | "ich" | "ip" | "ig" | "is" | "iu" | "it"
+        )
     }
 
     #[inline]
     fn match_exclude(text: &str) -> bool {
-        matches!(text, "er" | "ew" | "ect" | "eo" | "ech" | "ep" | "eg")
+        matches!(
+            text,
+            "er" | "ew" | "ect" | "eo" | "ea" | "ech" | "ep" | "eg" | "es" | "eu" | "et"
+        )
     }
 
-    fn filter<'s>(self, stories: &mut Vec<(&'s Id, &'s Story)>, include: bool) {
-        let lists = vec![
-            self.origins.map(|list| (EntityKind::Origin, list)),
-            self.characters.map(|list| (EntityKind::Character, list)),
-            self.pairings.map(|list| (EntityKind::Pairing, list)),
-            self.generals.map(|list| (EntityKind::General, list)),
-        ];
-
-        for (kind, list) in lists.into_iter().flatten() {
-            Self::filter_retain(stories, kind, include, list);
-        }
+    fn filter<'s>(&self, stories: &mut Vec<(&'s Id, &'s Story)>, include: bool) {
+        stories.retain(|(id, story)| self.allows(id, story, include));
     }
 
-    fn filter_retain<'s>(
-        stories: &mut Vec<(&'s Id, &'s Story)>,
-        kind: EntityKind,
-        include: bool,
-        entities: Vec<Id>,
-    ) {
-        match kind {
-            EntityKind::Origin => {
-                for entity in entities {
-                    stories.retain(|(_id, story)| {
-                        include ^ story.meta.origins.iter().any(|id| id == &entity)
-                    });
-                }
-            }
-            EntityKind::Pairing => {
-                for entity in entities {
-                    stories.retain(|(_id, story)| {
-                        include ^ story.meta.pairings.iter().any(|id| id == &entity)
-                    });
-                }
+    /// Checks a story against the group, an including group needs the story to have one of
+    /// the ratings and every entity, an excluding group needs it to have none of them.
+    fn allows(&self, id: &Id, story: &Story, include: bool) -> bool {
+        let meta = &story.meta;
+
+        if let Some(memberships) = &self.memberships {
+            if !memberships
+                .iter()
+                .all(|members| include == members.contains(id))
+            {
+                return false;
             }
-            EntityKind::Character => {
-                for entity in entities {
-                    stories.retain(|(_id, story)| {
-                        include ^ story.meta.characters.iter().any(|id| id == &entity)
-                    });
-                }
+        }
+
+        if let Some(rated) = &self.user_ratings {
+            if include != rated.contains(id) {
+                return false;
             }
-            EntityKind::General => {
-                for entity in entities {
-                    stories.retain(|(_id, story)| {
-                        include ^ story.meta.generals.iter().any(|id| id == &entity)
-                    });
-                }
+        }
+
+        if let Some(ratings) = &self.rating {
+            let rated = ratings.iter().any(|rating| {
+                rating.as_str() == meta.rating.class() || rating.as_str() == meta.rating.symbol()
+            });
+
+            if include != rated {
+                return false;
             }
         }
+
+        let lists = [
+            (&self.warnings, &meta.warnings),
+            (&self.categories, &meta.categories),
+            (&self.origins, &meta.origins),
+            (&self.authors, &meta.authors),
+            (&self.characters, &meta.characters),
+            (&self.pairings, &meta.pairings),
+            (&self.generals, &meta.generals),
+        ];
+
+        lists
+            .into_iter()
+            .filter_map(|(group, list)| group.as_ref().map(|group| (group, list)))
+            .all(|(group, list)| group.iter().all(|entity| include == list.contains(entity)))
     }
-}
 
-impl<'i> From<Vec<(Cow<'i, str>, Id)>> for Group {
-    fn from(list: Vec<(Cow<'i, str>, Id)>) -> Self {
+    fn new(list: Vec<(Cow<'_, str>, Id)>, library: &Library) -> Self {
         let mut group: Group = Group::default();
 
         for (key, value) in list {
+            let kind = StatKind::LIBRARY
+                .into_iter()
+                .find(|kind| key == kind.include_key() || key == kind.exclude_key());
+
+            if let Some(kind) = kind {
+                // unknown values have no stories, so they match nothing rather than being ignored
+                let members = kind
+                    .lookup(library, value.as_str())
+                    .into_iter()
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                match kind {
+                    StatKind::UserRating => group
+                        .user_ratings
+                        .get_or_insert_with(Vec::new)
+                        .extend(members),
+                    _ => group.memberships.get_or_insert_with(Vec::new).push(members),
+                }
+
+                continue;
+            }
+
             let list = match key.borrow() {
                 "ir" | "er" => Some(&mut group.rating),
                 "iw" | "ew" => Some(&mut group.warnings),
                 "ict" | "ect" => Some(&mut group.categories),
                 "io" | "eo" => Some(&mut group.origins),
+                "ia" | "ea" => Some(&mut group.authors),
                 "ich" | "ech" => Some(&mut group.characters),
                 "ip" | "ep" => Some(&mut group.pairings),
                 "ig" | "eg" => Some(&mut group.generals),
@@ -329,6 +685,188 @@ impl Bound {
     const fn general(include: bool, text: String) -> Bound {
         Bound::General { include, text }
     }
+
+    fn text(&self) -> &str {
+        match self {
+            Bound::Author { text, .. }
+            | Bound::Origin { text, .. }
+            | Bound::Pairing { text, .. }
+            | Bound::Character { text, .. }
+            | Bound::General { text, .. } => text,
+        }
+    }
+
+    const fn kind(&self) -> EntityKind {
+        match self {
+            Bound::Author { .. } => EntityKind::Author,
+            Bound::Origin { .. } => EntityKind::Origin,
+            Bound::Pairing { .. } => EntityKind::Pairing,
+            Bound::Character { .. } => EntityKind::Character,
+            Bound::General { .. } => EntityKind::General,
+        }
+    }
+}
+
+/// A search term that matched no entity, along with the closest entities to it.
+pub struct Suggestion {
+    pub term: String,
+    pub candidates: Vec<String>,
+    span: Span,
+}
+
+impl Suggestion {
+    /// Rewrites `search`, the text the suggestion was made for, with `candidate` written in
+    /// place of the unmatched term.
+    pub fn replace(&self, search: &str, candidate: &str) -> String {
+        let replacement = match self.span.group {
+            Some([open, close, sep]) => {
+                let inner = candidate.split(sep).collect::<Vec<_>>().join(", ");
+
+                vfmt::format!("{}{}{}", open, inner, close)
+            }
+            None => candidate.to_owned(),
+        };
+
+        let mut replaced = search.to_owned();
+
+        replaced.replace_range(self.span.range.clone(), &replacement);
+
+        replaced
+    }
+}
+
+pub fn suggest(database: &Database, text: &str) -> Vec<Suggestion> {
+    let index = database.index();
+
+    parse_spanned(text)
+        .into_iter()
+        .filter(|(bound, _)| !bound.text().is_empty())
+        .filter_map(|(bound, span)| {
+            let entities = index.entities(bound.kind());
+            let term = fuzzy::fold(bound.text());
+
+            if entities
+                .values()
+                .any(|entity| fuzzy::fold(&entity.text) == term)
+            {
+                return None;
+            }
+
+            let candidates = fuzzy::closest(entities.values().map(|e| e.text.as_str()), &term, 3);
+
+            if candidates.is_empty() {
+                None
+            } else {
+                Some(Suggestion {
+                    term: bound.text().to_owned(),
+                    candidates: candidates.into_iter().map(String::from).collect(),
+                    span,
+                })
+            }
+        })
+        .collect()
+}
+
+/// An entity matching an autocomplete query, along with how many stories use it.
+pub struct Completion<'i> {
+    pub id: &'i Id,
+    pub text: &'i str,
+    pub count: usize,
+}
+
+pub fn complete<'i>(
+    index: &'i Index,
+    kind: EntityKind,
+    query: &str,
+    limit: usize,
+) -> Vec<Completion<'i>> {
+    rank(
+        index.entities(kind),
+        index
+            .stories
+            .values()
+            .flat_map(|story| story.meta.entities(kind)),
+        query,
+        limit,
+    )
+}
+
+/// Ranks the entities whose text contains `query`, most used first, with entities starting
+/// with `query` winning ties.
+fn rank<'i, U>(
+    entities: &'i HashMap<Id, Entity>,
+    uses: U,
+    query: &str,
+    limit: usize,
+) -> Vec<Completion<'i>>
+where
+    U: Iterator<Item = &'i Id>,
+{
+    let query = fuzzy::fold(query);
+
+    let mut counts = HashMap::<&Id, usize>::new();
+
+    for id in uses {
+        *counts.entry(id).or_insert(0) += 1;
+    }
+
+    let mut matches = entities
+        .iter()
+        .filter_map(|(id, entity)| {
+            let folded = fuzzy::fold(&entity.text);
+
+            folded.contains(&query).then(|| {
+                let count = counts.get(id).copied().unwrap_or(0);
+
+                (folded.starts_with(&query), id, entity.text.as_str(), count)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    matches.sort_by(|a, b| {
+        b.3.cmp(&a.3)
+            .then_with(|| b.0.cmp(&a.0))
+            .then_with(|| a.2.cmp(b.2))
+    });
+    matches.truncate(limit);
+
+    matches
+        .into_iter()
+        .map(|(_, id, text, count)| Completion { id, text, count })
+        .collect()
+}
+
+/// Counts how many of the stories use each entity of a kind, most used first.
+pub fn entity_counts<'i, S>(
+    index: &'i Index,
+    stories: S,
+    kind: EntityKind,
+) -> Vec<(&'i Id, &'i Entity, usize)>
+where
+    S: Iterator<Item = &'i Story>,
+{
+    let entities = index.entities(kind);
+
+    let mut counts = HashMap::<&Id, usize>::new();
+
+    for story in stories {
+        for id in story.meta.entities(kind) {
+            *counts.entry(id).or_insert(0) += 1;
+        }
+    }
+
+    let mut counts = counts
+        .into_iter()
+        .filter_map(|(id, count)| {
+            entities
+                .get_key_value(id)
+                .map(|(id, entity)| (id, entity, count))
+        })
+        .collect::<Vec<_>>();
+
+    counts.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.1.text.cmp(&b.1.text)));
+
+    counts
 }
 
 pub fn search(database: &Database, text: &str) -> Vec<Id> {
@@ -412,14 +950,32 @@ where
 }
 
 fn any_by_text(full: &HashMap<Id, Entity>, refs: &[Id], text: &str) -> bool {
+    let text = fuzzy::fold(text);
+
     refs.iter().map(|id| full.get(id)).any(|a| match a {
-        Some(entity) => entity.text.to_lowercase() == text.to_lowercase(),
+        Some(entity) => fuzzy::fold(&entity.text) == text,
         None => false,
     })
 }
 
-#[allow(clippy::while_let_on_iterator)]
 fn parse(text: &str) -> Vec<Bound> {
+    parse_spanned(text)
+        .into_iter()
+        .map(|(bound, _)| bound)
+        .collect()
+}
+
+/// Where a [`Bound`] was written in the search text.
+struct Span {
+    /// the bytes of `text` the bound's text, or the whole group, came from
+    range: Range<usize>,
+    /// the symbols of the group the bound was written as, if it was one
+    group: Option<[&'static str; 3]>,
+}
+
+/// Parses the search like [`parse`], keeping where in `text` each bound came from.
+#[allow(clippy::while_let_on_iterator)]
+fn parse_spanned(text: &str) -> Vec<(Bound, Span)> {
     let cleaned = text.trim();
     let mut parts = cleaned.split(',').map(str::trim);
 
@@ -434,6 +990,7 @@ fn parse(text: &str) -> Vec<Bound> {
 
         if parse_group(
             ["[", "]", "/"],
+            text,
             &mut bounds,
             &mut parts,
             included,
@@ -444,6 +1001,7 @@ fn parse(text: &str) -> Vec<Bound> {
 
         if parse_group(
             ["(", ")", " & "],
+            text,
             &mut bounds,
             &mut parts,
             included,
@@ -455,6 +1013,7 @@ fn parse(text: &str) -> Vec<Bound> {
         if parse_prefixed(
             ["a:", "author:"],
             Bound::author,
+            text,
             &mut bounds,
             included,
             &mut part,
@@ -465,6 +1024,7 @@ fn parse(text: &str) -> Vec<Bound> {
         if parse_prefixed(
             ["o:", "origin:"],
             Bound::origin,
+            text,
             &mut bounds,
             included,
             &mut part,
@@ -475,6 +1035,7 @@ fn parse(text: &str) -> Vec<Bound> {
         if parse_prefixed(
             ["c:", "character:"],
             Bound::character,
+            text,
             &mut bounds,
             included,
             &mut part,
@@ -485,6 +1046,7 @@ fn parse(text: &str) -> Vec<Bound> {
         if parse_prefixed(
             ["g:", "general:"],
             Bound::general,
+            text,
             &mut bounds,
             included,
             &mut part,
@@ -492,16 +1054,30 @@ fn parse(text: &str) -> Vec<Bound> {
             continue;
         }
 
-        bounds.push(Bound::general(included, part.to_owned()));
+        bounds.push((
+            Bound::general(included, part.to_owned()),
+            Span {
+                range: offset(text, part),
+                group: None,
+            },
+        ));
     }
 
     bounds
 }
 
+/// Returns the bytes `part` takes up in `origin`, which it has to be a slice of.
+fn offset(origin: &str, part: &str) -> Range<usize> {
+    let start = part.as_ptr() as usize - origin.as_ptr() as usize;
+
+    start..(start + part.len())
+}
+
 fn parse_prefixed<B>(
     prefixes: [&str; 2],
     builder: B,
-    bounds: &mut Vec<Bound>,
+    origin: &str,
+    bounds: &mut Vec<(Bound, Span)>,
     included: bool,
     part: &mut &str,
 ) -> bool
@@ -511,12 +1087,15 @@ where
     let [short, long] = prefixes;
 
     if part.starts_with(short) || part.starts_with(long) {
-        let part = part
-            .trim_start_matches(short)
-            .trim_start_matches(long)
-            .to_owned();
+        let part = part.trim_start_matches(short).trim_start_matches(long);
 
-        bounds.push(builder(included, part));
+        bounds.push((
+            builder(included, part.to_owned()),
+            Span {
+                range: offset(origin, part),
+                group: None,
+            },
+        ));
 
         true
     } else {
@@ -525,11 +1104,12 @@ where
 }
 
 fn parse_group<'i, I>(
-    symbols: [&str; 3],
-    bounds: &mut Vec<Bound>,
+    symbols: [&'static str; 3],
+    origin: &str,
+    bounds: &mut Vec<(Bound, Span)>,
     parts: &mut I,
     included: bool,
-    part: &mut &str,
+    part: &mut &'i str,
 ) -> bool
 where
     I: Iterator<Item = &'i str>,
@@ -537,11 +1117,15 @@ where
     let [open, close, sep] = symbols;
 
     if part.starts_with(open) {
+        let mut range = offset(origin, part);
+
         let mut part = part.trim_start_matches(open).to_owned();
 
         part.push_str(sep);
 
         for mut inner in parts {
+            range.end = offset(origin, inner).end;
+
             if inner.ends_with(close) {
                 inner = inner.trim_end_matches(close);
 
@@ -554,7 +1138,13 @@ where
             part.push_str(sep);
         }
 
-        bounds.push(Bound::pairing(included, part));
+        bounds.push((
+            Bound::pairing(included, part),
+            Span {
+                range,
+                group: Some(symbols),
+            },
+        ));
 
         true
     } else {
@@ -671,6 +1261,379 @@ mod test {
         };
     }
 
+    #[test]
+    fn test_any_by_text_folded() {
+        let full = HashMap::from([
+            (
+                Id::from("a".to_owned()),
+                Entity {
+                    text: "Pokémon".to_owned(),
+                },
+            ),
+            (
+                Id::from("b".to_owned()),
+                Entity {
+                    text: "Digimon".to_owned(),
+                },
+            ),
+        ]);
+
+        assert!(any_by_text(&full, &[Id::from("a".to_owned())], "pokemon"));
+        assert!(any_by_text(&full, &[Id::from("a".to_owned())], "POKÉMON"));
+        assert!(!any_by_text(&full, &[Id::from("b".to_owned())], "pokemon"));
+    }
+
+    fn suggestion(search: &str, index: usize) -> Suggestion {
+        let (bound, span) = parse_spanned(search).swap_remove(index);
+
+        Suggestion {
+            term: bound.text().to_owned(),
+            candidates: Vec::new(),
+            span,
+        }
+    }
+
+    #[test]
+    fn test_suggestion_replaces_token() {
+        let search = "category:cat, cat";
+
+        assert_eq!(
+            "category:cat, Cats",
+            suggestion(search, 1).replace(search, "Cats")
+        );
+        assert_eq!(
+            "o:Pokemon, -Digimon",
+            suggestion("o:Pokmon, -Digimon", 0).replace("o:Pokmon, -Digimon", "Pokemon")
+        );
+        assert_eq!(
+            "tag, -Digimon",
+            suggestion("tag, -Digmon", 1).replace("tag, -Digmon", "Digimon")
+        );
+    }
+
+    #[test]
+    fn test_suggestion_replaces_group() {
+        let search = "tag, -[Hary, Draco], other";
+
+        assert_eq!(
+            "tag, -[Harry, Draco], other",
+            suggestion(search, 1).replace(search, "Harry/Draco")
+        );
+    }
+
+    #[test]
+    fn test_rank_by_usage() {
+        let ids = ["a", "b", "c", "d", "e"].map(|id| Id::from(id.to_owned()));
+        let texts = [
+            "Harry Potter",
+            "Hermione Granger",
+            "Ron Weasley",
+            "Hagrid",
+            "Ahab",
+        ];
+
+        let entities = ids
+            .iter()
+            .zip(texts)
+            .map(|(id, text)| {
+                let entity = Entity {
+                    text: text.to_owned(),
+                };
+
+                (id.clone(), entity)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let uses = [&ids[0], &ids[1], &ids[1], &ids[2], &ids[2], &ids[2]];
+
+        let ranked = |query: &str| {
+            rank(&entities, uses.into_iter(), query, 10)
+                .into_iter()
+                .map(|completion| (completion.text, completion.count))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec![
+                ("Hermione Granger", 2),
+                ("Harry Potter", 1),
+                ("Hagrid", 0),
+                ("Ahab", 0)
+            ],
+            ranked("H")
+        );
+        assert_eq!(vec![("Ron Weasley", 3)], ranked("wéas"));
+    }
+
+    fn story(rating: Rating, warnings: &[&str], origins: &[&str]) -> Story {
+        let ids = |list: &[&str]| {
+            list.iter()
+                .map(|id| Id::from((*id).to_owned()))
+                .collect::<Vec<_>>()
+        };
+
+        Story {
+            info: common::models::StoryInfo {
+                file_name: String::new(),
+                file_hash: 0,
+                kind: common::models::FileKind::Html,
+                title: String::new(),
+                summary: String::new(),
+                start_notes: None,
+                end_notes: None,
+                created: String::new(),
+                updated: String::new(),
+                source: None,
+                posted: None,
+            },
+            meta: StoryMeta {
+                rating,
+                authors: vec![],
+                categories: vec![],
+                origins: ids(origins),
+                warnings: ids(warnings),
+                pairings: vec![],
+                characters: vec![],
+                generals: vec![],
+            },
+            site: common::models::Site::Unknown,
+            chapters: vec![],
+        }
+    }
+
+    fn library() -> Library {
+        Library {
+            progress: HashMap::new(),
+            bookmarks: HashMap::new(),
+            shelves: vec![],
+        }
+    }
+
+    #[test]
+    fn test_filter_include_exclude() {
+        let ids = ["1", "2", "3"].map(|id| Id::from(id.to_owned()));
+        let stories = [
+            story(Rating::General, &[], &["hp"]),
+            story(Rating::Explicit, &["violence"], &["hp"]),
+            story(Rating::Teen, &[], &["pokemon"]),
+        ];
+
+        let library = Library {
+            bookmarks: HashMap::from([
+                (
+                    ids[0].clone(),
+                    Bookmark {
+                        favourite: true,
+                        status: Some(Status::Reading),
+                        notes: String::new(),
+                        rating: 4,
+                        tags: vec!["comfort".to_owned()],
+                        following: false,
+                        updated: false,
+                    },
+                ),
+                (
+                    ids[1].clone(),
+                    Bookmark {
+                        favourite: false,
+                        status: None,
+                        notes: String::new(),
+                        rating: 2,
+                        tags: vec!["comfort".to_owned(), "angst".to_owned()],
+                        following: false,
+                        updated: false,
+                    },
+                ),
+            ]),
+            shelves: vec![common::models::Shelf {
+                name: "later".to_owned(),
+                stories: vec![ids[2].clone()],
+            }],
+            ..library()
+        };
+
+        let filtered = |query: &str| {
+            let mut list = ids.iter().zip(stories.iter()).collect::<Vec<_>>();
+
+            filter(&parse_query(query)[..], &library, &mut list);
+
+            list.into_iter()
+                .map(|(id, _)| id.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec!["1", "2"], filtered("io=hp"));
+        assert_eq!(vec!["1"], filtered("io=hp&ew=violence"));
+        assert_eq!(vec!["3"], filtered("eo=hp"));
+        assert_eq!(vec!["1", "3"], filtered("er=explicit"));
+        assert_eq!(vec!["2", "3"], filtered("ir=e&ir=teen"));
+        assert_eq!(vec!["1"], filtered("is=favourites&is=reading"));
+        assert_eq!(vec!["1", "2"], filtered("es=later"));
+        assert_eq!(Vec::<&str>::new(), filtered("is=missing"));
+        assert_eq!(vec!["1", "2"], filtered("iu=4&iu=2"));
+        assert_eq!(vec!["3"], filtered("eu=4&eu=2"));
+        assert_eq!(vec!["2"], filtered("it=comfort&it=angst"));
+        assert_eq!(vec!["1", "3"], filtered("et=angst"));
+    }
+
+    #[test]
+    fn test_default_filter_only_excludes() {
+        let settings = Settings {
+            theme: common::models::Theme::Dark,
+            sync_key: String::new(),
+            data_path: String::new(),
+            temp_path: String::new(),
+            nodes: vec![],
+            saved_searches: vec![common::models::SavedSearch {
+                name: "daily".to_owned(),
+                query: "io=pokemon&ew=violence".to_owned(),
+            }],
+            default_filter: Some("daily".to_owned()),
+        };
+
+        let id = Id::from("1".to_owned());
+
+        let filter = DefaultFilter::new(&settings, &library());
+
+        assert!(filter.allows(&id, &story(Rating::General, &[], &["hp"])));
+        assert!(!filter.allows(&id, &story(Rating::General, &["violence"], &["pokemon"])));
+
+        let filter = DefaultFilter::new(
+            &Settings {
+                default_filter: None,
+                ..settings
+            },
+            &library(),
+        );
+
+        assert!(filter.allows(&id, &story(Rating::General, &["violence"], &["hp"])));
+    }
+
+    #[test]
+    fn test_stats_fill() {
+        let entities = |ids: &[&str]| {
+            ids.iter()
+                .map(|id| {
+                    let entity = Entity {
+                        text: id.to_uppercase(),
+                    };
+
+                    (Id::from((*id).to_owned()), entity)
+                })
+                .collect::<HashMap<_, _>>()
+        };
+
+        let origins = (0..12).map(|i| vfmt::format!("o{}", i)).collect::<Vec<_>>();
+        let origins = origins.iter().map(String::as_str).collect::<Vec<_>>();
+
+        let index = Index {
+            stories: HashMap::new(),
+            categories: HashMap::new(),
+            authors: HashMap::new(),
+            origins: entities(&origins),
+            warnings: entities(&["violence"]),
+            pairings: HashMap::new(),
+            characters: HashMap::new(),
+            generals: HashMap::new(),
+        };
+
+        let ids = origins
+            .iter()
+            .map(|id| Id::from((*id).to_owned()))
+            .collect::<Vec<_>>();
+        let stories = origins
+            .iter()
+            .map(|origin| match *origin {
+                // an origin that's gone from the index is left out rather than failing the rest
+                "o0" => story(Rating::Teen, &[], &[origin, "gone"]),
+                _ => story(Rating::Teen, &[], &[origin, "o0"]),
+            })
+            .collect::<Vec<_>>();
+
+        let query = parse_query("ew=violence&er=explicit");
+
+        let mut list = ids.iter().zip(stories.iter()).collect::<Vec<_>>();
+
+        let stats = search_v2(&query[..], &library(), &mut list).fill(&index, &query[..]);
+
+        let facet = |kind| {
+            stats
+                .facets
+                .iter()
+                .find(|facet| facet.kind == kind)
+                .expect("every kind has a facet")
+        };
+
+        let ratings = facet(StatKind::Rating);
+        assert_eq!(2, ratings.entries.len());
+        assert_eq!(("teen", 12, false), {
+            let entry = &ratings.entries[0];
+            (entry.value.as_str(), entry.count, entry.excluded)
+        });
+        assert_eq!(("explicit", 0, true), {
+            let entry = &ratings.entries[1];
+            (entry.value.as_str(), entry.count, entry.excluded)
+        });
+
+        let origins = facet(StatKind::Origin);
+        assert_eq!(12, origins.entries.len());
+        assert_eq!(
+            ("O0", 12),
+            (origins.entries[0].text.as_str(), origins.entries[0].count)
+        );
+
+        let warnings = facet(StatKind::Warning);
+        assert_eq!(1, warnings.entries.len());
+        assert!(warnings.entries[0].excluded);
+        assert_eq!(0, warnings.entries[0].count);
+    }
+
+    #[test]
+    fn test_entity_counts() {
+        let index = Index {
+            stories: HashMap::new(),
+            categories: HashMap::new(),
+            authors: HashMap::new(),
+            origins: HashMap::from([
+                (
+                    Id::from("hp".to_owned()),
+                    Entity {
+                        text: "Harry Potter".to_owned(),
+                    },
+                ),
+                (
+                    Id::from("pkmn".to_owned()),
+                    Entity {
+                        text: "Pokémon".to_owned(),
+                    },
+                ),
+                (
+                    Id::from("dm".to_owned()),
+                    Entity {
+                        text: "Digimon".to_owned(),
+                    },
+                ),
+            ]),
+            warnings: HashMap::new(),
+            pairings: HashMap::new(),
+            characters: HashMap::new(),
+            generals: HashMap::new(),
+        };
+
+        let stories = [
+            story(Rating::General, &[], &["pkmn"]),
+            story(Rating::General, &[], &["hp", "pkmn"]),
+            story(Rating::General, &[], &["dm"]),
+        ];
+
+        let counts = entity_counts(&index, stories.iter(), EntityKind::Origin)
+            .into_iter()
+            .map(|(id, _, count)| (id.as_str(), count))
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec![("pkmn", 2), ("dm", 1), ("hp", 1)], counts);
+    }
+
     test!(author, Bound::author, ["author", "a"]);
 
     test!(origin, Bound::origin, ["origin", "o"]);
@@ -0,0 +1,377 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash as _, Hasher as _},
+    sync::{Arc, RwLock},
+};
+
+use common::{
+    models::{EntityKind, Id, Index, Library, Progress, Rating, Status, Story},
+    prelude::*,
+};
+use once_cell::sync::Lazy;
+
+use crate::search;
+
+/// How many entities are shown in each of the top lists.
+const TOP: usize = 10;
+
+/// Numbers about the whole index, these only change when the library is reindexed.
+pub struct IndexStatistics {
+    pub works: usize,
+    pub chapters: usize,
+    pub words: usize,
+    pub authors: usize,
+    pub fandoms: usize,
+    pub top_fandoms: Vec<(Id, String, usize)>,
+    pub top_characters: Vec<(Id, String, usize)>,
+    pub top_pairings: Vec<(Id, String, usize)>,
+    pub ratings: Vec<(Rating, usize)>,
+    pub warnings: Vec<(Id, String, usize)>,
+    /// The number of works added each month, as `YYYY-MM`, oldest first.
+    pub months: Vec<(String, usize)>,
+}
+
+impl IndexStatistics {
+    pub fn new(index: &Index) -> Self {
+        let counts = |kind| {
+            search::entity_counts(index, index.stories.values(), kind)
+                .into_iter()
+                .map(|(id, entity, count)| (id.clone(), entity.text.clone(), count))
+                .collect::<Vec<_>>()
+        };
+
+        let top = |kind| {
+            let mut counts = counts(kind);
+
+            counts.truncate(TOP);
+
+            counts
+        };
+
+        let mut ratings = BTreeMap::<Rating, usize>::new();
+        let mut months = BTreeMap::<&str, usize>::new();
+
+        for story in index.stories.values() {
+            *ratings.entry(story.meta.rating).or_insert(0) += 1;
+
+            // `created` is RFC 3339, so the month is always the first 7 characters
+            if let Some(month) = story.info.created.get(..7) {
+                *months.entry(month).or_insert(0) += 1;
+            }
+        }
+
+        Self {
+            works: index.stories.len(),
+            chapters: index
+                .stories
+                .values()
+                .map(|story| story.chapters.len())
+                .sum(),
+            words: index.stories.values().map(words).sum(),
+            authors: counts(EntityKind::Author).len(),
+            fandoms: counts(EntityKind::Origin).len(),
+            top_fandoms: top(EntityKind::Origin),
+            top_characters: top(EntityKind::Character),
+            top_pairings: top(EntityKind::Pairing),
+            ratings: ratings.into_iter().collect(),
+            warnings: counts(EntityKind::Warning),
+            months: months
+                .into_iter()
+                .map(|(month, count)| (month.to_owned(), count))
+                .collect(),
+        }
+    }
+}
+
+/// Numbers about the reader's own progress, these change as they read so they aren't cached.
+pub struct ReadingStatistics {
+    /// Works that have been opened but not marked as finished.
+    pub in_progress: usize,
+    pub statuses: Vec<(Status, usize)>,
+    /// An estimate of the words read, from finished works and how far into the others the reader is.
+    pub words_read: usize,
+}
+
+impl ReadingStatistics {
+    pub fn new(index: &Index, library: &Library) -> Self {
+        let status = |id: &Id| {
+            library
+                .bookmarks
+                .get(id)
+                .and_then(|bookmark| bookmark.status)
+        };
+
+        let statuses = Status::ALL
+            .into_iter()
+            .map(|wanted| {
+                let count = index
+                    .stories
+                    .keys()
+                    .filter(|id| status(id) == Some(wanted))
+                    .count();
+
+                (wanted, count)
+            })
+            .collect();
+
+        let mut in_progress = 0;
+        let mut words_read = 0;
+
+        for (id, story) in &index.stories {
+            if status(id) == Some(Status::Finished) {
+                words_read += words(story);
+            } else if let Some(progress) = library.progress.get(id) {
+                in_progress += 1;
+                words_read += words_before(story, progress);
+            }
+        }
+
+        Self {
+            in_progress,
+            statuses,
+            words_read,
+        }
+    }
+}
+
+fn words(story: &Story) -> usize {
+    story.chapters.iter().map(|chapter| chapter.words).sum()
+}
+
+/// The words in the chapters before the saved one, plus however far into it the reader is.
+fn words_before(story: &Story, progress: &Progress) -> usize {
+    let current = progress.chapter.saturating_sub(1);
+
+    let before = story
+        .chapters
+        .iter()
+        .take(current)
+        .map(|chapter| chapter.words)
+        .sum::<usize>();
+
+    let partial = story
+        .chapters
+        .get(current)
+        .map(|chapter| chapter.words * progress.position / Progress::POSITION_MAX)
+        .unwrap_or(0);
+
+    before + partial
+}
+
+/// The last statistics built, along with the [`fingerprint`] of the index they were built from.
+type Cached = Option<(u64, Arc<IndexStatistics>)>;
+
+static CACHE: Lazy<RwLock<Cached>> = Lazy::new(|| RwLock::new(None));
+
+/// Gets the index statistics, only recounting them when the index has changed since they were last built.
+pub fn cached(index: &Index) -> Result<Arc<IndexStatistics>> {
+    let fingerprint = fingerprint(index);
+
+    if let Some((cached, statistics)) = &*CACHE
+        .read()
+        .map_err(|err| anyhow!("unable to get lock on cache: {}", err))?
+    {
+        if *cached == fingerprint {
+            return Ok(statistics.clone());
+        }
+    }
+
+    let statistics = Arc::new(IndexStatistics::new(index));
+
+    *CACHE
+        .write()
+        .map_err(|err| anyhow!("unable to get lock on cache: {}", err))? =
+        Some((fingerprint, statistics.clone()));
+
+    Ok(statistics)
+}
+
+/// Changes whenever a reindex adds, removes, renames or updates a story, as every story's ID, file name
+/// and file hash are part of it.
+fn fingerprint(index: &Index) -> u64 {
+    let mut stories = index.stories.iter().collect::<Vec<_>>();
+
+    // the map's order isn't the same from one index to the next
+    stories.sort_unstable_by_key(|(id, _)| *id);
+
+    let mut hasher = DefaultHasher::new();
+
+    for (id, story) in stories {
+        id.hash(&mut hasher);
+        story.info.file_name.hash(&mut hasher);
+        story.info.file_hash.hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use common::models::{Bookmark, Chapter, FileKind, Site, StoryInfo, StoryMeta};
+
+    use super::*;
+
+    fn story(created: &str, file_hash: u64, chapters: &[usize]) -> Story {
+        Story {
+            info: StoryInfo {
+                file_name: String::new(),
+                file_hash,
+                kind: FileKind::Html,
+                title: String::new(),
+                summary: String::new(),
+                start_notes: None,
+                end_notes: None,
+                created: created.to_owned(),
+                updated: String::new(),
+                source: None,
+                posted: None,
+            },
+            meta: StoryMeta {
+                rating: Rating::General,
+                authors: vec![],
+                categories: vec![],
+                origins: vec![],
+                warnings: vec![],
+                pairings: vec![],
+                characters: vec![],
+                generals: vec![],
+            },
+            site: Site::Unknown,
+            chapters: chapters
+                .iter()
+                .map(|words| Chapter {
+                    title: String::new(),
+                    content: 0..0,
+                    summary: None,
+                    start_notes: None,
+                    end_notes: None,
+                    words: *words,
+                })
+                .collect(),
+        }
+    }
+
+    fn index(stories: Vec<(&str, Story)>) -> Index {
+        Index {
+            stories: stories
+                .into_iter()
+                .map(|(id, story)| (Id::from(id.to_owned()), story))
+                .collect(),
+            categories: HashMap::new(),
+            authors: HashMap::new(),
+            origins: HashMap::new(),
+            warnings: HashMap::new(),
+            pairings: HashMap::new(),
+            characters: HashMap::new(),
+            generals: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_index_statistics() {
+        let index = index(vec![
+            ("1", story("2022-03-14T10:00:00Z", 1, &[100, 200])),
+            ("2", story("2022-01-02T10:00:00Z", 2, &[50])),
+            ("3", story("2022-03-01T10:00:00Z", 3, &[10])),
+        ]);
+
+        let statistics = IndexStatistics::new(&index);
+
+        assert_eq!(3, statistics.works);
+        assert_eq!(4, statistics.chapters);
+        assert_eq!(360, statistics.words);
+        assert_eq!(vec![(Rating::General, 3)], statistics.ratings);
+        assert_eq!(
+            vec![("2022-01".to_owned(), 1), ("2022-03".to_owned(), 2)],
+            statistics.months
+        );
+    }
+
+    #[test]
+    fn test_reading_statistics() {
+        let index = index(vec![
+            ("1", story("", 1, &[100, 200])),
+            ("2", story("", 2, &[50])),
+            ("3", story("", 3, &[10])),
+        ]);
+
+        let library = Library {
+            progress: HashMap::from([
+                (
+                    Id::from("1".to_owned()),
+                    Progress {
+                        chapter: 2,
+                        position: 500,
+                        updated: String::new(),
+                    },
+                ),
+                (
+                    Id::from("2".to_owned()),
+                    Progress {
+                        chapter: 1,
+                        position: 200,
+                        updated: String::new(),
+                    },
+                ),
+            ]),
+            bookmarks: HashMap::from([(
+                Id::from("2".to_owned()),
+                Bookmark {
+                    favourite: false,
+                    status: Some(Status::Finished),
+                    notes: String::new(),
+                    rating: 0,
+                    tags: vec![],
+                    following: false,
+                    updated: false,
+                },
+            )]),
+            shelves: vec![],
+        };
+
+        let statistics = ReadingStatistics::new(&index, &library);
+
+        assert_eq!(1, statistics.in_progress);
+        // all of the first chapter and half of the second, plus the whole finished story
+        assert_eq!(200 + 50, statistics.words_read);
+        assert_eq!(
+            Some(&(Status::Finished, 1)),
+            statistics
+                .statuses
+                .iter()
+                .find(|(status, _)| *status == Status::Finished)
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_changes() {
+        let before = index(vec![("1", story("", 1, &[])), ("2", story("", 2, &[]))]);
+        let updated = index(vec![("1", story("", 1, &[])), ("2", story("", 5, &[]))]);
+        let removed = index(vec![("1", story("", 1, &[]))]);
+        // the hashes still add up to the same
+        let cancelled = index(vec![("1", story("", 0, &[])), ("2", story("", 3, &[]))]);
+        let swapped = index(vec![("1", story("", 1, &[])), ("3", story("", 2, &[]))]);
+
+        let mut renamed = index(vec![("1", story("", 1, &[])), ("2", story("", 2, &[]))]);
+
+        for story in renamed.stories.values_mut() {
+            story.info.file_name = "renamed.html".to_owned();
+        }
+
+        assert_eq!(
+            fingerprint(&before),
+            fingerprint(&index(vec![
+                ("2", story("", 2, &[])),
+                ("1", story("", 1, &[]))
+            ]))
+        );
+
+        assert_ne!(fingerprint(&before), fingerprint(&updated));
+        assert_ne!(fingerprint(&before), fingerprint(&removed));
+        assert_ne!(fingerprint(&before), fingerprint(&cancelled));
+        assert_ne!(fingerprint(&before), fingerprint(&swapped));
+        assert_ne!(fingerprint(&before), fingerprint(&renamed));
+    }
+}
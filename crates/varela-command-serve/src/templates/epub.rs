@@ -0,0 +1,50 @@
+//! The files that make up a generated EPUB, everything in them has to be well formed XML.
+
+/// A chapter as it appears in the package manifest and table of contents.
+pub struct ChapterLink {
+    /// The manifest id, the file is this with `.xhtml` on the end.
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(opal::Template)]
+#[template(path = "epub/package.hbs")]
+pub struct Package {
+    pub id: String,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub subjects: Vec<String>,
+    /// The story's summary, as HTML.
+    pub summary: String,
+    /// When the story was last updated, as `YYYY-MM-DDThh:mm:ssZ`.
+    pub modified: String,
+    pub chapters: Vec<ChapterLink>,
+}
+
+#[derive(opal::Template)]
+#[template(path = "epub/nav.hbs")]
+pub struct Nav {
+    pub title: String,
+    pub chapters: Vec<ChapterLink>,
+}
+
+#[derive(opal::Template)]
+#[template(path = "epub/title.hbs")]
+pub struct TitlePage {
+    pub title: String,
+    pub authors: Vec<String>,
+    /// The story's summary, as XHTML.
+    pub summary: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(opal::Template)]
+#[template(path = "epub/chapter.hbs")]
+pub struct ChapterPage {
+    pub title: String,
+    /// The rest of these are XHTML, already cleaned up from the story's HTML.
+    pub summary: Option<String>,
+    pub start_notes: Vec<(&'static str, String)>,
+    pub body: String,
+    pub end_notes: Vec<(&'static str, String)>,
+}
@@ -0,0 +1,23 @@
+//! Stories as a single file, for downloading and reading elsewhere.
+
+pub struct ExportChapter {
+    pub id: String,
+    pub title: String,
+    /// The rest of these are HTML, already cleaned up so one chapter can't break the next.
+    pub summary: Option<String>,
+    pub start_notes: Vec<(&'static str, String)>,
+    pub body: String,
+    pub end_notes: Vec<(&'static str, String)>,
+}
+
+#[derive(opal::Template)]
+#[template(path = "export/story.hbs")]
+pub struct StoryPage {
+    pub title: String,
+    pub authors: Vec<String>,
+    /// The story's details as a label and text, like its rating and fandoms.
+    pub details: Vec<(&'static str, String)>,
+    /// The story's summary, as HTML.
+    pub summary: String,
+    pub chapters: Vec<ExportChapter>,
+}
@@ -1,3 +1,5 @@
+pub mod epub;
+pub mod export;
 pub mod pages;
 pub mod partials;
 
@@ -0,0 +1,18 @@
+use crate::templates::partials::Link;
+
+#[derive(opal::Template)]
+#[template(path = "pages/browse.hbs")]
+pub struct Browse {
+    /// the browse page being viewed, used for the filter form
+    pub path: &'static str,
+    pub kinds: Vec<Link>,
+    pub filter: String,
+    /// every jump link letter, along with if any entity starts with it
+    pub letters: Vec<(String, bool)>,
+    pub groups: Vec<BrowseGroup>,
+}
+
+pub struct BrowseGroup {
+    pub letter: String,
+    pub entities: Vec<(Link, usize)>,
+}
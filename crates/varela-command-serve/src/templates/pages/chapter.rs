@@ -1,18 +1,24 @@
-use crate::templates::partials::{Pagination, StoryPartial};
+use crate::templates::partials::{
+    ChapterBody, Contrast, Link, Pagination, ReaderPanel, StoryPartial,
+};
 
 #[derive(opal::Template)]
 #[template(path = "pages/chapter.hbs")]
 pub struct Chapter {
     pub card: StoryPartial,
-    pub chapter: String,
+    pub body: ChapterBody,
     pub index: usize,
     pub pagination: Pagination,
+    pub reader: ReaderPanel,
+    /// Where to scroll back to, if the reader left off in this chapter.
+    pub resume: Option<usize>,
 }
 
 impl Chapter {
-    pub fn new(card: StoryPartial, chapter: String, index: usize) -> Self {
+    pub fn new(card: StoryPartial, body: ChapterBody, reader: ReaderPanel, index: usize) -> Self {
         Self {
-            chapter,
+            reader,
+            body,
             index,
             pagination: Pagination::new(
                 format!("/story/{}", card.id),
@@ -20,6 +26,7 @@ impl Chapter {
                 card.len as u32,
             ),
             card,
+            resume: None,
         }
     }
 }
@@ -0,0 +1,23 @@
+use crate::templates::partials::{Contrast, Link, StoryPartial};
+
+/// A story's table of contents, listing each chapter with its summary.
+#[derive(opal::Template)]
+#[template(path = "pages/contents.hbs")]
+pub struct Contents {
+    pub card: StoryPartial,
+    pub chapters: Vec<ContentsEntry>,
+}
+
+impl Contents {
+    pub fn new(card: StoryPartial, chapters: Vec<ContentsEntry>) -> Self {
+        Self { card, chapters }
+    }
+}
+
+pub struct ContentsEntry {
+    pub number: usize,
+    pub title: String,
+    pub summary: Option<String>,
+    /// If this is the chapter the reader left off in.
+    pub current: bool,
+}
@@ -1,9 +1,47 @@
-#[derive(opal::Template)]
+#[derive(Default, opal::Template)]
 #[template(path = "pages/download.hbs")]
-pub struct Download {}
+pub struct Download {
+    /// Why the link couldn't be queued, along with the link so it can be fixed.
+    pub error: Option<(String, String)>,
+}
 
 impl Download {
     pub fn new() -> Self {
-        Self {}
+        Self::default()
+    }
+
+    pub fn failed(url: String, error: String) -> Self {
+        Self {
+            error: Some((url, error)),
+        }
+    }
+}
+
+#[derive(opal::Template)]
+#[template(path = "pages/downloads.hbs")]
+pub struct Downloads {
+    pub downloads: Vec<DownloadEntry>,
+}
+
+impl Downloads {
+    pub fn new(downloads: Vec<DownloadEntry>) -> Self {
+        Self { downloads }
     }
 }
+
+pub struct DownloadEntry {
+    pub url: String,
+    pub work: String,
+    pub status: &'static str,
+    pub error: Option<String>,
+    pub attempts: usize,
+    pub updated: String,
+    /// When a failed attempt will be tried again.
+    pub retry_at: Option<String>,
+    /// The link to and title of the story, once it has been indexed.
+    pub story: Option<(String, String)>,
+    /// Only downloads that were given up on can be retried.
+    pub retry: bool,
+    /// Downloads can be removed from the queue, unless they're being downloaded.
+    pub remove: bool,
+}
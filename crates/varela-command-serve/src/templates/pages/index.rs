@@ -1,13 +1,34 @@
-use crate::templates::partials::StoryPartial;
+use crate::templates::partials::{ContinueReading, DidYouMean, Related, StoryPartial};
 
 #[derive(opal::Template)]
 #[template(path = "pages/index.hbs")]
 pub struct Index {
     pub stories: Vec<StoryPartial>,
+    pub did_you_mean: Option<DidYouMean>,
+    pub related: Option<Related>,
+    pub continue_reading: Option<ContinueReading>,
+    /// Where to download every story on the page as a zip, for shelves.
+    pub download: Option<String>,
 }
 
 impl Index {
     pub fn new(stories: Vec<StoryPartial>) -> Self {
-        Self { stories }
+        Self {
+            stories,
+            did_you_mean: None,
+            related: None,
+            continue_reading: None,
+            download: None,
+        }
+    }
+
+    pub fn with_suggestions(stories: Vec<StoryPartial>, did_you_mean: DidYouMean) -> Self {
+        Self {
+            stories,
+            did_you_mean: Some(did_you_mean),
+            related: None,
+            continue_reading: None,
+            download: None,
+        }
     }
 }
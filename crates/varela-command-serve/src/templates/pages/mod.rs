@@ -1,11 +1,26 @@
+pub mod browse;
 pub mod chapter;
+pub mod contents;
 pub mod download;
 pub mod index;
 pub mod opds;
+pub mod saved;
 pub mod search;
+pub mod shelves;
+pub mod statistics;
+pub mod work;
 
 pub use crate::templates::pages::{
-    chapter::Chapter, download::Download, index::Index, search::Search,
+    browse::Browse,
+    chapter::Chapter,
+    contents::Contents,
+    download::{Download, DownloadEntry, Downloads},
+    index::Index,
+    saved::Saved,
+    search::Search,
+    shelves::Shelves,
+    statistics::Statistics,
+    work::Work,
 };
 
 #[derive(opal::Template)]
@@ -52,12 +67,25 @@ impl Error {
 
 impl enrgy::response::IntoResponse for Error {
     fn into_response(self) -> enrgy::http::HttpResponse {
-        crate::handlers::Template(self).into_response()
+        let status = match &self {
+            Error::Error(_) => 500,
+            Error::Http(code, _) => *code,
+        };
+
+        let mut res = crate::handlers::Template(self).into_response();
+
+        *res.status_mut() = enrgy::http::StatusCode(status);
+
+        res
     }
 }
 
 impl<E: Into<common::prelude::anyhow::Error>> From<E> for Error {
-    fn from(_: E) -> Self {
-        todo!()
+    fn from(err: E) -> Self {
+        let err = err.into();
+
+        common::prelude::error!("{}", format!("{:#}", err));
+
+        Self::Error(err)
     }
 }
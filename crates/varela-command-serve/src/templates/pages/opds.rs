@@ -1,14 +1,332 @@
-use common::models::{Existing, ResolvedStory};
+use crate::json;
+
+/// The media type of every OPDS 2.0 feed, whatever it lists.
+pub const JSON_MIME: &str = "application/opds+json";
+
+/// Whether a feed lists other feeds or stories, catalog readers use it to decide how to show a link.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FeedKind {
+    Navigation,
+    Acquisition,
+}
+
+impl FeedKind {
+    pub const fn mime(self) -> &'static str {
+        match self {
+            FeedKind::Navigation => "application/atom+xml;profile=opds-catalog;kind=navigation",
+            FeedKind::Acquisition => "application/atom+xml;profile=opds-catalog;kind=acquisition",
+        }
+    }
+}
+
+pub struct FeedLink {
+    pub rel: &'static str,
+    pub href: String,
+    pub kind: &'static str,
+    /// If the href is a URI template, only used by the search link of OPDS 2.0 feeds.
+    pub templated: bool,
+}
+
+/// Another way of looking at the same feed, like a different sort order.
+pub struct Facet {
+    pub group: &'static str,
+    pub title: &'static str,
+    pub href: String,
+    pub active: bool,
+}
+
+/// A link to another feed, only used in navigation feeds.
+pub struct NavigationEntry {
+    pub id: String,
+    pub title: String,
+    pub updated: String,
+    pub href: String,
+    pub kind: &'static str,
+    pub content: String,
+}
+
+/// A story and where to get it, only used in acquisition feeds.
+pub struct StoryEntry {
+    pub id: String,
+    pub title: String,
+    pub updated: String,
+    pub published: String,
+    pub authors: Vec<(String, String)>,
+    /// The story's summary, as HTML.
+    pub summary: String,
+    /// The `(term, label)` of each fandom and tag.
+    pub categories: Vec<(String, String)>,
+    pub read_href: String,
+    /// The `(href, type)` of each file the story can be downloaded as.
+    pub acquisitions: Vec<(String, &'static str)>,
+}
+
+pub enum OpdsEntry {
+    Navigation(NavigationEntry),
+    Story(StoryEntry),
+}
+
+/// Tells catalog readers how to search, the search links of the Atom feeds point to this.
+#[derive(opal::Template)]
+#[template(path = "pages/opds-search.hbs")]
+pub struct OpenSearch {
+    /// The scheme and host the reader used, as OpenSearch templates should be absolute.
+    pub base: String,
+}
 
 #[derive(opal::Template)]
 #[template(path = "pages/opds-feed.hbs")]
 pub struct OpdsFeed {
+    pub id: String,
+    pub title: String,
     pub updated: String,
-    pub stories: Vec<Existing<ResolvedStory>>,
+    /// The page being shown, starting at `1`.
+    pub page: usize,
+    pub per_page: usize,
+    /// How many entries there are across every page.
+    pub total: usize,
+    pub links: Vec<FeedLink>,
+    pub facets: Vec<Facet>,
+    pub entries: Vec<OpdsEntry>,
 }
 
 impl OpdsFeed {
-    pub fn new(updated: String, stories: Vec<Existing<ResolvedStory>>) -> Self {
-        Self { updated, stories }
+    /// Writes the feed as an OPDS 2.0 feed, the Atom version is the template.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{");
+
+        json::key(&mut out, "metadata");
+        out.push('{');
+        json::key(&mut out, "identifier");
+        json::string(&mut out, &self.id);
+        out.push(',');
+        json::key(&mut out, "title");
+        json::string(&mut out, &self.title);
+        out.push(',');
+        json::key(&mut out, "modified");
+        json::string(&mut out, &self.updated);
+        out.push(',');
+        json::key(&mut out, "numberOfItems");
+        json::number(&mut out, self.total);
+        out.push(',');
+        json::key(&mut out, "itemsPerPage");
+        json::number(&mut out, self.per_page);
+        out.push(',');
+        json::key(&mut out, "currentPage");
+        json::number(&mut out, self.page);
+        out.push_str("},");
+
+        json::key(&mut out, "links");
+        list(&mut out, &self.links, |out, link| {
+            json_link(
+                out,
+                Some(link.rel),
+                &link.href,
+                link.kind,
+                None,
+                link.templated,
+            )
+        });
+
+        if !self.facets.is_empty() {
+            out.push(',');
+            json::key(&mut out, "facets");
+
+            let mut groups = Vec::<(&str, Vec<&Facet>)>::new();
+
+            for facet in &self.facets {
+                match groups.iter_mut().find(|(group, _)| *group == facet.group) {
+                    Some((_, facets)) => facets.push(facet),
+                    None => groups.push((facet.group, vec![facet])),
+                }
+            }
+
+            list(&mut out, &groups, |out, (group, facets)| {
+                out.push('{');
+                json::key(out, "metadata");
+                out.push('{');
+                json::key(out, "title");
+                json::string(out, group);
+                out.push_str("},");
+                json::key(out, "links");
+                list(out, facets, |out, facet| {
+                    json_link(
+                        out,
+                        facet.active.then_some("self"),
+                        &facet.href,
+                        JSON_MIME,
+                        Some(facet.title),
+                        false,
+                    )
+                });
+                out.push('}');
+            });
+        }
+
+        let navigation = self
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                OpdsEntry::Navigation(entry) => Some(entry),
+                OpdsEntry::Story(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        let publications = self
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                OpdsEntry::Story(entry) => Some(entry),
+                OpdsEntry::Navigation(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        // every feed needs one of the collections, even if it is empty
+        if !navigation.is_empty() || publications.is_empty() {
+            out.push(',');
+            json::key(&mut out, "navigation");
+            list(&mut out, &navigation, |out, entry| {
+                json_link(
+                    out,
+                    Some("subsection"),
+                    &entry.href,
+                    entry.kind,
+                    Some(&entry.title),
+                    false,
+                )
+            });
+        }
+
+        if !publications.is_empty() {
+            out.push(',');
+            json::key(&mut out, "publications");
+            list(&mut out, &publications, |out, entry| {
+                publication(out, entry)
+            });
+        }
+
+        out.push('}');
+
+        out
+    }
+}
+
+fn list<T>(out: &mut String, items: &[T], mut write: impl FnMut(&mut String, &T)) {
+    out.push('[');
+
+    for (i, item) in items.iter().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+
+        write(out, item);
+    }
+
+    out.push(']');
+}
+
+fn json_link(
+    out: &mut String,
+    rel: Option<&str>,
+    href: &str,
+    kind: &str,
+    title: Option<&str>,
+    templated: bool,
+) {
+    out.push('{');
+
+    if let Some(rel) = rel {
+        json::key(out, "rel");
+        json::string(out, rel);
+        out.push(',');
+    }
+
+    if let Some(title) = title {
+        json::key(out, "title");
+        json::string(out, title);
+        out.push(',');
+    }
+
+    json::key(out, "href");
+    json::string(out, href);
+    out.push(',');
+    json::key(out, "type");
+    json::string(out, kind);
+
+    if templated {
+        out.push(',');
+        json::key(out, "templated");
+        out.push_str("true");
+    }
+
+    out.push('}');
+}
+
+fn publication(out: &mut String, entry: &StoryEntry) {
+    out.push('{');
+    json::key(out, "metadata");
+    out.push('{');
+    json::key(out, "@type");
+    json::string(out, "http://schema.org/Book");
+    out.push(',');
+    json::key(out, "identifier");
+    json::string(out, &entry.id);
+    out.push(',');
+    json::key(out, "title");
+    json::string(out, &entry.title);
+    out.push(',');
+    json::key(out, "modified");
+    json::string(out, &entry.updated);
+    out.push(',');
+    json::key(out, "published");
+    json::string(out, &entry.published);
+    out.push(',');
+    json::key(out, "description");
+    json::string(out, &entry.summary);
+    out.push(',');
+    json::key(out, "author");
+    list(out, &entry.authors, |out, (name, href)| {
+        out.push('{');
+        json::key(out, "name");
+        json::string(out, name);
+        out.push(',');
+        json::key(out, "links");
+        out.push('[');
+        json_link(out, None, href, "text/html", None, false);
+        out.push_str("]}");
+    });
+    out.push(',');
+    json::key(out, "subject");
+    list(out, &entry.categories, |out, (term, label)| {
+        out.push('{');
+        json::key(out, "name");
+        json::string(out, label);
+        out.push(',');
+        json::key(out, "code");
+        json::string(out, term);
+        out.push('}');
+    });
+    out.push_str("},");
+    json::key(out, "links");
+    out.push('[');
+    for (href, kind) in &entry.acquisitions {
+        json_link(
+            out,
+            Some("http://opds-spec.org/acquisition"),
+            href,
+            kind,
+            None,
+            false,
+        );
+        out.push(',');
     }
+    json_link(
+        out,
+        Some("alternate"),
+        &entry.read_href,
+        "text/html",
+        None,
+        false,
+    );
+    out.push_str("]}");
 }
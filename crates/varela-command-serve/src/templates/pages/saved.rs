@@ -0,0 +1,18 @@
+#[derive(opal::Template)]
+#[template(path = "pages/saved.hbs")]
+pub struct Saved {
+    pub searches: Vec<SavedEntry>,
+}
+
+impl Saved {
+    pub fn new(searches: Vec<SavedEntry>) -> Self {
+        Self { searches }
+    }
+}
+
+pub struct SavedEntry {
+    pub name: String,
+    pub query: String,
+    pub count: usize,
+    pub default: bool,
+}
@@ -5,10 +5,15 @@ use crate::{search::FilledStats, templates::partials::StoryPartial};
 pub struct Search {
     pub stories: Vec<StoryPartial>,
     pub stats: FilledStats,
+    pub query: String,
 }
 
 impl Search {
-    pub fn new(stories: Vec<StoryPartial>, stats: FilledStats) -> Self {
-        Self { stories, stats }
+    pub fn new(stories: Vec<StoryPartial>, stats: FilledStats, query: String) -> Self {
+        Self {
+            stories,
+            stats,
+            query,
+        }
     }
 }
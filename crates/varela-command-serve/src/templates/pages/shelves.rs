@@ -0,0 +1,22 @@
+#[derive(opal::Template)]
+#[template(path = "pages/shelves.hbs")]
+pub struct Shelves {
+    pub shelves: Vec<ShelfEntry>,
+}
+
+impl Shelves {
+    pub fn new(shelves: Vec<ShelfEntry>) -> Self {
+        Self { shelves }
+    }
+}
+
+pub struct ShelfEntry {
+    pub href: String,
+    /// The name, encoded for use in a query.
+    pub query: String,
+    pub name: String,
+    pub text: String,
+    pub count: usize,
+    /// Only shelves made by the reader can be deleted.
+    pub custom: bool,
+}
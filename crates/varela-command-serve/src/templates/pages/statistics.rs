@@ -0,0 +1,22 @@
+use crate::templates::partials::Link;
+
+#[derive(opal::Template)]
+#[template(path = "pages/statistics.hbs")]
+pub struct Statistics {
+    /// The headline numbers, as a name and an already formatted value.
+    pub totals: Vec<(&'static str, String)>,
+    pub groups: Vec<StatisticsGroup>,
+}
+
+pub struct StatisticsGroup {
+    pub name: &'static str,
+    pub rows: Vec<StatisticsRow>,
+}
+
+pub struct StatisticsRow {
+    /// The entity's link, or just its text when there's nowhere to go.
+    pub label: Result<Link, String>,
+    pub count: String,
+    /// How wide the row's bar is, as a percentage of the largest in the group.
+    pub width: usize,
+}
@@ -0,0 +1,25 @@
+use crate::templates::partials::{ChapterBody, ReaderPanel, StoryPartial};
+
+/// Every chapter of a story on one page, with a table of contents up top.
+#[derive(opal::Template)]
+#[template(path = "pages/work.hbs")]
+pub struct Work {
+    pub card: StoryPartial,
+    /// Each chapter's title and text, in order.
+    pub chapters: Vec<(String, ChapterBody)>,
+    pub reader: ReaderPanel,
+}
+
+impl Work {
+    pub fn new(
+        card: StoryPartial,
+        chapters: Vec<(String, ChapterBody)>,
+        reader: ReaderPanel,
+    ) -> Self {
+        Self {
+            card,
+            chapters,
+            reader,
+        }
+    }
+}
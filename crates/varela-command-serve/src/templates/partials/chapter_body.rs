@@ -0,0 +1,11 @@
+/// A chapter's text along with the summary and notes that surround it.
+#[derive(opal::Template)]
+#[template(path = "partials/chapter-body.hbs")]
+pub struct ChapterBody {
+    pub summary: Option<String>,
+    /// Notes shown before the chapter, as a heading and their contents.
+    pub start_notes: Vec<(&'static str, String)>,
+    pub chapter: String,
+    /// Notes shown after the chapter, as a heading and their contents.
+    pub end_notes: Vec<(&'static str, String)>,
+}
@@ -0,0 +1,50 @@
+use common::{
+    database::Database,
+    models::{Id, Progress},
+};
+
+use crate::templates::partials::{Contrast, Link};
+
+/// The most recently read stories that haven't been finished yet.
+#[derive(opal::Template)]
+#[template(path = "partials/continue-reading.hbs")]
+pub struct ContinueReading {
+    pub entries: Vec<ContinueEntry>,
+}
+
+pub struct ContinueEntry {
+    pub id: Id,
+    pub title: String,
+    pub chapters: usize,
+    pub progress: Progress,
+}
+
+impl ContinueReading {
+    pub const LIMIT: usize = 5;
+
+    /// Returns `None` when there is nothing left to continue.
+    pub fn new(db: &Database) -> Option<Self> {
+        let mut entries = db
+            .library()
+            .progress
+            .iter()
+            .filter_map(|(id, progress)| {
+                let story = db.index().stories.get(id)?;
+
+                Some(ContinueEntry {
+                    id: id.clone(),
+                    title: story.info.title.clone(),
+                    chapters: story.chapters.len(),
+                    progress: progress.clone(),
+                })
+            })
+            .filter(|entry| entry.progress.percent(entry.chapters) < 100)
+            .collect::<Vec<_>>();
+
+        // timestamps are all RFC 3339 so they sort as strings
+        entries.sort_by(|a, b| b.progress.updated.cmp(&a.progress.updated));
+        entries.truncate(Self::LIMIT);
+
+        (!entries.is_empty()).then_some(Self { entries })
+    }
+}
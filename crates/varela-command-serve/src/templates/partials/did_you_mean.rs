@@ -0,0 +1,62 @@
+use enrgy::http::encoding::percent::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+use crate::{
+    search::Suggestion,
+    templates::partials::{Contrast, Link},
+};
+
+#[derive(opal::Template)]
+#[template(path = "partials/did-you-mean.hbs")]
+pub struct DidYouMean {
+    pub suggestions: Vec<(String, Vec<Link>)>,
+}
+
+impl DidYouMean {
+    /// Builds the suggestion links, each replacing the unmatched term in `search`.
+    pub fn new(search: &str, suggestions: Vec<Suggestion>) -> Self {
+        Self {
+            suggestions: suggestions
+                .into_iter()
+                .map(|suggestion| {
+                    let links = suggestion
+                        .candidates
+                        .iter()
+                        .map(|candidate| {
+                            let replaced = suggestion.replace(search, candidate);
+
+                            Link::new(
+                                Contrast::High,
+                                vfmt::format!(
+                                    "/search?search={}",
+                                    utf8_percent_encode(&replaced, NON_ALPHANUMERIC).to_string()
+                                ),
+                                candidate.clone(),
+                            )
+                        })
+                        .collect();
+
+                    (suggestion.term, links)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use opal::Template as _;
+
+    use super::*;
+
+    #[test]
+    fn test_term_escaped() {
+        let rendered = DidYouMean {
+            suggestions: vec![("<script>\"x\"</script>".to_owned(), Vec::new())],
+        }
+        .render_into_string()
+        .unwrap();
+
+        assert!(rendered.contains("&lt;script&gt;&quot;x&quot;&lt;/script&gt;"));
+        assert!(!rendered.contains("<script>"));
+    }
+}
@@ -1,15 +1,25 @@
+pub mod chapter_body;
+pub mod continue_reading;
+pub mod did_you_mean;
 pub mod link;
 pub mod nav;
 pub mod origin_list;
 pub mod pagination;
+pub mod reader_panel;
+pub mod related;
 pub mod story;
 pub mod tag_list;
 
 pub use crate::templates::partials::{
+    chapter_body::ChapterBody,
+    continue_reading::ContinueReading,
+    did_you_mean::DidYouMean,
     link::{Contrast, Link},
     nav::Nav,
     origin_list::OriginList,
     pagination::Pagination,
+    reader_panel::ReaderPanel,
+    related::{Related, RelatedGroup},
     story::StoryPartial,
     tag_list::TagList,
 };
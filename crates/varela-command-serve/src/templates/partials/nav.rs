@@ -1,14 +1,32 @@
 pub static NAV: Nav = Nav {
-    entries: [NavEntry {
-        url: "/fandoms",
-        name: "fandoms",
-    }],
+    entries: [
+        NavEntry {
+            url: "/fandoms",
+            name: "fandoms",
+        },
+        NavEntry {
+            url: "/saved",
+            name: "saved",
+        },
+        NavEntry {
+            url: "/shelves",
+            name: "shelves",
+        },
+        NavEntry {
+            url: "/stats",
+            name: "stats",
+        },
+        NavEntry {
+            url: "/random",
+            name: "random",
+        },
+    ],
 };
 
 #[derive(Clone, Copy, opal::Template)]
 #[template(path = "partials/nav.hbs")]
 pub struct Nav {
-    entries: [NavEntry; 1],
+    entries: [NavEntry; 5],
 }
 
 #[derive(Clone, Copy, opal::Template)]
@@ -30,6 +30,7 @@ impl Pagination {
                 vfmt::format!("{}/{}", url, page.checked_sub(1).unwrap_or(page))
             },
             text: "previous".into(),
+            rel: (page != 1).then_some("prev"),
         };
 
         for i in 1..=pages {
@@ -64,12 +65,13 @@ impl Pagination {
             } else {
                 LinkState::Normal
             },
-            href: if page == 1 {
+            href: if page == pages {
                 vfmt::format!("{}/{}", url, page)
             } else {
                 vfmt::format!("{}/{}", url, page.checked_add(1).unwrap_or(page))
             },
             text: "next".into(),
+            rel: (page != pages).then_some("next"),
         };
 
         let buff = buff
@@ -83,17 +85,32 @@ impl Pagination {
                     },
                     href: vfmt::format!("{}/{}", url, page),
                     text: vfmt::uDisplay::to_string(&page.into_readable()),
+                    rel: None,
                 },
                 Pager::Ellipse => Link {
                     state: LinkState::Normal,
                     href: "#".into(),
                     text: "..".into(),
+                    rel: None,
                 },
             })
             .collect::<Vec<_>>();
 
         (prev, buff, next)
     }
+
+    /// The previous and next pages, as a `Link` header value, if there are any.
+    pub fn link_header(&self) -> Option<String> {
+        let links = [&self.prev, &self.next]
+            .into_iter()
+            .filter_map(|link| {
+                link.rel
+                    .map(|rel| vfmt::format!("<{}>; rel=\"{}\"", link.href, rel))
+            })
+            .collect::<Vec<_>>();
+
+        (!links.is_empty()).then(|| links.join(", "))
+    }
 }
 
 #[derive(PartialEq)]
@@ -109,6 +126,8 @@ struct Link {
     state: LinkState,
     href: String,
     text: String,
+    /// Set on the previous and next links so that they can be followed from the keyboard.
+    rel: Option<&'static str>,
 }
 
 #[derive(PartialEq)]
@@ -118,3 +137,25 @@ enum LinkState {
     Active,
     Disabled,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_link_header() {
+        assert_eq!(
+            Some("</story/a/2>; rel=\"next\"".to_owned()),
+            Pagination::new("/story/a".into(), 1, 3).link_header()
+        );
+        assert_eq!(
+            Some("</story/a/1>; rel=\"prev\", </story/a/3>; rel=\"next\"".to_owned()),
+            Pagination::new("/story/a".into(), 2, 3).link_header()
+        );
+        assert_eq!(
+            Some("</story/a/2>; rel=\"prev\"".to_owned()),
+            Pagination::new("/story/a".into(), 3, 3).link_header()
+        );
+        assert_eq!(None, Pagination::new("/story/a".into(), 1, 1).link_header());
+    }
+}
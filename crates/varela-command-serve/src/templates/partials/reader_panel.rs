@@ -0,0 +1,79 @@
+use crate::reader::{Font, Preferences};
+
+/// The reader's typography settings, both applied to the page and as a form to change them.
+#[derive(opal::Template)]
+#[template(path = "partials/reader-panel.hbs")]
+pub struct ReaderPanel {
+    pub css: String,
+    pub justify: bool,
+    pub fields: Vec<ReaderField>,
+}
+
+pub struct ReaderField {
+    pub name: &'static str,
+    pub label: &'static str,
+    /// Each option's value, its text and if it's the current one.
+    pub options: Vec<(String, String, bool)>,
+}
+
+impl ReaderPanel {
+    pub fn new(preferences: Preferences) -> Self {
+        let numbers = |values: &[usize], current: usize, text: &dyn Fn(usize) -> String| {
+            values
+                .iter()
+                .map(|value| (value.to_string(), text(*value), *value == current))
+                .collect()
+        };
+
+        let tenths = |value: usize| format!("{}.{}", value / 10, value % 10);
+
+        Self {
+            css: preferences.css(),
+            justify: preferences.justify,
+            fields: vec![
+                ReaderField {
+                    name: "font",
+                    label: "font",
+                    options: Font::ALL
+                        .into_iter()
+                        .map(|font| {
+                            (
+                                font.slug().to_owned(),
+                                font.slug().to_owned(),
+                                font == preferences.font,
+                            )
+                        })
+                        .collect(),
+                },
+                ReaderField {
+                    name: "size",
+                    label: "size",
+                    options: numbers(&Preferences::SIZES, preferences.size, &|value| {
+                        format!("{}px", value)
+                    }),
+                },
+                ReaderField {
+                    name: "line",
+                    label: "line spacing",
+                    options: numbers(&Preferences::LINE_HEIGHTS, preferences.line_height, &tenths),
+                },
+                ReaderField {
+                    name: "width",
+                    label: "width",
+                    options: numbers(&Preferences::WIDTHS, preferences.width, &|value| {
+                        if value == 0 {
+                            "full".to_owned()
+                        } else {
+                            format!("{} characters", value)
+                        }
+                    }),
+                },
+                ReaderField {
+                    name: "spacing",
+                    label: "paragraph spacing",
+                    options: numbers(&Preferences::SPACINGS, preferences.spacing, &tenths),
+                },
+            ],
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use common::models::Id;
+
+use crate::templates::TagKind;
+
+/// The entities that show up in the same stories as the one being viewed.
+#[derive(opal::Template)]
+#[template(path = "partials/related.hbs")]
+pub struct Related {
+    pub groups: Vec<RelatedGroup>,
+}
+
+pub struct RelatedGroup {
+    pub name: &'static str,
+    pub kind: TagKind,
+    pub entities: Vec<(Id, String, usize)>,
+}
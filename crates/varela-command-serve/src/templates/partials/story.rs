@@ -1,11 +1,20 @@
 use common::{
-    models::{Entity, Existing, Id, Rating, ResolvedStory, ResolvedStoryMeta, StoryInfo},
+    database::Database,
+    models::{
+        Bookmark, Entity, Existing, Id, Library, Progress, Rating, ResolvedStory,
+        ResolvedStoryMeta, Status, StoryInfo,
+    },
     prelude::*,
 };
 
-use crate::templates::{
-    partials::{Contrast, Link, OriginList, TagList},
-    TagKind,
+use enrgy::http::encoding::percent::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+use crate::{
+    templates::{
+        partials::{Contrast, Link, OriginList, TagList},
+        TagKind,
+    },
+    utils,
 };
 
 #[derive(opal::Template)]
@@ -24,6 +33,13 @@ pub struct StoryPartial {
     pub tags: TagList,
 
     pub query: Option<String>,
+
+    pub progress: Option<Progress>,
+    pub bookmark: Bookmark,
+    /// The reader's shelves and if this story is on them.
+    pub shelves: Vec<(String, bool)>,
+    /// The reader's tags, as a link to search for them and their text.
+    pub user_tags: Vec<(String, String)>,
 }
 
 impl StoryPartial {
@@ -66,9 +82,59 @@ impl StoryPartial {
             },
 
             query,
+
+            progress: None,
+            bookmark: Bookmark {
+                favourite: false,
+                status: None,
+                notes: String::new(),
+                rating: 0,
+                tags: Vec::new(),
+                following: false,
+                updated: false,
+            },
+            shelves: Vec::new(),
+            user_tags: Vec::new(),
         })
     }
 
+    /// Resolves a story from the index and fills in what the reader has done with it.
+    pub fn load(db: &Database, id: &Id, query: Option<String>) -> Result<Self> {
+        let story = utils::get_story_full(db, id)?;
+
+        Ok(Self::new(id.clone(), story, query)?.with_library(db.library()))
+    }
+
+    pub fn with_library(mut self, library: &Library) -> Self {
+        self.progress = library.progress.get(&self.id).cloned();
+
+        if let Some(bookmark) = library.bookmarks.get(&self.id) {
+            self.bookmark = bookmark.clone();
+        }
+
+        self.user_tags = self
+            .bookmark
+            .tags
+            .iter()
+            .map(|tag| {
+                let href = vfmt::format!(
+                    "/search2?it={}",
+                    utf8_percent_encode(tag, NON_ALPHANUMERIC).to_string()
+                );
+
+                (href, tag.clone())
+            })
+            .collect();
+
+        self.shelves = library
+            .shelves
+            .iter()
+            .map(|shelf| (shelf.name.clone(), shelf.stories.contains(&self.id)))
+            .collect();
+
+        self
+    }
+
     fn push(
         tags: &mut Vec<(TagKind, Existing<Entity>)>,
         kind: TagKind,
@@ -0,0 +1,228 @@
+//! Checking followed works for new chapters, and downloading them again when they have some.
+
+use std::{sync::RwLock, time::Duration};
+
+use common::{database::Database, models::Id, prelude::*};
+use format_ao3::site::{self, WorkStats};
+
+use crate::{queue, utils};
+
+/// How often followed works are checked.
+pub const INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// How long after starting the first check is made, so restarting the server doesn't check every time.
+pub const FIRST: Duration = Duration::from_secs(10 * 60);
+
+/// How long to wait between checking each work, the archive limits how often it can be asked for pages.
+pub const SPACING: Duration = Duration::from_secs(5);
+
+/// A followed story that came from the archive, with what it had when it was last downloaded.
+#[derive(Debug)]
+pub struct Followed {
+    pub id: Id,
+    /// The link to the work on the archive.
+    pub source: String,
+    pub work: String,
+    pub chapters: usize,
+    pub posted: Option<String>,
+}
+
+pub fn followed(db: &Database) -> Vec<Followed> {
+    db.library()
+        .bookmarks
+        .iter()
+        .filter(|(_, bookmark)| bookmark.following)
+        .filter_map(|(id, _)| {
+            let story = db.index().stories.get(id)?;
+
+            // following a story that didn't come from the archive, or that hasn't been indexed since sources
+            // were kept, can't do anything
+            let Some((source, work)) = story
+                .info
+                .source
+                .as_ref()
+                .and_then(|source| Some((source, site::work_id(source)?)))
+            else {
+                warn!(
+                    target: "updates",
+                    "story {} is followed but has no archive link to check",
+                    id.as_str()
+                );
+
+                return None;
+            };
+
+            Some(Followed {
+                id: id.clone(),
+                source: source.clone(),
+                work: work.to_string(),
+                chapters: story.chapters.len(),
+                posted: story.info.posted.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Checks every followed work on the archive, queuing the ones with new chapters to be downloaded again.
+///
+/// `pause` is called between works to wait out the [`SPACING`], the check stops early if it gives back
+/// `false`. Gives back how many were queued.
+pub fn check<P>(db: &RwLock<Database>, archive: &str, mut pause: P) -> Result<usize>
+where
+    P: FnMut() -> bool,
+{
+    let followed = followed(&*utils::read(db)?);
+
+    info!(target: "updates", "checking {} followed works", followed.len());
+
+    let mut queued = 0;
+
+    for (i, followed) in followed.iter().enumerate() {
+        if i != 0 && !pause() {
+            info!(target: "updates", "stopped checking after {} works", i);
+
+            break;
+        }
+
+        // one work being gone or the archive having a hiccup shouldn't stop the rest being checked
+        match check_work(archive, followed) {
+            Ok(true) => {
+                info!(
+                    target: "updates",
+                    "work {} ({}) has been updated",
+                    followed.work,
+                    followed.id.as_str()
+                );
+
+                // nor should one that can't be queued
+                match queue::push(db, &followed.source) {
+                    Ok(_) => queued += 1,
+                    Err(err) => warn!(target: "updates", "{}", format!("{:#}", err)),
+                }
+            }
+            Ok(false) => {}
+            Err(err) => warn!(target: "updates", "{}", format!("{:#}", err)),
+        }
+    }
+
+    Ok(queued)
+}
+
+/// Checks the work's page to see if it has been updated since it was downloaded.
+pub fn check_work(archive: &str, followed: &Followed) -> Result<bool> {
+    let page = utils::http::get(&site::work_url(archive, &followed.work))
+        .with_context(|| vfmt::format!("unable to check work {}", followed.work))?;
+
+    let stats = site::work_stats(&String::from_utf8_lossy(&page)).ok_or_else(|| {
+        anyhow!(
+            "unable to find the stats for work {}, it may only be visible to logged in users",
+            followed.work
+        )
+    })?;
+
+    Ok(is_updated(followed, &stats))
+}
+
+fn is_updated(followed: &Followed, stats: &WorkStats) -> bool {
+    // a chapter can be replaced without the count changing, the date still moves when it is
+    stats.chapters > followed.chapters
+        || followed
+            .posted
+            .as_deref()
+            .map(|posted| stats.updated.as_str() > posted)
+            .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use enrgy::client::testing::serve;
+
+    use super::*;
+
+    /// A stand in for the archive, serving the canned responses in order and giving back the requested paths.
+    fn archive(responses: Vec<String>) -> (String, impl FnOnce() -> Vec<String>) {
+        let (base, server) = serve(responses.into_iter().map(String::into_bytes).collect());
+
+        let paths = move || {
+            server
+                .join()
+                .unwrap()
+                .iter()
+                .map(|request| request.split(' ').nth(1).unwrap_or_default().to_string())
+                .collect()
+        };
+
+        (base, paths)
+    }
+
+    fn ok(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    fn page(status: &str, chapters: &str) -> String {
+        ok(&format!(
+            r#"<dl class="stats"><dt class="published">Published:</dt><dd class="published">2019-01-15</dd>{}<dt class="chapters">Chapters:</dt><dd class="chapters">{}</dd></dl>"#,
+            status, chapters
+        ))
+    }
+
+    fn followed(chapters: usize, posted: &str) -> Followed {
+        Followed {
+            id: Id::from("a".to_string()),
+            source: "http://archiveofourown.org/works/17434637".to_string(),
+            work: "17434637".to_string(),
+            chapters,
+            posted: Some(posted.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_check_work() {
+        let updated = r#"<dt class="status">Updated:</dt><dd class="status">2019-02-01</dd>"#;
+
+        let (base, server) = archive(vec![
+            page("", "2/5"),
+            page("", r#"<a href="/works/17434637/chapters/3">3</a>/5"#),
+            page(updated, "2/5"),
+            page(updated, "2/5"),
+        ]);
+
+        assert!(!check_work(&base, &followed(2, "2019-01-15")).unwrap());
+        assert!(check_work(&base, &followed(2, "2019-01-15")).unwrap());
+        assert!(check_work(&base, &followed(2, "2019-01-15")).unwrap());
+        assert!(!check_work(&base, &followed(2, "2019-02-01")).unwrap());
+
+        let paths = server();
+
+        assert_eq!(4, paths.len());
+        assert!(paths
+            .iter()
+            .all(|path| path == "/works/17434637?view_adult=true"));
+    }
+
+    #[test]
+    fn test_check_work_errors() {
+        let (base, server) = archive(vec![
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+            ok(r#"<form action="/users/login"></form>"#),
+        ]);
+
+        let followed = followed(2, "2019-01-15");
+
+        let err = check_work(&base, &followed).unwrap_err();
+        assert_eq!("unable to check work 17434637", err.to_string());
+        assert!(err
+            .root_cause()
+            .to_string()
+            .ends_with("failed with status 404"));
+
+        let err = check_work(&base, &followed).unwrap_err().to_string();
+        assert!(err.starts_with("unable to find the stats for work 17434637"));
+
+        server();
+    }
+}
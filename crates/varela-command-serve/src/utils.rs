@@ -1,4 +1,9 @@
-use std::{collections::HashMap, sync::RwLock};
+use std::{
+    collections::HashMap,
+    fs,
+    ops::Range,
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
 
 use common::{
     database::Database,
@@ -7,59 +12,84 @@ use common::{
 };
 use once_cell::sync::Lazy;
 
+use crate::templates::partials;
+
 pub mod http {
     use {
         common::prelude::*,
-        std::{fs, path::Path},
+        enrgy::client::{Client, ClientResponse},
+        once_cell::sync::Lazy,
     };
 
-    pub fn get<P>(temp_path: P, url: &str) -> Result<Vec<u8>>
-    where
-        P: AsRef<Path>,
-    {
-        let url = url.replace(
-            &['\\', '/', ':', ';', '<', '>', '"', '|', '?', '*', '[', ']'][..],
-            "-",
-        );
+    /// Shared so cookies from one download carry over to the next, sites check for them.
+    static CLIENT: Lazy<Client> =
+        Lazy::new(|| Client::new().user_agent(concat!("varela/", env!("CARGO_PKG_VERSION"))));
 
-        let temp_path = temp_path.as_ref();
+    pub fn get(url: &str) -> Result<Vec<u8>> {
+        Ok(fetch(url)?.body)
+    }
 
-        fs::create_dir_all(temp_path)?;
+    /// Gets where the URL ends up, after following its redirects.
+    pub fn locate(url: &str) -> Result<String> {
+        Ok(fetch(url)?.url.to_string())
+    }
 
-        let temp_file_path = temp_path.join(&url);
+    fn fetch(url: &str) -> Result<ClientResponse> {
+        let response = CLIENT.get(url)?;
 
-        let output = common::utils::command("curl")
-            .arg("-L")
-            .arg("-o")
-            .arg(&temp_file_path)
-            .arg(&url)
-            .output()?;
+        if !response.is_success() {
+            return Err(anyhow!(
+                "request to `{}` failed with status {}",
+                response.url,
+                response.status.0
+            ));
+        }
 
-        if !output.status.success() {
-            let mut err = anyhow!("curl return with error code {:?}", output.status);
+        Ok(response)
+    }
+}
 
-            if let Ok(text) = String::from_utf8(output.stdout) {
-                err = err.context(vfmt::format!("with stdout: {}", text));
-            }
+pub fn read(db: &RwLock<Database>) -> Result<RwLockReadGuard<'_, Database>> {
+    db.read()
+        .map_err(|err| anyhow!("unable to get lock on database: {}", err))
+}
 
-            if let Ok(text) = String::from_utf8(output.stderr) {
-                err = err.context(vfmt::format!("with stderr: {}", text));
-            }
+pub fn write(db: &RwLock<Database>) -> Result<RwLockWriteGuard<'_, Database>> {
+    db.write()
+        .map_err(|err| anyhow!("unable to get lock on database: {}", err))
+}
 
-            return Err(err);
-        }
+/// Writes the file into the data directory and indexes it, giving back its ID if it's a story that can be indexed.
+///
+/// Doesn't save the index.
+pub fn store(db: &mut Database, name: &str, bytes: &[u8]) -> Result<Option<Id>> {
+    let path = db.data_path.join(name);
 
-        let bytes = fs::read(&temp_file_path)?;
+    // the files are held open and locked while the server runs, this one may be one of them
+    db.unlock_data()?;
 
-        fs::remove_file(&temp_file_path)?;
+    let indexed = fs::write(&path, bytes)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| command_index::index_file(db, &path));
 
-        Ok(bytes)
-    }
+    db.lock_data()?;
+
+    indexed
 }
 
 static STORY_CACHE: Lazy<RwLock<HashMap<Id, ResolvedStory>>> =
     Lazy::new(|| RwLock::new(HashMap::new()));
 
+/// Drops the story from the cache, for when its file has been replaced.
+pub fn uncache_story(id: &Id) -> Result<()> {
+    STORY_CACHE
+        .write()
+        .map_err(|err| anyhow!("unable to get lock on cache: {}", err))?
+        .remove(id);
+
+    Ok(())
+}
+
 #[allow(clippy::ptr_arg)]
 pub fn get_story_full(db: &Database, id: &Id) -> Result<ResolvedStory> {
     if let Some(story) = STORY_CACHE
@@ -121,8 +151,12 @@ pub fn get_story_full(db: &Database, id: &Id) -> Result<ResolvedStory> {
             kind: story_ref.info.kind,
             title: info.title.clone(),
             summary: info.summary.clone(),
+            start_notes: info.start_notes.clone(),
+            end_notes: info.end_notes.clone(),
             created: story_ref.info.created.clone(),
             updated: story_ref.info.updated.clone(),
+            source: info.source.clone(),
+            posted: info.posted.clone(),
         },
         meta: ResolvedStoryMeta {
             rating: meta.rating,
@@ -204,3 +238,56 @@ impl IntoReadable for u64 {}
 
 impl IntoReadable for i32 {}
 impl IntoReadable for i64 {}
+
+/// Loads a chapter's text along with its summary and the notes around it.
+pub fn chapter_body(
+    db: &Database,
+    id: &Id,
+    story: &ResolvedStory,
+    index: usize,
+) -> Result<partials::ChapterBody> {
+    let chapter = db.get_chapter_body(id, index)?;
+
+    let meta = index
+        .checked_sub(1)
+        .and_then(|i| story.chapters.get(i))
+        .ok_or_else(|| anyhow!("chapter `{}` does not exist", index))?;
+
+    let notes = |range: &Option<Range<usize>>| -> Result<Option<String>> {
+        range
+            .clone()
+            .map(|range| db.get_range(id, range))
+            .transpose()
+    };
+
+    // the work's own notes sit before the first chapter and after the last, like on the archive
+    let mut start_notes = Vec::new();
+    let mut end_notes = Vec::new();
+
+    if index == 1 {
+        if let Some(work) = notes(&story.info.start_notes)? {
+            start_notes.push(("notes", work));
+        }
+    }
+
+    if let Some(chapter) = notes(&meta.start_notes)? {
+        start_notes.push(("chapter notes", chapter));
+    }
+
+    if let Some(chapter) = notes(&meta.end_notes)? {
+        end_notes.push(("chapter end notes", chapter));
+    }
+
+    if index == story.chapters.len() {
+        if let Some(work) = notes(&story.info.end_notes)? {
+            end_notes.push(("end notes", work));
+        }
+    }
+
+    Ok(partials::ChapterBody {
+        summary: meta.summary.clone(),
+        start_notes,
+        chapter,
+        end_notes,
+    })
+}
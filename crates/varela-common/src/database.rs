@@ -3,16 +3,25 @@ use std::{
     env,
     ffi::OsStr,
     fs::{self, File},
+    io::Write as _,
     mem,
-    path::PathBuf,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
 };
 
-use aloene::Aloene as _;
+use aloene::Aloene;
 use fs2::FileExt as _;
 use memmap2::Mmap;
 
 use crate::{
-    models::{Config, Entity, EntityKind, Id, Index, Settings, Theme, Version},
+    models::{
+        v1, Config, Download, Entity, EntityKind, Id, Index, Library, Settings, Theme, Version,
+    },
     prelude::*,
     utils::FileIter,
 };
@@ -26,12 +35,20 @@ pub struct Database {
     pub temp_path: PathBuf,
 
     lock_maps: HashMap<Id, MappedFile>,
+
+    /// changes that have been made but left to be saved later, see [`Database::mark_dirty`]
+    dirty: AtomicBool,
+    /// when the index was last written, see [`Database::save_throttled`]
+    saved: Mutex<Instant>,
 }
 
 impl Database {
     pub fn open() -> Result<Self> {
-        let cur = env::current_dir()?.canonicalize()?;
+        Self::open_in(&env::current_dir()?.canonicalize()?)
+    }
 
+    /// Opens the index in `cur`, creating it if there isn't one yet.
+    pub fn open_in(cur: &Path) -> Result<Self> {
         let index_path = cur.join("varela.aloe.dfl");
 
         let database = if index_path.exists() {
@@ -42,8 +59,7 @@ impl Database {
             let bytes = miniz_oxide::inflate::decompress_to_vec(&content)
                 .map_err(|err| anyhow::anyhow!("unable to decompress index: {:?}", err))?;
 
-            let inner =
-                Config::deserialize(&mut &bytes[..]).context("unable to deserialize index")?;
+            let inner = read_config(&bytes).context("unable to deserialize index")?;
 
             let data_path = PathBuf::from(&inner.settings.data_path);
             let temp_path = PathBuf::from(&inner.settings.temp_path);
@@ -56,6 +72,9 @@ impl Database {
                 temp_path,
 
                 lock_maps: HashMap::new(),
+
+                dirty: AtomicBool::new(false),
+                saved: Mutex::new(Instant::now()),
             }
         } else {
             debug!("not found, creating");
@@ -65,7 +84,7 @@ impl Database {
 
             Self {
                 inner: Config {
-                    version: Version::V1,
+                    version: Version::V2,
                     index: Index {
                         stories: HashMap::new(),
                         categories: HashMap::new(),
@@ -88,7 +107,15 @@ impl Database {
                             .ok_or_else(|| anyhow!("temp path with not valid utf-8"))?
                             .to_string(),
                         nodes: vec![],
+                        saved_searches: vec![],
+                        default_filter: None,
                     },
+                    library: Library {
+                        progress: HashMap::new(),
+                        bookmarks: HashMap::new(),
+                        shelves: vec![],
+                    },
+                    downloads: vec![],
                 },
 
                 data_path,
@@ -96,6 +123,9 @@ impl Database {
                 temp_path,
 
                 lock_maps: HashMap::new(),
+
+                dirty: AtomicBool::new(false),
+                saved: Mutex::new(Instant::now()),
             }
         };
 
@@ -113,6 +143,22 @@ impl Database {
         &mut self.inner.index
     }
 
+    pub fn library(&self) -> &Library {
+        &self.inner.library
+    }
+
+    pub fn library_mut(&mut self) -> &mut Library {
+        &mut self.inner.library
+    }
+
+    pub fn downloads(&self) -> &[Download] {
+        &self.inner.downloads
+    }
+
+    pub fn downloads_mut(&mut self) -> &mut Vec<Download> {
+        &mut self.inner.downloads
+    }
+
     pub fn settings(&self) -> &Settings {
         &self.inner.settings
     }
@@ -164,10 +210,10 @@ impl Database {
             .get(id)
             .ok_or_else(|| anyhow!("unable to find story in index"))?;
 
-        if let Some(mapped) = self.lock_maps.get(id) {
-            let contents = mapped.map.as_ref();
-
-            let chapter = story.chapters.get(number - 1).ok_or_else(|| {
+        let chapter = number
+            .checked_sub(1)
+            .and_then(|i| story.chapters.get(i))
+            .ok_or_else(|| {
                 anyhow!(
                     "chapter `{}` not found, chapters: {}",
                     number,
@@ -175,13 +221,32 @@ impl Database {
                 )
             })?;
 
-            let sliced = contents.get(chapter.content.clone()).ok_or_else(|| {
-                anyhow!(
+        self.get_range(id, chapter.content.clone())
+            .with_context(|| {
+                format!(
                     "chapter `{}` not found in chapter index for `{}`",
-                    number,
-                    id,
+                    number, id
                 )
-            })?;
+            })
+    }
+
+    /// Gets the whole of a story's original file from its mapped file.
+    pub fn get_file(&self, id: &Id) -> Result<&[u8]> {
+        if let Some(mapped) = self.lock_maps.get(id) {
+            Ok(mapped.map.as_ref())
+        } else {
+            bail!("unable to find story in locked mapped index")
+        }
+    }
+
+    /// Gets a section of a story's file, like a chapter's notes, from its mapped file.
+    pub fn get_range(&self, id: &Id, range: Range<usize>) -> Result<String> {
+        if let Some(mapped) = self.lock_maps.get(id) {
+            let contents = mapped.map.as_ref();
+
+            let sliced = contents
+                .get(range.clone())
+                .ok_or_else(|| anyhow!("range `{:?}` is outside of story `{}`", range, id))?;
 
             Ok(String::from_utf8(sliced.to_vec())?)
         } else {
@@ -262,15 +327,195 @@ impl Database {
 
         let compressed = miniz_oxide::deflate::compress_to_vec(&buf, 10);
 
-        fs::write(&self.index_path, compressed)?;
+        // written next to the index and moved over it, so stopping part way through never leaves half an index
+        let temp_path = self.index_path.with_extension("dfl.tmp");
+
+        let mut file = File::create(&temp_path)?;
+
+        file.write_all(&compressed)?;
+        file.sync_all()?;
+
+        drop(file);
+
+        fs::rename(&temp_path, &self.index_path)?;
+
+        self.dirty.store(false, Ordering::Release);
+
+        if let Ok(mut saved) = self.saved.lock() {
+            *saved = Instant::now();
+        }
+
+        Ok(())
+    }
+
+    /// Marks the index as changed without saving it, for changes that happen too often to save each time.
+    ///
+    /// Something else has to call [`Database::save_dirty`] every so often.
+    pub fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Marks the index as changed, saving it only if it hasn't been saved in the last `every`.
+    ///
+    /// Whatever is left unsaved is written by the next save, so [`Database::save_dirty`] still has to
+    /// be called before the database is dropped.
+    pub fn save_throttled(&self, every: Duration) -> Result<()> {
+        self.mark_dirty();
+
+        let due = self
+            .saved
+            .lock()
+            .map(|saved| saved.elapsed() >= every)
+            .unwrap_or(true);
+
+        if due {
+            self.save()?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the index if it has been marked as changed since it was last saved.
+    pub fn save_dirty(&self) -> Result<()> {
+        if self.dirty.load(Ordering::Acquire) {
+            self.save()?;
+        }
 
         Ok(())
     }
 }
 
+/// Reads the index, migrating it from whichever version it was written as.
+fn read_config(bytes: &[u8]) -> Result<Config> {
+    let header = Header::deserialize(&mut &bytes[..])?;
+
+    let config = match header.version {
+        Version::V1 => {
+            info!("migrating index from version 1");
+
+            v1::Config::deserialize(&mut &bytes[..])?.into()
+        }
+        Version::V2 => Config::deserialize(&mut &bytes[..])?,
+    };
+
+    Ok(config)
+}
+
+/// The start of every version of [`Config`], used to find out which one the rest is.
+#[derive(Aloene)]
+struct Header {
+    version: Version,
+}
+
 #[cfg_attr(feature = "std", derive(Debug))]
 struct MappedFile {
     name: String,
     file: File,
     map: Mmap,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::models::{FileKind, Rating, Site, StoryMeta, Theme};
+
+    use super::*;
+
+    fn v1() -> v1::Config {
+        let story = v1::Story {
+            info: v1::StoryInfo {
+                file_name: "Hello World - Txuritan.html".to_string(),
+                file_hash: 1234,
+                kind: FileKind::Html,
+                title: "Hello World".to_string(),
+                summary: "<p>A summary.</p>".to_string(),
+                created: "2019-01-15".to_string(),
+                updated: "2019-02-01".to_string(),
+            },
+            meta: StoryMeta {
+                rating: Rating::General,
+                authors: vec![Id::from("b".to_string())],
+                categories: vec![],
+                origins: vec![],
+                warnings: vec![],
+                pairings: vec![],
+                characters: vec![],
+                generals: vec![],
+            },
+            site: Site::ArchiveOfOurOwn,
+            chapters: vec![v1::Chapter {
+                title: "Chapter 1".to_string(),
+                content: 10..20,
+                summary: None,
+                start_notes: Some(5..10),
+                end_notes: None,
+            }],
+        };
+
+        v1::Config {
+            version: Version::V1,
+            settings: v1::Settings {
+                theme: Theme::Dark,
+                sync_key: "key".to_string(),
+                data_path: "/data".to_string(),
+                temp_path: "/temp".to_string(),
+                nodes: vec![],
+            },
+            index: v1::Index {
+                stories: HashMap::from([(Id::from("a".to_string()), story)]),
+                categories: HashMap::new(),
+                authors: HashMap::from([(
+                    Id::from("b".to_string()),
+                    Entity {
+                        text: "Txuritan".to_string(),
+                    },
+                )]),
+                origins: HashMap::new(),
+                warnings: HashMap::new(),
+                pairings: HashMap::new(),
+                characters: HashMap::new(),
+                generals: HashMap::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_read_v1() {
+        let mut buf = Vec::new();
+
+        v1().serialize(&mut buf).unwrap();
+
+        let config = read_config(&buf).unwrap();
+
+        assert_eq!(Version::V2, config.version);
+        assert_eq!(Theme::Dark, config.settings.theme);
+        assert_eq!("/data", config.settings.data_path);
+        assert!(config.settings.saved_searches.is_empty());
+        assert!(config.library.bookmarks.is_empty());
+        assert!(config.downloads.is_empty());
+        assert_eq!(
+            "Txuritan",
+            config.index.authors[&Id::from("b".to_string())].text
+        );
+
+        let story = &config.index.stories[&Id::from("a".to_string())];
+
+        assert_eq!("Hello World", story.info.title);
+        assert_eq!(0, story.info.file_hash);
+        assert_eq!(None, story.info.source);
+        assert_eq!(1, story.chapters.len());
+        assert_eq!(10..20, story.chapters[0].content);
+        assert_eq!(Some(5..10), story.chapters[0].start_notes);
+        assert_eq!(0, story.chapters[0].words);
+    }
+
+    #[test]
+    fn test_read_v2() {
+        let mut buf = Vec::new();
+
+        let config = Config::from(v1());
+
+        config.serialize(&mut buf).unwrap();
+
+        assert_eq!(config, read_config(&buf).unwrap());
+    }
+}
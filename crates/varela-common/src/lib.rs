@@ -2,6 +2,7 @@ pub mod database;
 pub mod logger;
 pub mod models;
 pub mod utils;
+pub mod zip;
 
 pub type Args = std::iter::Peekable<std::iter::Skip<std::env::Args>>;
 
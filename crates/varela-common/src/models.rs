@@ -6,11 +6,17 @@ use std::{borrow::Cow, collections::HashMap};
 
 use aloene::Aloene;
 
+pub mod v1;
+
 #[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "std", derive(Debug))]
 pub struct Id(Cow<'static, str>);
 
 impl Id {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
     pub fn len(&self) -> usize {
         self.0.len()
     }
@@ -119,12 +125,17 @@ pub struct Config {
     pub version: Version,
     pub settings: Settings,
     pub index: Index,
+    pub library: Library,
+    /// works waiting to be downloaded, or that have been, in the order they were added
+    pub downloads: Vec<Download>,
 }
 
 #[derive(Clone, Copy, PartialEq, Aloene)]
 #[cfg_attr(feature = "std", derive(Debug))]
 pub enum Version {
     V1,
+    /// added the library, downloads, saved searches and the newer story and chapter fields
+    V2,
 }
 
 #[derive(Clone, PartialEq, Aloene)]
@@ -140,6 +151,10 @@ pub struct Settings {
     pub temp_path: String,
     /// other instances that this instance will try to sync with
     pub nodes: Vec<Node>,
+    /// named `/search2` filters, in the order they were saved
+    pub saved_searches: Vec<SavedSearch>,
+    /// the name of the saved search whose exclusions are applied to every listing page
+    pub default_filter: Option<String>,
 }
 
 /// Nested message and enum types in `Settings`.
@@ -156,20 +171,303 @@ pub struct Node {
     pub port: u16,
 }
 
+/// Reader state that is kept separate from the imported story metadata.
+#[derive(Clone, PartialEq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Library {
+    /// how far into each story the reader has gotten
+    pub progress: HashMap<Id, Progress>,
+    /// favourites, reading statuses and notes for each story
+    pub bookmarks: HashMap<Id, Bookmark>,
+    /// shelves made by the reader, in the order they were created
+    pub shelves: Vec<Shelf>,
+}
+
+impl Library {
+    /// The name of the built in shelf holding favourited stories.
+    pub const FAVOURITES: &'static str = "favourites";
+    /// The name of the built in shelf holding stories that are checked for new chapters.
+    pub const FOLLOWING: &'static str = "following";
+    /// The name of the built in shelf holding followed stories with chapters that haven't been read yet.
+    pub const UPDATED: &'static str = "updated";
+
+    /// Checks if a name is taken by one of the built in shelves.
+    pub fn is_reserved(name: &str) -> bool {
+        [Self::FAVOURITES, Self::FOLLOWING, Self::UPDATED].contains(&name)
+            || Status::from_slug(name).is_some()
+    }
+
+    /// The names of every shelf, the built in ones first.
+    pub fn shelf_names(&self) -> Vec<&str> {
+        [Self::FAVOURITES, Self::FOLLOWING, Self::UPDATED]
+            .into_iter()
+            .chain(Status::ALL.iter().map(|status| status.slug()))
+            .chain(self.shelves.iter().map(|shelf| shelf.name.as_str()))
+            .collect()
+    }
+
+    /// Gets the stories on a shelf, either a built in one or one made by the reader.
+    pub fn shelf(&self, name: &str) -> Option<Vec<&Id>> {
+        let marked = |f: &dyn Fn(&Bookmark) -> bool| {
+            self.bookmarks
+                .iter()
+                .filter(|(_, bookmark)| f(bookmark))
+                .map(|(id, _)| id)
+                .collect()
+        };
+
+        if name == Self::FAVOURITES {
+            return Some(marked(&|bookmark| bookmark.favourite));
+        }
+
+        if name == Self::FOLLOWING {
+            return Some(marked(&|bookmark| bookmark.following));
+        }
+
+        if name == Self::UPDATED {
+            return Some(marked(&|bookmark| bookmark.updated));
+        }
+
+        if let Some(status) = Status::from_slug(name) {
+            return Some(marked(&|bookmark| bookmark.status == Some(status)));
+        }
+
+        self.shelves
+            .iter()
+            .find(|shelf| shelf.name == name)
+            .map(|shelf| shelf.stories.iter().collect())
+    }
+
+    /// Every tag the reader has used, sorted and without duplicates.
+    pub fn tags(&self) -> Vec<&str> {
+        let mut tags = self
+            .bookmarks
+            .values()
+            .flat_map(|bookmark| bookmark.tags.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+
+        tags.sort_unstable();
+        tags.dedup();
+
+        tags
+    }
+
+    pub fn tagged(&self, tag: &str) -> Vec<&Id> {
+        self.bookmarks
+            .iter()
+            .filter(|(_, bookmark)| bookmark.tags.iter().any(|t| t == tag))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    pub fn rated(&self, rating: usize) -> Vec<&Id> {
+        self.bookmarks
+            .iter()
+            .filter(|(_, bookmark)| bookmark.rating != 0 && bookmark.rating == rating)
+            .map(|(id, _)| id)
+            .collect()
+    }
+}
+
+#[derive(Clone, PartialEq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Progress {
+    /// the last chapter read, starting at `1`
+    pub chapter: usize,
+    /// how far down the chapter was scrolled, in thousandths
+    pub position: usize,
+    /// when the story was last read, in RFC 3339
+    pub updated: String,
+}
+
+impl Progress {
+    pub const POSITION_MAX: usize = 1000;
+
+    /// How much of a story with `chapters` chapters has been read, out of `100`.
+    pub fn percent(&self, chapters: usize) -> usize {
+        if chapters == 0 {
+            return 0;
+        }
+
+        let read = self.chapter.saturating_sub(1) * Self::POSITION_MAX
+            + self.position.min(Self::POSITION_MAX);
+
+        (read * 100 / (chapters * Self::POSITION_MAX)).min(100)
+    }
+}
+
+#[derive(Clone, PartialEq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Bookmark {
+    pub favourite: bool,
+    pub status: Option<Status>,
+    /// private notes, never shown outside of this instance
+    pub notes: String,
+    /// the reader's own rating from `1` to `5`, `0` if it hasn't been rated
+    pub rating: usize,
+    /// the reader's own tags, kept apart from the ones the story was exported with
+    pub tags: Vec<String>,
+    /// whether the story is checked for new chapters
+    pub following: bool,
+    /// new chapters were downloaded since the reader last read the story
+    pub updated: bool,
+}
+
+impl Bookmark {
+    pub const RATING_MAX: usize = 5;
+
+    pub fn is_empty(&self) -> bool {
+        !self.favourite
+            && self.status.is_none()
+            && self.notes.is_empty()
+            && self.rating == 0
+            && self.tags.is_empty()
+            && !self.following
+            && !self.updated
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum Status {
+    ToRead,
+    Reading,
+    Finished,
+    Dropped,
+}
+
+impl Status {
+    pub const ALL: [Status; 4] = [
+        Status::ToRead,
+        Status::Reading,
+        Status::Finished,
+        Status::Dropped,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Status::ToRead => "to read",
+            Status::Reading => "reading",
+            Status::Finished => "finished",
+            Status::Dropped => "dropped",
+        }
+    }
+
+    pub const fn slug(self) -> &'static str {
+        match self {
+            Status::ToRead => "to-read",
+            Status::Reading => "reading",
+            Status::Finished => "finished",
+            Status::Dropped => "dropped",
+        }
+    }
+
+    pub fn from_slug(slug: &str) -> Option<Status> {
+        Status::ALL.into_iter().find(|status| status.slug() == slug)
+    }
+}
+
+#[derive(Clone, PartialEq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Shelf {
+    /// the name of the shelf, unique between shelves and never a built in shelf's name
+    pub name: String,
+    pub stories: Vec<Id>,
+}
+
+#[derive(Clone, PartialEq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Download {
+    /// the link the download was asked for with
+    pub url: String,
+    /// the AO3 work ID from the link, unique between downloads
+    pub work: String,
+    pub status: DownloadStatus,
+    /// why the last attempt failed, kept while it waits to be tried again
+    pub error: Option<String>,
+    /// how many times the download has been tried
+    pub attempts: usize,
+    /// when the download was last added or changed status, in RFC 3339
+    pub updated: String,
+    /// when a failed attempt can be tried again, in RFC 3339
+    pub retry_at: Option<String>,
+    /// the story the work was indexed as
+    pub story: Option<Id>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum DownloadStatus {
+    Queued,
+    Downloading,
+    Indexed,
+    /// Gave up after too many attempts.
+    Failed,
+}
+
+impl DownloadStatus {
+    pub const fn name(self) -> &'static str {
+        match self {
+            DownloadStatus::Queued => "queued",
+            DownloadStatus::Downloading => "downloading",
+            DownloadStatus::Indexed => "indexed",
+            DownloadStatus::Failed => "failed",
+        }
+    }
+
+    /// Whether the worker is finished with the download, one way or the other.
+    pub const fn is_done(self) -> bool {
+        matches!(self, DownloadStatus::Indexed | DownloadStatus::Failed)
+    }
+}
+
+#[derive(Clone, PartialEq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct SavedSearch {
+    /// the name of the search, unique between saved searches
+    pub name: String,
+    /// the raw `/search2` query string, without the leading `?`
+    pub query: String,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Aloene)]
 #[cfg_attr(feature = "std", derive(Debug))]
 pub enum Theme {
     Light,
     Dark,
+    Sepia,
+    Contrast,
+    Oled,
+    /// Light or dark depending on the browser's `prefers-color-scheme`.
+    System,
 }
 
 impl Theme {
+    pub const ALL: [Theme; 6] = [
+        Theme::Light,
+        Theme::Dark,
+        Theme::Sepia,
+        Theme::Contrast,
+        Theme::Oled,
+        Theme::System,
+    ];
+
     pub fn as_class(&self) -> &'static str {
         match self {
             Theme::Light => "light",
             Theme::Dark => "dark",
+            Theme::Sepia => "sepia",
+            Theme::Contrast => "contrast",
+            Theme::Oled => "oled",
+            Theme::System => "system",
         }
     }
+
+    pub fn from_class(class: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|theme| theme.as_class() == class)
+    }
 }
 
 #[derive(Clone, PartialEq, Aloene)]
@@ -185,6 +483,20 @@ pub struct Index {
     pub generals: HashMap<Id, Entity>,
 }
 
+impl Index {
+    pub fn entities(&self, kind: EntityKind) -> &HashMap<Id, Entity> {
+        match kind {
+            EntityKind::Author => &self.authors,
+            EntityKind::Category => &self.categories,
+            EntityKind::Warning => &self.warnings,
+            EntityKind::Origin => &self.origins,
+            EntityKind::Pairing => &self.pairings,
+            EntityKind::Character => &self.characters,
+            EntityKind::General => &self.generals,
+        }
+    }
+}
+
 pub type Story = CoreStory<StoryMeta>;
 pub type ResolvedStory = CoreStory<ResolvedStoryMeta>;
 
@@ -209,8 +521,14 @@ pub struct StoryInfo {
     pub kind: FileKind,
     pub title: String,
     pub summary: String,
+    pub start_notes: Option<Range<usize>>,
+    pub end_notes: Option<Range<usize>>,
     pub created: String,
     pub updated: String,
+    /// the link to the work on the site it came from, if the file has it
+    pub source: Option<String>,
+    /// when the site says a chapter was last posted, as `YYYY-MM-DD`
+    pub posted: Option<String>,
 }
 
 pub type StoryMeta = StoryMetaCore<Id>;
@@ -232,6 +550,23 @@ where
     pub generals: Vec<Entity>,
 }
 
+impl<Entity> StoryMetaCore<Entity>
+where
+    Entity: Aloene,
+{
+    pub fn entities(&self, kind: EntityKind) -> &[Entity] {
+        match kind {
+            EntityKind::Author => &self.authors,
+            EntityKind::Category => &self.categories,
+            EntityKind::Warning => &self.warnings,
+            EntityKind::Origin => &self.origins,
+            EntityKind::Pairing => &self.pairings,
+            EntityKind::Character => &self.characters,
+            EntityKind::General => &self.generals,
+        }
+    }
+}
+
 /// Nested message and enum types in `Meta`.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Aloene)]
 #[cfg_attr(feature = "nostd", derive(vfmt::derive::uDebug))]
@@ -295,10 +630,14 @@ pub struct Chapter {
     pub summary: Option<String>,
     pub start_notes: Option<Range<usize>>,
     pub end_notes: Option<Range<usize>>,
+    pub words: usize,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
 pub enum EntityKind {
     Author,
+    Category,
     Warning,
     Origin,
     Pairing,
@@ -0,0 +1,158 @@
+//! The index as it was written before the library, downloads and the newer story fields were added.
+//!
+//! Aloene reads fields by position, so an older index can only be read with the structs it was written
+//! with. These are only ever read, and then migrated to the current ones.
+
+use core::ops::Range;
+use std::collections::HashMap;
+
+use aloene::Aloene;
+
+use super::{Entity, FileKind, Id, Library, Node, Site, StoryMeta, Theme, Version};
+
+#[derive(Clone, PartialEq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Config {
+    pub version: Version,
+    pub settings: Settings,
+    pub index: Index,
+}
+
+#[derive(Clone, PartialEq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Settings {
+    pub theme: Theme,
+    pub sync_key: String,
+    pub data_path: String,
+    pub temp_path: String,
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Clone, PartialEq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Index {
+    pub stories: HashMap<Id, Story>,
+    pub categories: HashMap<Id, Entity>,
+    pub authors: HashMap<Id, Entity>,
+    pub origins: HashMap<Id, Entity>,
+    pub warnings: HashMap<Id, Entity>,
+    pub pairings: HashMap<Id, Entity>,
+    pub characters: HashMap<Id, Entity>,
+    pub generals: HashMap<Id, Entity>,
+}
+
+#[derive(Clone, PartialEq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Story {
+    pub info: StoryInfo,
+    pub meta: StoryMeta,
+    pub site: Site,
+    pub chapters: Vec<Chapter>,
+}
+
+#[derive(Clone, PartialEq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct StoryInfo {
+    pub file_name: String,
+    pub file_hash: u64,
+    pub kind: FileKind,
+    pub title: String,
+    pub summary: String,
+    pub created: String,
+    pub updated: String,
+}
+
+#[derive(Clone, PartialEq, Aloene)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Chapter {
+    pub title: String,
+    pub content: Range<usize>,
+    pub summary: Option<String>,
+    pub start_notes: Option<Range<usize>>,
+    pub end_notes: Option<Range<usize>>,
+}
+
+impl From<Config> for super::Config {
+    fn from(config: Config) -> Self {
+        let Config {
+            version: _,
+            settings,
+            index,
+        } = config;
+
+        super::Config {
+            version: Version::V2,
+            settings: super::Settings {
+                theme: settings.theme,
+                sync_key: settings.sync_key,
+                data_path: settings.data_path,
+                temp_path: settings.temp_path,
+                nodes: settings.nodes,
+                saved_searches: vec![],
+                default_filter: None,
+            },
+            index: super::Index {
+                stories: index
+                    .stories
+                    .into_iter()
+                    .map(|(id, story)| (id, story.into()))
+                    .collect(),
+                categories: index.categories,
+                authors: index.authors,
+                origins: index.origins,
+                warnings: index.warnings,
+                pairings: index.pairings,
+                characters: index.characters,
+                generals: index.generals,
+            },
+            library: Library {
+                progress: HashMap::new(),
+                bookmarks: HashMap::new(),
+                shelves: vec![],
+            },
+            downloads: vec![],
+        }
+    }
+}
+
+impl From<Story> for super::Story {
+    fn from(story: Story) -> Self {
+        let Story {
+            info,
+            meta,
+            site,
+            chapters,
+        } = story;
+
+        super::Story {
+            info: super::StoryInfo {
+                file_name: info.file_name,
+                // the older index didn't have the source, posted date, work notes or word counts, a hash
+                // that never matches has the next index run read every file again to fill them in
+                file_hash: 0,
+                kind: info.kind,
+                title: info.title,
+                summary: info.summary,
+                start_notes: None,
+                end_notes: None,
+                created: info.created,
+                updated: info.updated,
+                source: None,
+                posted: None,
+            },
+            meta,
+            site,
+            chapters: chapters
+                .into_iter()
+                .map(|chapter| super::Chapter {
+                    title: chapter.title,
+                    content: chapter.content,
+                    summary: chapter.summary,
+                    start_notes: chapter.start_notes,
+                    end_notes: chapter.end_notes,
+                    words: 0,
+                })
+                .collect(),
+        }
+    }
+}
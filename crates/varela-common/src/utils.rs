@@ -101,6 +101,13 @@ pub fn new_id() -> Id {
     }
 }
 
+/// Picks a random index into something `len` long, if it isn't empty.
+pub fn random_index(len: usize) -> Option<usize> {
+    let len = u32::try_from(len).ok().filter(|len| *len != 0)?;
+
+    Some(fastrand::u32_below(len) as usize)
+}
+
 mod fastrand {
     //! A stripped down version of [fastrand](https://github.com/smol-rs/fastrand).
 
@@ -257,4 +264,12 @@ mod fastrand {
     pub fn u8(range: impl RangeBounds<u8>) -> u8 {
         with_rng(|r| r.u8(range))
     }
+
+    /// Generates a random `u32` below `n`.
+    ///
+    /// Panics if `n` is zero.
+    #[inline]
+    pub fn u32_below(n: u32) -> u32 {
+        with_rng(|r| r.gen_mod_u32(n))
+    }
 }
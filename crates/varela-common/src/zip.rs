@@ -0,0 +1,172 @@
+//! Just enough zip writing for EPUBs and exports, files are either stored or deflated and nothing is over 4GB.
+
+use std::io::Write;
+
+use crate::prelude::*;
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL: u32 = 0x06054b50;
+
+/// Version 2.0, the first to support deflate and folders.
+const VERSION: u16 = 20;
+
+/// Marks the names as UTF-8.
+const FLAG_UTF8: u16 = 1 << 11;
+
+/// 1980-01-01 00:00, the earliest date zip can store, this keeps the output the same for the same input.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+#[derive(Clone, Copy)]
+pub enum Method {
+    Stored = 0,
+    Deflated = 8,
+}
+
+struct Entry {
+    name: String,
+    method: Method,
+    crc: u32,
+    compressed: u32,
+    uncompressed: u32,
+    offset: u32,
+}
+
+pub struct ZipWriter<W: Write> {
+    writer: W,
+    offset: u32,
+    entries: Vec<Entry>,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds a file, they end up in the archive in the order they're added.
+    pub fn file(&mut self, name: &str, method: Method, data: &[u8]) -> Result<()> {
+        let crc = crc32fast::hash(data);
+
+        let deflated;
+        let stored = match method {
+            Method::Stored => data,
+            Method::Deflated => {
+                deflated = miniz_oxide::deflate::compress_to_vec(data, 6);
+
+                &deflated[..]
+            }
+        };
+
+        let entry = Entry {
+            name: name.to_owned(),
+            method,
+            crc,
+            compressed: size(stored.len())?,
+            uncompressed: size(data.len())?,
+            offset: self.offset,
+        };
+
+        let mut header = Vec::with_capacity(30 + name.len());
+
+        u32_le(&mut header, LOCAL_HEADER);
+        u16_le(&mut header, VERSION);
+        u16_le(&mut header, FLAG_UTF8);
+        u16_le(&mut header, method as u16);
+        u16_le(&mut header, DOS_TIME);
+        u16_le(&mut header, DOS_DATE);
+        u32_le(&mut header, entry.crc);
+        u32_le(&mut header, entry.compressed);
+        u32_le(&mut header, entry.uncompressed);
+        u16_le(&mut header, name_len(name)?);
+        u16_le(&mut header, 0);
+        header.extend_from_slice(name.as_bytes());
+
+        self.write(&header)?;
+        self.write(stored)?;
+
+        self.entries.push(entry);
+
+        Ok(())
+    }
+
+    /// Writes the central directory, giving back the writer once the archive is complete.
+    pub fn finish(mut self) -> Result<W> {
+        let start = self.offset;
+
+        let mut directory = Vec::new();
+
+        for entry in &self.entries {
+            u32_le(&mut directory, CENTRAL_HEADER);
+            u16_le(&mut directory, VERSION);
+            u16_le(&mut directory, VERSION);
+            u16_le(&mut directory, FLAG_UTF8);
+            u16_le(&mut directory, entry.method as u16);
+            u16_le(&mut directory, DOS_TIME);
+            u16_le(&mut directory, DOS_DATE);
+            u32_le(&mut directory, entry.crc);
+            u32_le(&mut directory, entry.compressed);
+            u32_le(&mut directory, entry.uncompressed);
+            u16_le(&mut directory, name_len(&entry.name)?);
+            // extra field, comment, disk number, internal and external attributes
+            u16_le(&mut directory, 0);
+            u16_le(&mut directory, 0);
+            u16_le(&mut directory, 0);
+            u16_le(&mut directory, 0);
+            u32_le(&mut directory, 0);
+            u32_le(&mut directory, entry.offset);
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let count = u16::try_from(self.entries.len())
+            .map_err(|_| anyhow!("too many files for a zip archive"))?;
+
+        let directory_len = size(directory.len())?;
+
+        u32_le(&mut directory, END_OF_CENTRAL);
+        u16_le(&mut directory, 0);
+        u16_le(&mut directory, 0);
+        u16_le(&mut directory, count);
+        u16_le(&mut directory, count);
+        u32_le(&mut directory, directory_len);
+        u32_le(&mut directory, start);
+        u16_le(&mut directory, 0);
+
+        self.write(&directory)?;
+
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes)?;
+
+        self.offset = self
+            .offset
+            .checked_add(size(bytes.len())?)
+            .ok_or_else(|| anyhow!("zip archive is over 4GB"))?;
+
+        Ok(())
+    }
+}
+
+fn size(len: usize) -> Result<u32> {
+    u32::try_from(len).map_err(|_| anyhow!("zip archive is over 4GB"))
+}
+
+fn name_len(name: &str) -> Result<u16> {
+    u16::try_from(name.len()).map_err(|_| anyhow!("file name `{}` is too long", name))
+}
+
+fn u16_le(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn u32_le(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
@@ -18,6 +18,24 @@ pub fn parse_info(doc: &Document<'_>) -> ParsedInfo {
     #[query::selector]
     static META_SUMMARY: &str = "html > body > #preface > .meta > blockquote.userstuff";
 
+    /// Selects the headings of the stories summary and notes
+    #[query::selector]
+    static META_HEADINGS: &str = "html > body > #preface > .meta > p";
+
+    /// Selects the notes at the end of the story
+    #[query::selector]
+    static AFTERWORD_NOTES: &str = "html > body > #afterword > .meta > #endnotes > blockquote";
+
+    /// Selects the links to the archive and the work on it
+    #[query::selector]
+    static MESSAGE_LINKS: &str = "html > body > #preface > p.message > a";
+
+    #[query::selector]
+    static META_TAGS_DT: &str = "html > body > #preface > .meta > .tags > dt";
+
+    #[query::selector]
+    static META_TAGS_DF: &str = "html > body > #preface > .meta > .tags > dd";
+
     let title = doc
         .select(&META_TITLE)
         .and_then(Node::into_text)
@@ -60,13 +78,61 @@ pub fn parse_info(doc: &Document<'_>) -> ParsedInfo {
         }
     };
 
+    let start_notes = doc
+        .select_all(&META_HEADINGS)
+        .into_iter()
+        .zip(doc.select_all(&META_SUMMARY))
+        .find(|(p, _)| p.get_text() == Some("Notes"))
+        .and_then(|(_, blockquote)| blockquote.get_span_of_children(doc.input()))
+        .map(span_as_range);
+
+    let end_notes = doc
+        .select(&AFTERWORD_NOTES)
+        .and_then(|node| node.get_span_of_children(doc.input()))
+        .map(span_as_range);
+
+    let source = doc
+        .select_all(&MESSAGE_LINKS)
+        .into_iter()
+        .filter_map(|node| node.get_attribute("href"))
+        .find(|href| crate::site::work_id(href).is_some())
+        .map(String::from);
+
+    let posted = doc
+        .select_all(&META_TAGS_DT)
+        .into_iter()
+        .zip(doc.select_all(&META_TAGS_DF))
+        .find(|(dt, _)| dt.get_text().map(str::trim) == Some("Stats:"))
+        .and_then(|(_, dd)| dd.get_text())
+        .and_then(parse_posted);
+
     ParsedInfo {
         title,
         authors,
         summary,
+        start_notes,
+        end_notes,
+        source,
+        posted,
     }
 }
 
+/// Gets the last time the work was posted to from its stats, which are lines like `Published: 2019-01-15`.
+fn parse_posted(stats: &str) -> Option<String> {
+    let stat = |name: &str| {
+        stats
+            .lines()
+            .filter_map(|line| line.trim().split_once(':'))
+            .find(|(key, _)| key.trim() == name)
+            .map(|(_, value)| value.trim().to_string())
+    };
+
+    // a finished work says `Completed` instead of `Updated`
+    stat("Updated")
+        .or_else(|| stat("Completed"))
+        .or_else(|| stat("Published"))
+}
+
 #[inline(never)]
 pub fn parse_meta(doc: &Document<'_>) -> ParsedMeta {
     #[query::selector]
@@ -174,6 +240,13 @@ fn parse_chapter_single<'input>(
 
     match doc.select(&CHAPTER) {
         Some(chapter) => {
+            let content = chapter
+                .get_span_of_children(doc.input())
+                .map(span_as_range)
+                .ok_or_else(|| {
+                    anyhow!("Parser was unable to find chapter content for single chapter story")
+                })?;
+
             // TODO: Do people have single chapter stories that have chapter summaries
             // let summary = toc_heading
             //     .get_child_by_tag("div")
@@ -188,14 +261,8 @@ fn parse_chapter_single<'input>(
                 })?,
                 summary: None,
                 start_notes: None,
-                content: chapter
-                    .get_span_of_children(doc.input())
-                    .map(span_as_range)
-                    .ok_or_else(|| {
-                        anyhow!(
-                            "Parser was unable to find chapter content for single chapter story"
-                        )
-                    })?,
+                words: count_words(&doc.input()[content.clone()]),
+                content,
                 end_notes: None,
             }])
         }
@@ -215,12 +282,13 @@ struct MultiState {
 
 impl MultiState {
     #[inline]
-    fn build(&mut self) -> Option<ParsedChapter> {
+    fn build(&mut self, input: &str) -> Option<ParsedChapter> {
         self.title.take().and_then(|title| {
             self.content.take().map(|content| ParsedChapter {
                 title,
                 summary: self.summary.take(),
                 start_notes: self.start_notes.take(),
+                words: count_words(&input[content.clone()]),
                 content,
                 end_notes: self.end_notes.take(),
             })
@@ -252,7 +320,7 @@ fn parse_chapters_multi<'input>(
                 match classes {
                     // chapter details
                     "meta group" => {
-                        if let Some(chapter) = state.build() {
+                        if let Some(chapter) = state.build(doc.input()) {
                             chapters.push(chapter);
                         }
 
@@ -306,13 +374,40 @@ fn parse_chapters_multi<'input>(
             },
         )?;
 
-    if let Some(chapter) = state.build() {
+    if let Some(chapter) = state.build(doc.input()) {
         chapters.push(chapter);
     }
 
     Ok(chapters)
 }
 
+/// Counts the words in a chunk of HTML, tags are treated as breaks between words.
+fn count_words(html: &str) -> usize {
+    let mut words = 0;
+    let mut in_tag = false;
+    let mut in_word = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                in_word = false;
+            }
+            '>' if in_tag => in_tag = false,
+            _ if in_tag => {}
+            c if c.is_whitespace() => in_word = false,
+            _ => {
+                if !in_word {
+                    words += 1;
+                    in_word = true;
+                }
+            }
+        }
+    }
+
+    words
+}
+
 #[inline]
 fn span_as_range(span: Span<'_>) -> Range<usize> {
     let start = span.start();
@@ -3,6 +3,7 @@ mod tests;
 
 pub mod epub;
 pub mod html;
+pub mod site;
 
 use {
     common::{
@@ -20,6 +21,12 @@ pub struct ParsedInfo {
     pub title: String,
     pub authors: Vec<String>,
     pub summary: String,
+    pub start_notes: Option<Range<usize>>,
+    pub end_notes: Option<Range<usize>>,
+    /// The link to the work on the archive.
+    pub source: Option<String>,
+    /// When a chapter was last posted, or when the work was published if it never has been, as `YYYY-MM-DD`.
+    pub posted: Option<String>,
 }
 
 #[derive(PartialEq)]
@@ -51,6 +58,7 @@ pub struct ParsedChapter {
     pub start_notes: Option<Range<usize>>,
     pub content: Range<usize>,
     pub end_notes: Option<Range<usize>>,
+    pub words: usize,
 }
 
 pub fn parse_epub(path: &Path) -> Result<(ParsedInfo, ParsedMeta, ParsedChapters)> {
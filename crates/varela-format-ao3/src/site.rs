@@ -0,0 +1,156 @@
+//! Finding works on the archive itself, rather than reading downloaded ones.
+
+/// Where the archive is, the functions that make links take it so they can be pointed elsewhere in tests.
+pub const ARCHIVE: &str = "https://archiveofourown.org";
+
+/// The hosts the archive answers on, all of them serve the same works.
+const HOSTS: &[&str] = &[
+    "archiveofourown.org",
+    "www.archiveofourown.org",
+    "download.archiveofourown.org",
+    "ao3.org",
+    "www.ao3.org",
+];
+
+/// Gets the work ID out of any link to a work, one of its chapters, or one of its downloads.
+///
+/// Chapter links without the work in them, like `/chapters/123`, can't be resolved without asking the archive,
+/// see [`chapter_id`].
+pub fn work_id(url: &str) -> Option<&str> {
+    find_id(archive_path(url)?, "works").or_else(|| find_id(archive_path(url)?, "downloads"))
+}
+
+/// Gets the chapter ID out of a link to a chapter that doesn't have its work in it, like `/chapters/123`.
+pub fn chapter_id(url: &str) -> Option<&str> {
+    let path = archive_path(url)?;
+
+    if find_id(path, "works").is_some() {
+        return None;
+    }
+
+    find_id(path, "chapters")
+}
+
+/// Gets the work ID out of where a request to [`chapter_url`] ended up, after the archive redirected it.
+pub fn redirected_work_id<'u>(archive: &str, url: &'u str) -> Option<&'u str> {
+    match url.strip_prefix(archive) {
+        Some(path) => find_id(path.split(['?', '#']).next().unwrap_or_default(), "works"),
+        None => work_id(url),
+    }
+}
+
+/// Checks if the link is to the archive, whether or not it's to a work.
+pub fn is_archive(url: &str) -> bool {
+    archive_path(url).is_some()
+}
+
+/// The path of a link to one of the archive's hosts, without the query or fragment.
+fn archive_path(url: &str) -> Option<&str> {
+    let rest = url.trim();
+    let rest = rest
+        .strip_prefix("https://")
+        .or_else(|| rest.strip_prefix("http://"))
+        .unwrap_or(rest);
+
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+
+    if !HOSTS.iter().any(|known| host.eq_ignore_ascii_case(known)) {
+        return None;
+    }
+
+    path.split(['?', '#']).next()
+}
+
+/// The numeric ID in the path segment after `kind`, like the `123` in `/works/123/chapters/456` for `works`.
+fn find_id<'p>(path: &'p str, kind: &str) -> Option<&'p str> {
+    let mut segments = path.split('/');
+
+    while let Some(segment) = segments.next() {
+        if segment == kind {
+            return segments
+                .next()
+                .filter(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()));
+        }
+    }
+
+    None
+}
+
+/// The work's page, with the adult content warning already agreed to so the page has the work on it.
+pub fn work_url(archive: &str, id: &str) -> String {
+    vfmt::format!("{}/works/{}?view_adult=true", archive, id)
+}
+
+/// The chapter's page, which the archive redirects to the chapter's place in its work.
+pub fn chapter_url(archive: &str, id: &str) -> String {
+    vfmt::format!("{}/chapters/{}?view_adult=true", archive, id)
+}
+
+/// Finds the link to download the work in the given format (like `html` or `epub`) on its page.
+///
+/// Works only shown to logged in users don't have any, the page is a login form instead.
+pub fn export_link(archive: &str, page: &str, ext: &str) -> Option<String> {
+    let suffix = vfmt::format!(".{}", ext);
+
+    page.split("href=\"").skip(1).find_map(|rest| {
+        let href = rest.split('"').next()?;
+        let path = href.split('?').next()?;
+
+        if path.starts_with("/downloads/") && path.ends_with(&suffix) {
+            Some(vfmt::format!("{}{}", archive, href.replace("&amp;", "&")))
+        } else {
+            None
+        }
+    })
+}
+
+#[derive(PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct WorkStats {
+    /// How many chapters have been posted so far.
+    pub chapters: usize,
+    /// When a chapter was last posted, or when the work was published if it never has been, as `YYYY-MM-DD`.
+    pub updated: String,
+}
+
+/// Finds the chapter count and last update in the stats on the work's page.
+pub fn work_stats(page: &str) -> Option<WorkStats> {
+    let stat = |class: &str| {
+        let start = vfmt::format!("<dd class=\"{}\">", class);
+
+        let rest = &page[page.find(&start)? + start.len()..];
+        let value = &rest[..rest.find("</dd>")?];
+
+        Some(strip_tags(value).trim().to_string())
+    };
+
+    // the count of posted chapters links to the latest one, like `<a href="...">2</a>/5`
+    let chapters = stat("chapters")?
+        .split('/')
+        .next()?
+        .trim()
+        .replace(',', "")
+        .parse()
+        .ok()?;
+
+    // the `status` stat is either when it was updated or completed, it's missing for works that have been neither
+    let updated = stat("status").or_else(|| stat("published"))?;
+
+    Some(WorkStats { chapters, updated })
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text
+}
@@ -18,6 +18,10 @@ fn test_parse_info_multiple() {
         title: "Disrupt".to_string(),
         authors: vec!["testy".to_string()],
         summary: "<p>Unicorn et Retro adipisicing yr, nulla disrupt laboris austin.</p>\n<p> </p>\n<p>  <b>Please do not delete! We may need this for further download testing.</b></p>".to_string(),
+        start_notes: Some(2268..2483),
+        end_notes: Some(42118..42131),
+        source: Some("http://archiveofourown.org/works/17434637".to_string()),
+        posted: Some("2019-01-15".to_string()),
     };
     let right = parse_info(&doc);
 
@@ -53,6 +57,7 @@ fn test_parse_chapters_multiple() {
             start_notes: Some(3112..3349),
             content: 3541..19287,
             end_notes: Some(19419..19430),
+            words: 2149,
         },
         ParsedChapter {
             title: "Try-hard Brunch".to_string(),
@@ -60,6 +65,7 @@ fn test_parse_chapters_multiple() {
             start_notes: None,
             content: 19837..41637,
             end_notes: Some(41769..41905),
+            words: 3032,
         },
     ] };
     let right = parse_chapters(&doc).unwrap();
@@ -77,6 +83,10 @@ fn test_parse_info_single() {
         title: "A Work To Test Downloads Again".to_string(),
         authors: vec!["testy".to_string()],
         summary: "<p>This is a new work for a test.</p>".to_string(),
+        start_notes: Some(2053..2095),
+        end_notes: None,
+        source: Some("http://archiveofourown.org/works/12336681".to_string()),
+        posted: Some("2017-10-11".to_string()),
     };
     let right = parse_info(&doc);
 
@@ -112,6 +122,7 @@ fn test_parse_chapters_single() {
             start_notes: None,
             content: 2265..18822,
             end_notes: None,
+            words: 2266,
         }],
     };
     let right = parse_chapters(&doc).unwrap();
@@ -1,2 +1,3 @@
 #[cfg(feature = "std")]
 pub mod html;
+pub mod site;
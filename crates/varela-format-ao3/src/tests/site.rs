@@ -0,0 +1,177 @@
+use crate::site::{
+    chapter_id, chapter_url, export_link, is_archive, redirected_work_id, work_id, work_stats,
+    work_url, WorkStats, ARCHIVE,
+};
+
+#[test]
+fn test_work_id() {
+    assert_eq!(
+        Some("36141154"),
+        work_id("https://archiveofourown.org/works/36141154")
+    );
+    assert_eq!(
+        Some("36141154"),
+        work_id("http://www.archiveofourown.org/works/36141154/chapters/90082141#workskin")
+    );
+    assert_eq!(
+        Some("36141154"),
+        work_id("archiveofourown.org/collections/test/works/36141154?view_adult=true")
+    );
+    assert_eq!(
+        Some("36141154"),
+        work_id(
+            "https://download.archiveofourown.org/downloads/36141154/Disrupt.html?updated_at=1"
+        )
+    );
+    assert_eq!(Some("1"), work_id("https://ao3.org/works/1"));
+
+    assert_eq!(
+        None,
+        work_id("https://archiveofourown.org/chapters/90082141")
+    );
+    assert_eq!(None, work_id("https://archiveofourown.org/works/new"));
+    assert_eq!(None, work_id("https://example.com/works/36141154"));
+    assert_eq!(
+        None,
+        work_id("https://archiveofourown.org.example.com/works/1")
+    );
+}
+
+#[test]
+fn test_chapter_id() {
+    assert_eq!(
+        Some("90082141"),
+        chapter_id("https://archiveofourown.org/chapters/90082141")
+    );
+    assert_eq!(
+        Some("90082141"),
+        chapter_id("www.ao3.org/chapters/90082141?view_adult=true#workskin")
+    );
+
+    assert_eq!(
+        None,
+        chapter_id("https://archiveofourown.org/works/36141154/chapters/90082141")
+    );
+    assert_eq!(None, chapter_id("https://archiveofourown.org/chapters/new"));
+    assert_eq!(None, chapter_id("https://example.com/chapters/90082141"));
+}
+
+#[test]
+fn test_chapter_url() {
+    assert_eq!(
+        "https://archiveofourown.org/chapters/1?view_adult=true",
+        chapter_url(ARCHIVE, "1")
+    );
+}
+
+#[test]
+fn test_redirected_work_id() {
+    assert_eq!(
+        Some("36141154"),
+        redirected_work_id(
+            ARCHIVE,
+            "https://archiveofourown.org/works/36141154/chapters/90082141?view_adult=true"
+        )
+    );
+    assert_eq!(
+        Some("36141154"),
+        redirected_work_id(
+            "http://127.0.0.1:8080",
+            "http://127.0.0.1:8080/works/36141154/chapters/90082141"
+        )
+    );
+    assert_eq!(
+        Some("36141154"),
+        redirected_work_id(ARCHIVE, "https://ao3.org/works/36141154/chapters/90082141")
+    );
+
+    assert_eq!(
+        None,
+        redirected_work_id(ARCHIVE, "https://archiveofourown.org/users/login")
+    );
+}
+
+#[test]
+fn test_is_archive() {
+    assert!(is_archive("https://archiveofourown.org/works/new"));
+    assert!(is_archive("ao3.org"));
+
+    assert!(!is_archive("https://example.com/works/36141154"));
+}
+
+#[test]
+fn test_work_url() {
+    assert_eq!(
+        "https://archiveofourown.org/works/1?view_adult=true",
+        work_url(ARCHIVE, "1")
+    );
+    assert_eq!(
+        "http://127.0.0.1:8080/works/1?view_adult=true",
+        work_url("http://127.0.0.1:8080", "1")
+    );
+}
+
+#[test]
+fn test_export_link() {
+    let page = r##"<li class="download" aria-haspopup="true">
+  <a href="#">Download</a>
+  <ul class="expandable secondary">
+    <li><a href="/downloads/36141154/Disrupt.azw3?updated_at=1640000000">AZW3</a></li>
+    <li><a href="/downloads/36141154/Disrupt.epub?updated_at=1640000000">EPUB</a></li>
+    <li><a href="/downloads/36141154/Disrupt.html?updated_at=1640000000&amp;x=1">HTML</a></li>
+  </ul>
+</li>"##;
+
+    assert_eq!(
+        Some(
+            "https://archiveofourown.org/downloads/36141154/Disrupt.html?updated_at=1640000000&x=1"
+                .to_string()
+        ),
+        export_link(ARCHIVE, page, "html")
+    );
+    assert_eq!(
+        Some(
+            "https://archiveofourown.org/downloads/36141154/Disrupt.epub?updated_at=1640000000"
+                .to_string()
+        ),
+        export_link(ARCHIVE, page, "epub")
+    );
+    assert_eq!(None, export_link(ARCHIVE, page, "pdf"));
+    assert_eq!(
+        None,
+        export_link(ARCHIVE, "<form action=\"/users/login\"></form>", "html")
+    );
+}
+
+#[test]
+fn test_work_stats() {
+    let page = r##"<dl class="stats">
+  <dt class="published">Published:</dt><dd class="published">2019-01-15</dd>
+  <dt class="status">Updated:</dt><dd class="status">2019-02-01</dd>
+  <dt class="words">Words:</dt><dd class="words">5,193</dd>
+  <dt class="chapters">Chapters:</dt><dd class="chapters"><a href="/works/17434637/chapters/2">2</a>/5</dd>
+</dl>"##;
+
+    assert_eq!(
+        Some(WorkStats {
+            chapters: 2,
+            updated: "2019-02-01".to_string(),
+        }),
+        work_stats(page)
+    );
+
+    let page = r##"<dl class="stats">
+  <dt class="published">Published:</dt><dd class="published">2017-10-11</dd>
+  <dt class="chapters">Chapters:</dt><dd class="chapters">1/1</dd>
+</dl>"##;
+
+    assert_eq!(
+        Some(WorkStats {
+            chapters: 1,
+            updated: "2017-10-11".to_string(),
+        }),
+        work_stats(page)
+    );
+
+    assert_eq!(None, work_stats("<form action=\"/users/login\"></form>"));
+}
@@ -22,12 +22,18 @@ fn main() -> Result<()> {
             vfmt::println!();
             vfmt::println!("Commands:");
             vfmt::println!("  config          access and change the config");
+            vfmt::println!(
+                "  export          writes a story out as an epub, html, markdown, or text file"
+            );
             vfmt::println!("  index           builds or updates the index");
             vfmt::println!("  serve           run the internal web server [default]");
         }
         Some("config") => {
             command_config::run(args)?;
         }
+        Some("export") => {
+            command_export::run(args)?;
+        }
         Some("index") => {
             command_index::run(args)?;
         }